@@ -0,0 +1,57 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use flux::mc::commands::SummonCommand;
+use flux::mc::entity::meta::{ArmorStand, Equipment, HandItems};
+use flux::nbt::{BinaryNbtWriter, NbtTag, NbtWriter};
+use flux::prelude::*;
+
+/// Armor stand with full equipment, carrying a stack of riders that each carry two more
+fn nested_meta() -> EntityMeta {
+    let rider = ArmorStand::new()
+        .hand_items(HandItems::new(
+            Some(Material::NetheriteSword.stack()),
+            Some(Material::Shield.stack()),
+        ))
+        .equipment(Equipment::new(
+            Some(Material::NetheriteBoots.stack()),
+            Some(Material::NetheriteLeggings.stack()),
+            Some(Material::NetheriteChestplate.stack()),
+            Some(Material::NetheriteHelmet.stack()),
+        ))
+        .show_arms(true);
+    let passengers = (0..16)
+        .map(|_| {
+            EntityMeta::ArmorStand(rider.clone().passengers(vec![
+                EntityMeta::ArmorStand(rider.clone()),
+                EntityMeta::ArmorStand(rider.clone()),
+            ]))
+        })
+        .collect::<Vec<EntityMeta>>();
+    EntityMeta::ArmorStand(rider.clone().passengers(passengers))
+}
+
+fn summon_commands(c: &mut Criterion) {
+    let meta = nested_meta();
+    let mut cmd = SummonCommand::new(
+        EntityType::ArmorStand,
+        Some("~ ~ ~".into()),
+        Some(meta.clone()),
+    );
+    c.bench_function("compile nested summon", |b| {
+        b.iter(|| black_box(cmd.compile().unwrap()))
+    });
+
+    let tag: NbtTag = meta.into();
+    let mut buf = vec![];
+    c.bench_function("write nested meta as binary", |b| {
+        b.iter(|| {
+            buf.clear();
+            BinaryNbtWriter::new(&mut buf)
+                .write_tag(None, black_box(&tag))
+                .unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, summon_commands);
+criterion_main!(benches);
@@ -1,9 +1,17 @@
-use crate::nbt::NbtTag;
+use crate::nbt::{NbtTag, ToTag};
 use lobsterchat::component::Component;
 pub use lobsterchat::*;
 
+pub mod text;
+
 impl Into<NbtTag> for Component {
     fn into(self) -> NbtTag {
         NbtTag::String(self.to_string())
     }
 }
+
+impl ToTag for Component {
+    fn to_tag(&self) -> NbtTag {
+        NbtTag::String(self.to_string())
+    }
+}
@@ -0,0 +1,392 @@
+//! Typed text components, that are written in the format the target version expects.
+//!
+//! Before 1.20.3 text components are stored as JSON inside string tags,
+//! while since 1.20.3 commands accept them as structured NBT compounds.
+//! Hover events are written in the `contents` format, introduced in 1.16.
+
+use anyhow::bail;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use uuid::Uuid;
+
+use crate::mc::block::Location;
+use crate::modules::MinecraftVersion;
+use crate::nbt::error::NbtResult;
+use crate::nbt::ser::to_nbt;
+use crate::nbt::NbtTag;
+use crate::utils::Keybind;
+
+/// Color of a text component, either one of the 16 named colors or a hex color
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TextColor {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+    /// Hex color, only supported since 1.16
+    Rgb(u8, u8, u8),
+}
+
+impl ToString for TextColor {
+    fn to_string(&self) -> String {
+        String::from(match *self {
+            TextColor::Black => "black",
+            TextColor::DarkBlue => "dark_blue",
+            TextColor::DarkGreen => "dark_green",
+            TextColor::DarkAqua => "dark_aqua",
+            TextColor::DarkRed => "dark_red",
+            TextColor::DarkPurple => "dark_purple",
+            TextColor::Gold => "gold",
+            TextColor::Gray => "gray",
+            TextColor::DarkGray => "dark_gray",
+            TextColor::Blue => "blue",
+            TextColor::Green => "green",
+            TextColor::Aqua => "aqua",
+            TextColor::Red => "red",
+            TextColor::LightPurple => "light_purple",
+            TextColor::Yellow => "yellow",
+            TextColor::White => "white",
+            TextColor::Rgb(r, g, b) => return format!("#{:02X}{:02X}{:02X}", r, g, b),
+        })
+    }
+}
+
+impl Serialize for TextColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Action performed when the text is clicked
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClickEvent {
+    OpenUrl(String),
+    RunCommand(String),
+    SuggestCommand(String),
+    /// Only works in books
+    ChangePage(u32),
+    CopyToClipboard(String),
+}
+
+impl Serialize for ClickEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (action, value) = match self {
+            ClickEvent::OpenUrl(url) => ("open_url", url.clone()),
+            ClickEvent::RunCommand(cmd) => ("run_command", cmd.clone()),
+            ClickEvent::SuggestCommand(cmd) => ("suggest_command", cmd.clone()),
+            // pages are strings in both formats
+            ClickEvent::ChangePage(page) => ("change_page", page.to_string()),
+            ClickEvent::CopyToClipboard(text) => ("copy_to_clipboard", text.clone()),
+        };
+        let mut event = serializer.serialize_struct("ClickEvent", 2)?;
+        event.serialize_field("action", action)?;
+        event.serialize_field("value", &value)?;
+        event.end()
+    }
+}
+
+/// Tooltip shown when the text is hovered
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", content = "contents", rename_all = "snake_case")]
+pub enum HoverEvent {
+    ShowText(Box<TextComponent>),
+    ShowItem {
+        id: String,
+        count: i32,
+        /// Stringified NBT of the item
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+    },
+    ShowEntity {
+        #[serde(rename = "type")]
+        entity_type: String,
+        id: Uuid,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<Box<TextComponent>>,
+    },
+}
+
+/// Where an NBT component takes its NBT from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NbtSource {
+    /// Block entity at the coordinates
+    Block(String),
+    /// Entities matched by the selector
+    Entity(String),
+    /// Command storage with the id
+    Storage(String),
+}
+
+impl NbtSource {
+    pub fn block(at: Location) -> Self {
+        NbtSource::Block(at.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Score {
+    name: String,
+    objective: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+enum TextContent {
+    Text {
+        text: String,
+    },
+    Translatable {
+        translate: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fallback: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        with: Vec<TextComponent>,
+    },
+    Score {
+        score: Score,
+    },
+    Selector {
+        selector: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        separator: Option<Box<TextComponent>>,
+    },
+    Keybind {
+        keybind: String,
+    },
+    Nbt {
+        nbt: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        interpret: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        separator: Option<Box<TextComponent>>,
+        #[serde(flatten)]
+        source: NbtSource,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+struct Style {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<TextColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    font: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bold: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    italic: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    underlined: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strikethrough: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    obfuscated: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    insertion: Option<String>,
+    #[serde(rename = "clickEvent", skip_serializing_if = "Option::is_none")]
+    click_event: Option<ClickEvent>,
+    #[serde(rename = "hoverEvent", skip_serializing_if = "Option::is_none")]
+    hover_event: Option<HoverEvent>,
+}
+
+/// Text component with its style and children
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TextComponent {
+    #[serde(flatten)]
+    content: TextContent,
+    #[serde(flatten)]
+    style: Style,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extra: Vec<TextComponent>,
+}
+
+impl TextComponent {
+    fn new(content: TextContent) -> Self {
+        Self {
+            content,
+            style: Style::default(),
+            extra: vec![],
+        }
+    }
+
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        Self::new(TextContent::Text { text: text.into() })
+    }
+
+    /// Translated text, with the arguments inserted in place of `%s` and `%1$s`
+    pub fn translatable<S: Into<String>>(key: S, with: Vec<TextComponent>) -> Self {
+        Self::new(TextContent::Translatable {
+            translate: key.into(),
+            fallback: None,
+            with,
+        })
+    }
+
+    /// Score of the holder in the objective, where the holder is a player name or a selector
+    /// that matches a single entity
+    pub fn score<N: Into<String>, O: Into<String>>(name: N, objective: O) -> Self {
+        Self::new(TextContent::Score {
+            score: Score {
+                name: name.into(),
+                objective: objective.into(),
+            },
+        })
+    }
+
+    /// Names of all entities matched by the selector
+    pub fn selector<S: Into<String>>(selector: S) -> Self {
+        Self::new(TextContent::Selector {
+            selector: selector.into(),
+            separator: None,
+        })
+    }
+
+    /// Key bound to the action on the client, e.g. `Space` for [Keybind::Jump]
+    pub fn keybind(key: Keybind) -> Self {
+        Self::new(TextContent::Keybind { keybind: key.key() })
+    }
+
+    /// Values at the NBT path of the source
+    pub fn nbt<S: Into<String>>(path: S, source: NbtSource) -> Self {
+        Self::new(TextContent::Nbt {
+            nbt: path.into(),
+            interpret: None,
+            separator: None,
+            source,
+        })
+    }
+
+    /// Text shown by translatable components if the key is not translated, since 1.19.4.
+    /// Fails for other kinds of components
+    pub fn fallback<S: Into<String>>(&mut self, fallback: S) -> anyhow::Result<Self> {
+        match &mut self.content {
+            TextContent::Translatable { fallback: f, .. } => *f = Some(fallback.into()),
+            _ => bail!("Only translatable components can have a fallback"),
+        }
+        Ok(self.clone())
+    }
+
+    /// Separator between the values of selector and NBT components.
+    /// Fails for other kinds of components
+    pub fn separator(&mut self, separator: TextComponent) -> anyhow::Result<Self> {
+        match &mut self.content {
+            TextContent::Selector { separator: s, .. } | TextContent::Nbt { separator: s, .. } => {
+                *s = Some(Box::new(separator))
+            }
+            _ => bail!("Only selector and NBT components can have a separator"),
+        }
+        Ok(self.clone())
+    }
+
+    /// Makes NBT components parse their values as text components.
+    /// Fails for other kinds of components
+    pub fn interpret(&mut self, interpret: bool) -> anyhow::Result<Self> {
+        match &mut self.content {
+            TextContent::Nbt { interpret: i, .. } => *i = Some(interpret),
+            _ => bail!("Only NBT components can be interpreted"),
+        }
+        Ok(self.clone())
+    }
+
+    pub fn color(&mut self, color: TextColor) -> Self {
+        self.style.color = Some(color);
+        self.clone()
+    }
+
+    /// Resource location of the font, e.g. `minecraft:uniform`
+    pub fn font<S: Into<String>>(&mut self, font: S) -> Self {
+        self.style.font = Some(font.into());
+        self.clone()
+    }
+
+    pub fn bold(&mut self, bold: bool) -> Self {
+        self.style.bold = Some(bold);
+        self.clone()
+    }
+
+    pub fn italic(&mut self, italic: bool) -> Self {
+        self.style.italic = Some(italic);
+        self.clone()
+    }
+
+    pub fn underlined(&mut self, underlined: bool) -> Self {
+        self.style.underlined = Some(underlined);
+        self.clone()
+    }
+
+    pub fn strikethrough(&mut self, strikethrough: bool) -> Self {
+        self.style.strikethrough = Some(strikethrough);
+        self.clone()
+    }
+
+    pub fn obfuscated(&mut self, obfuscated: bool) -> Self {
+        self.style.obfuscated = Some(obfuscated);
+        self.clone()
+    }
+
+    /// Text inserted into the chat input when the component is shift-clicked
+    pub fn insertion<S: Into<String>>(&mut self, insertion: S) -> Self {
+        self.style.insertion = Some(insertion.into());
+        self.clone()
+    }
+
+    pub fn click(&mut self, event: ClickEvent) -> Self {
+        self.style.click_event = Some(event);
+        self.clone()
+    }
+
+    pub fn hover(&mut self, event: HoverEvent) -> Self {
+        self.style.hover_event = Some(event);
+        self.clone()
+    }
+
+    /// Adds a child component, which inherits the style of this one
+    pub fn append(&mut self, child: TextComponent) -> Self {
+        self.extra.push(child);
+        self.clone()
+    }
+
+    /// Writes this component as JSON, the format used before 1.20.3
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Text components are always valid JSON")
+    }
+
+    /// Writes only the style of this component as JSON, e.g. for styled number formats
+    pub fn style_json(&self) -> String {
+        serde_json::to_string(&self.style).expect("Text styles are always valid JSON")
+    }
+
+    /// Converts this component into a compound, the format used since 1.20.3
+    pub fn to_nbt(&self) -> NbtResult<NbtTag> {
+        to_nbt(self)
+    }
+
+    /// Converts this component into the tag the version expects: a compound since 1.20.3,
+    /// or a string with JSON before it
+    pub fn to_tag(&self, version: MinecraftVersion) -> NbtResult<NbtTag> {
+        if version.nbt_text_components() {
+            self.to_nbt()
+        } else {
+            Ok(NbtTag::String(self.to_json()))
+        }
+    }
+}
+
+impl Into<NbtTag> for TextComponent {
+    /// Converts this component into a JSON string, which is still used for names and lore
+    fn into(self) -> NbtTag {
+        NbtTag::String(self.to_json())
+    }
+}
@@ -16,8 +16,23 @@ mod tests {
     use std::fs::File;
 
     use crate::chat::component::{Component, NamedColor};
-    use crate::mc::commands::{GiveCommand, SummonCommand};
+    use crate::chat::text::{ClickEvent, HoverEvent, NbtSource, TextColor, TextComponent};
+    use crate::mc::commands::block::{
+        CloneCommand, CloneMask, CloneMode, FillCommand, FillMode, PlaceCommand, SetBlockCommand,
+        SetBlockMode, StructureMirror, StructureRotation,
+    };
+    use crate::mc::commands::execute::{
+        Anchor, Comparison, ExecuteCommand, ExecuteCondition, IntRange, StoreTarget, StoreType,
+    };
+    use crate::mc::commands::{
+        AttributeAction, AttributeCommand, DamageCommand, DamageSource, EffectClearCommand,
+        EffectDuration, EffectGiveCommand, EnchantCommand, GiveCommand, KillCommand, ModifierId,
+        RideAction, RideCommand, SummonCommand, TagAction, TagCommand, TeamJoinCommand, Teleport,
+        TeleportCommand,
+    };
+    use crate::mc::data::{DataAccess, DataCommand, DataSource, DataTarget, ModifyOperation};
     use crate::mc::enchant::{Enchant, Enchantment};
+    use crate::mc::entity::effect::Effect;
     use crate::mc::entity::meta::{
         ArmorStand, EntityRotation, Equipment, GeneralZombie, HandItems, StandPose,
     };
@@ -29,16 +44,37 @@ mod tests {
         DefaultMeta, SkullData, SkullMeta, SkullOwner, FLAG_HIDE_ATTRIBUTES, FLAG_HIDE_DESTROY,
         FLAG_HIDE_DYED, FLAG_HIDE_ENCHANTMENTS, FLAG_HIDE_PLACE, FLAG_HIDE_UNBREAKABLE,
     };
+    use crate::mc::region::{Chunk, RegionFile};
+    use crate::mc::schematic::Schematic;
+    use crate::mc::scoreboard::{
+        Criteria, DisplaySlot, NumberFormat, Objective, Operation, ScoreHolder, Scoreboard,
+        StatType,
+    };
+    use crate::mc::structure::Structure;
     use crate::mc::world::WorldAccess;
     use crate::modules::functions::FunctionWriter;
-    use crate::modules::{Module, GLOBAL_MODULE_LOADER};
+    use crate::modules::{MinecraftVersion, Module, GLOBAL_MODULE_LOADER};
+    use crate::nbt::compression::{CompressedNbtReader, CompressedNbtWriter, NbtCompression};
+    use crate::nbt::de::{from_binary, from_nbt, from_snbt};
+    use crate::nbt::diff::diff;
+    use crate::nbt::error::NbtError;
+    use crate::nbt::json::{from_json, to_json, NumberPolicy};
+    use crate::nbt::mutf8;
+    use crate::nbt::path::NbtPath;
+    use crate::nbt::ser::{to_binary, to_nbt, to_snbt};
+    use crate::nbt::{
+        BinaryNbtReader, BinaryNbtWriter, Compound, NbtEncoding, NbtReader, NbtTag, NbtWriter,
+    };
     use crate::prelude::*;
-    use crate::utils::{Keybind, Vec3F};
+    use crate::snbt::{SnbtOptions, StringNbtWriter};
+    use crate::utils::{Keybind, Vec3D, Vec3F, Vec3I};
     use crate::ExampleModule;
     use lobsterchat::lobster;
+    use serde::{Deserialize, Serialize};
     use std::str::FromStr;
     use std::sync::{Arc, Mutex};
     use std::time::Instant;
+    use uuid::Uuid;
 
     #[test]
     fn test_items() {
@@ -54,7 +90,7 @@ mod tests {
         );
         meta.display(display);
         item.meta(ItemMeta::Default(meta));
-        println!("{}", item.stringified());
+        println!("{}", item.stringified().unwrap());
     }
 
     #[test]
@@ -102,7 +138,7 @@ mod tests {
         ));
 
         let mut cmd = GiveCommand::new("@p", item);
-        println!("{}", cmd.compile())
+        println!("{}", cmd.compile().unwrap())
     }
 
     #[test]
@@ -136,7 +172,498 @@ mod tests {
         );
         let diff = (Instant::now() - time).as_micros();
         println!("Took {}mcs", diff);
-        println!("{}", cmd.compile())
+        println!("{}", cmd.compile().unwrap())
+    }
+
+    #[test]
+    fn test_execute_command() -> anyhow::Result<()> {
+        let mut cmd = ExecuteCommand::new()
+            .as_(Selector::AllPlayers)
+            .at(Selector::Executor)
+            .anchored(Anchor::Eyes)
+            .align("xz")?
+            .in_(Identifier::minecraft("the_nether"))
+            .if_(ExecuteCondition::block(
+                Location::relative(0, -1, 0),
+                BlockState::from_str("minecraft:oak_stairs[facing=north]")?,
+            ))
+            .unless(ExecuteCondition::score_matches(
+                "@s",
+                "deaths",
+                IntRange::new(Some(1), None)?,
+            ))
+            .if_(ExecuteCondition::data(
+                DataTarget::Storage(Identifier::new("flux", "data")),
+                "players[0].name",
+            )?)
+            .store_result(StoreTarget::data(
+                DataTarget::entity("@s"),
+                "Health",
+                StoreType::Float,
+                0.5,
+            )?)
+            .run(SummonCommand::new(EntityType::Pig, None, None))?;
+        assert_eq!(
+            cmd.compile()?,
+            "execute as @a at @s anchored eyes align xz in minecraft:the_nether \
+            if block ~ ~-1 ~ minecraft:oak_stairs[facing=north] \
+            unless score @s deaths matches 1.. \
+            if data storage flux:data players[0].name \
+            store result entity @s Health float 0.5 run summon minecraft:pig"
+        );
+
+        let mut check = ExecuteCommand::new().if_(ExecuteCondition::Predicate(Identifier::new(
+            "flux", "is_day",
+        )));
+        assert_eq!(check.compile()?, "execute if predicate flux:is_day");
+        assert!(ExecuteCommand::new().as_("@a").compile().is_err());
+
+        let mut compare = ExecuteCommand::new().if_(ExecuteCondition::score_compare(
+            Selector::Executor,
+            "kills",
+            Comparison::GreaterOrEqual,
+            "#max".to_string(),
+            String::from("flux_temporary_values"),
+        ));
+        assert_eq!(
+            compare.compile()?,
+            "execute if score @s kills >= #max flux_temporary_values"
+        );
+        assert!(IntRange::new(None, None).is_err());
+        assert!(IntRange::new(Some(2), Some(1)).is_err());
+        assert!(ExecuteCommand::new().align("").is_err());
+        assert!(ExecuteCommand::new().align("xx").is_err());
+        assert!(ExecuteCommand::new().align("xw").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scoreboard() -> anyhow::Result<()> {
+        let kills = Objective::new(
+            "kills",
+            Criteria::Statistic(StatType::Killed, Identifier::minecraft("zombie")),
+        )
+        .display_name(TextComponent::text("Kills").color(TextColor::Red));
+        let temp = Objective::dummy("flux_temporary_values");
+        let player = ScoreHolder::new(Selector::NearestPlayer);
+
+        assert_eq!(
+            kills.add().compile()?,
+            r#"scoreboard objectives add kills minecraft.killed:minecraft.zombie {"text":"Kills","color":"red"}"#
+        );
+        assert_eq!(
+            kills
+                .set_display(DisplaySlot::BelowName)
+                .command(MinecraftVersion::v1_19)?,
+            "scoreboard objectives setdisplay belowName kills"
+        );
+        assert_eq!(
+            kills.set_display(DisplaySlot::BelowName).compile()?,
+            "scoreboard objectives setdisplay below_name kills"
+        );
+        assert_eq!(
+            kills
+                .operation(
+                    player.clone(),
+                    Operation::Min,
+                    ScoreHolder::new("#max"),
+                    &temp
+                )
+                .compile()?,
+            "scoreboard players operation @p kills < #max flux_temporary_values"
+        );
+        assert_eq!(
+            kills.add_score(ScoreHolder::all(), 1).compile()?,
+            "scoreboard players add * kills 1"
+        );
+        assert!(kills.remove_score(player, -1).compile().is_err());
+
+        // objective names were limited to 16 characters before 1.18
+        let mut scoreboard = Scoreboard::new(
+            MinecraftVersion::v1_17,
+            Arc::new(Mutex::new(FunctionWriter::new(std::io::sink()))),
+        );
+        scoreboard.run(kills.add())?;
+        assert!(scoreboard.run(temp.add()).is_err());
+        assert!(temp.add().command(MinecraftVersion::v1_18).is_ok());
+
+        // RGB colors can not be team colors
+        let rgb = TextColor::Rgb(0x12, 0x34, 0x56);
+        assert!(Objective::new("k", Criteria::TeamKill(rgb))
+            .add()
+            .compile()
+            .is_err());
+        assert!(temp
+            .set_display(DisplaySlot::SidebarTeam(rgb))
+            .compile()
+            .is_err());
+        assert_eq!(
+            temp.set_display(DisplaySlot::SidebarTeam(TextColor::Gold))
+                .compile()?,
+            "scoreboard objectives setdisplay sidebar.team.gold flux_temporary_values"
+        );
+
+        // number formats and holder names were added in 1.20.3
+        assert_eq!(
+            kills.modify_display_auto_update(true).compile()?,
+            "scoreboard objectives modify kills displayautoupdate true"
+        );
+        assert_eq!(
+            kills
+                .modify_number_format(Some(NumberFormat::Styled(
+                    TextComponent::text("").color(TextColor::Red).bold(true)
+                )))
+                .compile()?,
+            r#"scoreboard objectives modify kills numberformat styled {"color":"red","bold":true}"#
+        );
+        assert_eq!(
+            kills.modify_number_format(None).compile()?,
+            "scoreboard objectives modify kills numberformat"
+        );
+        assert_eq!(
+            kills
+                .display_name_of(ScoreHolder::new("#max"), Some(TextComponent::text("Max")))
+                .compile()?,
+            r#"scoreboard players display name #max kills {"text":"Max"}"#
+        );
+        assert_eq!(
+            kills
+                .display_number_format_of(ScoreHolder::all(), Some(NumberFormat::Blank))
+                .compile()?,
+            "scoreboard players display numberformat * kills blank"
+        );
+        assert!(kills
+            .modify_number_format(None)
+            .command(MinecraftVersion::v1_20_2)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_access() -> anyhow::Result<()> {
+        let writer = Arc::new(Mutex::new(FunctionWriter::new(Vec::new())));
+        let mut data = DataAccess::new(Identifier::new("flux", "d"), writer.clone());
+        data.set("a", 1)?;
+        data.set("b", 2)?;
+        data.replace(nbt! { b: 2, c: 3 })?;
+        assert_eq!(data.get("c")?, NbtTag::Int(3));
+        assert!(data.set("my key", 1).is_err());
+
+        let written = String::from_utf8(writer.lock().unwrap().get_ref().clone())?;
+        assert_eq!(
+            written.lines().collect::<Vec<_>>(),
+            [
+                "data modify storage flux:d a set value 1",
+                "data modify storage flux:d b set value 2",
+                "data remove storage flux:d a",
+                "data merge storage flux:d {c:3}",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_command() -> anyhow::Result<()> {
+        let storage = DataTarget::Storage(Identifier::new("flux", "players"));
+        let player = DataTarget::entity(Selector::NearestPlayer);
+
+        let mut copy = DataCommand::modify(
+            storage.clone(),
+            "inventories[-1]",
+            ModifyOperation::Insert(0),
+            DataSource::from_target(player.clone(), "Inventory[{Slot:0b}]")?,
+        )?;
+        assert_eq!(
+            copy.compile()?,
+            "data modify storage flux:players inventories[-1] insert 0 from entity @p Inventory[{Slot:0b}]"
+        );
+        let mut name = DataCommand::modify(
+            DataTarget::Block(Location::new(1, 64, -3)),
+            "CustomName",
+            ModifyOperation::Set,
+            DataSource::String {
+                target: player.clone(),
+                path: Some("SelectedItem.id".parse()?),
+                start: Some(10),
+                end: None,
+            },
+        )?;
+        assert_eq!(
+            name.compile()?,
+            "data modify block 1 64 -3 CustomName set string entity @p SelectedItem.id 10"
+        );
+        assert_eq!(
+            DataCommand::modify(
+                storage.clone(),
+                "names",
+                ModifyOperation::Append,
+                DataSource::value("Steve")
+            )?
+            .compile()?,
+            r#"data modify storage flux:players names append value "Steve""#
+        );
+        assert_eq!(
+            DataCommand::Merge(player.clone(), nbt! { Glowing: 1b }).compile()?,
+            "data merge entity @p {Glowing:1b}"
+        );
+        assert_eq!(
+            DataCommand::Get {
+                target: player.clone(),
+                path: Some("Health".parse()?),
+                scale: Some(10.0),
+            }
+            .compile()?,
+            "data get entity @p Health 10"
+        );
+        assert_eq!(
+            DataCommand::remove(storage.clone(), "names[0]")?.compile()?,
+            "data remove storage flux:players names[0]"
+        );
+        // malformed paths are errors instead of panics
+        assert!(DataCommand::remove(storage, "names[0").is_err());
+        assert!(DataCommand::Get {
+            target: player,
+            path: None,
+            scale: Some(2.0),
+        }
+        .compile()
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_commands() -> anyhow::Result<()> {
+        // every axis is written, and absolute zero stays a number unlike relative zero
+        assert_eq!(Location::new(1, 0, -3).to_string(), "1 0 -3");
+        assert_eq!(Location::relative(0, 2, 0).to_string(), "~ ~2 ~");
+        assert_eq!(Location::local(0, 0, 1).to_string(), "^ ^ ^1");
+        assert_eq!(
+            Location::relative(1, 0, 0).offset(-1, 0, 2).to_string(),
+            "~ ~ ~2"
+        );
+        assert_eq!(
+            SetBlockCommand::new(Location::relative(0, 1, 0), Material::Chest)
+                .nbt(nbt! { Lock: "key" })
+                .mode(SetBlockMode::Destroy)
+                .compile()?,
+            r#"setblock ~ ~1 ~ minecraft:chest{Lock:"key"} destroy"#
+        );
+        assert_eq!(
+            FillCommand::new(
+                Location::new(0, 0, 0),
+                Location::new(4, 4, 4),
+                Material::Glass
+            )
+            .mode(FillMode::replace("#minecraft:logs"))
+            .compile()?,
+            "fill 0 0 0 4 4 4 minecraft:glass replace #minecraft:logs"
+        );
+
+        // 100 * 10 * 100 blocks are split into layers of 3
+        let fill = FillCommand::new(
+            Location::new(99, 9, 99),
+            Location::new(0, 0, 0),
+            Material::Stone,
+        )
+        .mode(FillMode::Keep);
+        let commands = fill.commands()?;
+        assert_eq!(commands.len(), 4);
+        assert_eq!(commands[0], "fill 0 0 0 99 2 99 minecraft:stone keep");
+        assert_eq!(commands[3], "fill 0 9 0 99 9 99 minecraft:stone keep");
+
+        // 6 sides and 2 parts of the inside
+        let hollow = FillCommand::new(
+            Location::relative(0, 0, 0),
+            Location::relative(39, 39, 39),
+            Material::Stone,
+        )
+        .mode(FillMode::Hollow)
+        .commands()?;
+        assert_eq!(hollow.len(), 8);
+        assert_eq!(hollow[0], "fill ~ ~ ~ ~39 ~ ~39 minecraft:stone");
+        assert_eq!(hollow[7], "fill ~1 ~23 ~1 ~38 ~38 ~38 minecraft:air");
+        assert!(FillCommand::new(
+            Location::local(0, 0, 0),
+            Location::local(39, 39, 39),
+            Material::Stone
+        )
+        .commands()
+        .is_err());
+        assert!(FillCommand::new(
+            Location::new(-2_000_000_000, 0, 0),
+            Location::new(2_000_000_000, 255, 2_000_000_000),
+            Material::Stone
+        )
+        .commands()
+        .is_err());
+        assert!(ExecuteCommand::default().run(fill).is_err());
+
+        assert_eq!(
+            CloneCommand::new(
+                Location::new(0, 0, 0),
+                Location::new(9, 9, 9),
+                Location::relative(0, 0, 0)
+            )
+            .from_dimension(Identifier::minecraft("the_nether"))
+            .mode(CloneMode::Move)
+            .compile()?,
+            "clone from minecraft:the_nether 0 0 0 9 9 9 ~ ~ ~ replace move"
+        );
+        assert_eq!(
+            CloneCommand::new(
+                Location::new(0, 0, 0),
+                Location::new(9, 9, 9),
+                Location::new(20, 0, 0)
+            )
+            .mask(CloneMask::Filtered("minecraft:stone".into()))
+            .compile()?,
+            "clone 0 0 0 9 9 9 20 0 0 filtered minecraft:stone"
+        );
+
+        assert_eq!(
+            PlaceCommand::Feature(Identifier::minecraft("oak"), None).compile()?,
+            "place feature minecraft:oak"
+        );
+        assert_eq!(
+            PlaceCommand::Jigsaw {
+                pool: Identifier::minecraft("village/plains/town_centers"),
+                target: Identifier::minecraft("bottom"),
+                max_depth: 7,
+                pos: None,
+            }
+            .at(Location::new(0, 64, 0))
+            .compile()?,
+            "place jigsaw minecraft:village/plains/town_centers minecraft:bottom 7 0 64 0"
+        );
+        assert_eq!(
+            PlaceCommand::template(Identifier::new("flux", "house"))
+                .transform(
+                    StructureRotation::Clockwise90,
+                    StructureMirror::None,
+                    0.5,
+                    0
+                )?
+                .compile()?,
+            "place template flux:house ~ ~ ~ clockwise_90 none 0.5 0"
+        );
+        assert!(PlaceCommand::Feature(Identifier::minecraft("oak"), None)
+            .transform(StructureRotation::None, StructureMirror::None, 1.0, 0)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_entity_commands() -> anyhow::Result<()> {
+        let zombies = FullSelector::new(Selector::AllEntities, [("type", "zombie")]);
+        assert_eq!(
+            KillCommand::new(zombies.clone()).compile()?,
+            "kill @e[type=zombie]"
+        );
+        assert_eq!(
+            TeleportCommand::new(
+                Selector::AllPlayers,
+                Teleport::facing_entity(Location::new(0, 64, 0), zombies.clone(), Anchor::Eyes)
+            )
+            .compile()?,
+            "tp @a 0 64 0 facing entity @e[type=zombie] eyes"
+        );
+        assert_eq!(
+            EffectGiveCommand::new(
+                Selector::NearestPlayer,
+                Effect::JumpBoost,
+                Some(EffectDuration::Infinite),
+                Some(2),
+                Some(true)
+            )
+            .compile()?,
+            "effect give @p minecraft:jump_boost infinite 2 true"
+        );
+        assert!(EffectGiveCommand::builder()
+            .targets(Selector::NearestPlayer)
+            .effect(Effect::Speed)
+            .amplifier(Some(1))
+            .compile()
+            .is_err());
+        assert_eq!(
+            EffectClearCommand::new(Selector::AllPlayers, None).compile()?,
+            "effect clear @a"
+        );
+        assert_eq!(
+            EnchantCommand::new(
+                Selector::NearestPlayer,
+                Enchantment::new(Enchant::FireAspect, 2)
+            )
+            .compile()?,
+            "enchant @p minecraft:fire_aspect 2"
+        );
+        assert_eq!(
+            TagCommand::new(zombies.clone(), TagAction::Add("boss".into())).compile()?,
+            "tag @e[type=zombie] add boss"
+        );
+        let mut attribute = AttributeCommand::new(
+            Selector::NearestPlayer,
+            Attribute::MaxHealth,
+            AttributeAction::AddModifier {
+                id: ModifierId::Uuid(Uuid::from_u128(1)),
+                name: Some("Boss health".into()),
+                amount: 0.5,
+                operation: AttributeOperation::MultiplyBase,
+            },
+        );
+        assert_eq!(
+            attribute.command(MinecraftVersion::v1_20_3)?,
+            r#"attribute @p generic.max_health modifier add 00000000-0000-0000-0000-000000000001 "Boss health" 0.5 multiply_base"#
+        );
+        assert!(attribute.compile().is_err());
+        let mut attribute = attribute.action(AttributeAction::AddModifier {
+            id: ModifierId::Id(Identifier::new("boss", "health")),
+            name: None,
+            amount: 0.5,
+            operation: AttributeOperation::MultiplyBase,
+        });
+        assert_eq!(
+            attribute.compile()?,
+            "attribute @p generic.max_health modifier add boss:health 0.5 add_multiplied_base"
+        );
+        assert!(attribute.command(MinecraftVersion::v1_20_3).is_err());
+        assert!(AttributeCommand::builder()
+            .target(Selector::NearestPlayer)
+            .action(AttributeAction::Get(None))
+            .command(MinecraftVersion::v1_21)
+            .is_err());
+        assert_eq!(
+            DamageCommand::new(
+                Selector::NearestPlayer,
+                4.5,
+                Some(Identifier::minecraft("arrow")),
+                Some(DamageSource::by_from(
+                    "@e[type=arrow,limit=1]",
+                    zombies.clone()
+                ))
+            )
+            .compile()?,
+            "damage @p 4.5 minecraft:arrow by @e[type=arrow,limit=1] from @e[type=zombie]"
+        );
+        assert_eq!(
+            RideCommand::new(Selector::NearestPlayer, RideAction::mount(zombies)).compile()?,
+            "ride @p mount @e[type=zombie]"
+        );
+        assert_eq!(
+            TeamJoinCommand::builder()
+                .team("red".to_string())
+                .members(Selector::AllPlayers)
+                .compile()?,
+            "team join red @a"
+        );
+        assert_eq!(
+            TeamJoinCommand::new("red".to_string(), None).compile()?,
+            "team join red"
+        );
+        assert!(TeamJoinCommand::builder()
+            .members(Selector::AllPlayers)
+            .compile()
+            .is_err());
+        Ok(())
     }
 
     #[test]
@@ -168,7 +695,7 @@ mod tests {
                     .invulnerable(true),
             )
         });
-        world.summon_entity(Location::from_str("~ ~ ~").unwrap(), entity.clone());
+        entity.summon(&mut world, Location::from_str("~ ~ ~").unwrap())?;
         entity.modify_meta(|meta| {
             if let EntityMeta::GeneralZombie(zombie) = meta {
                 return EntityMeta::GeneralZombie(
@@ -177,7 +704,1003 @@ mod tests {
             }
             unreachable!()
         });
-        entity.save(&mut world);
+        entity.save(&mut world)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_nbt_round_trip() -> anyhow::Result<()> {
+        let name = "Zombie";
+        let tag = NbtTag::Compound(nbt! {
+            id: name,
+            Health: 20.0f32,
+            UUID: [I; 1, 2, 3, 4],
+            Tags: ["first", "second"],
+            Empty: [],
+            Nested: {
+                Motion: [0.5, 1.5, 2.5],
+                Flag: true
+            }
+        });
+        let mut buf = vec![];
+        BinaryNbtWriter::new(&mut buf).write_tag(Some("root"), &tag)?;
+
+        let (read_name, read) = BinaryNbtReader::new(buf.as_slice()).read_tag()?;
+        assert_eq!(read_name, "root");
+        assert_eq!(read, tag);
+
+        let truncated = BinaryNbtReader::new(&buf[..buf.len() - 3]).read_tag();
+        assert!(truncated.is_err());
+        let unknown = BinaryNbtReader::new([0x0du8, 0x00, 0x00].as_slice()).read_tag();
+        assert!(unknown.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nbt_encodings() -> anyhow::Result<()> {
+        let score = -300;
+        let tag = NbtTag::Compound(nbt! {
+            name: "Steve",
+            Health: 20.0f32,
+            Score: score,
+            Time: 123456789i64,
+            Inventory: [{ Slot: 0i8 }],
+            Tags: [I; 1, 300]
+        });
+        for encoding in [
+            NbtEncoding::Java,
+            NbtEncoding::JavaNetwork,
+            NbtEncoding::BedrockLittleEndian,
+            NbtEncoding::BedrockNetwork,
+        ] {
+            let mut writer = BinaryNbtWriter::with_encoding(vec![], encoding);
+            writer.write_tag(Some("root"), &tag)?;
+            let buf = writer.into_inner();
+
+            let (name, read) =
+                BinaryNbtReader::with_encoding(buf.as_slice(), encoding).read_tag()?;
+            assert_eq!(read, tag);
+            assert_eq!(
+                name,
+                if encoding == NbtEncoding::JavaNetwork {
+                    ""
+                } else {
+                    "root"
+                }
+            );
+        }
+
+        let mut writer = BinaryNbtWriter::with_encoding(vec![], NbtEncoding::BedrockNetwork);
+        writer.write_tag(None, &NbtTag::Int(-300))?;
+        assert_eq!(writer.into_inner(), vec![0x03, 0x00, 0xD7, 0x04]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_modified_utf8() -> anyhow::Result<()> {
+        let str = "nul\0 and \u{1F600}";
+        let encoded = mutf8::encode(str);
+        assert_eq!(&encoded[3..5], &[0xC0, 0x80]);
+        assert_eq!(&encoded[10..], &[0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+        assert_eq!(mutf8::decode(&encoded)?, str);
+        // raw NUL bytes decode the same with and without encoded characters next to them
+        assert_eq!(mutf8::decode(b"a\0b")?, "a\0b");
+        assert_eq!(mutf8::decode(b"a\0\xC0\x80")?, "a\0\0");
+        assert!(mutf8::decode("\u{1F600}".as_bytes()).is_err());
+
+        let mut buf = vec![];
+        BinaryNbtWriter::new(&mut buf).write_tag(Some(str), &NbtTag::String(str.into()))?;
+        let (name, read) = BinaryNbtReader::new(buf.as_slice()).read_tag()?;
+        assert_eq!(name, str);
+        assert_eq!(read, NbtTag::String(str.into()));
+
+        let long = NbtTag::String("a".repeat(u16::MAX as usize + 1));
+        assert!(BinaryNbtWriter::new(vec![]).write_tag(None, &long).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_snbt_parser() -> anyhow::Result<()> {
+        let tag = NbtTag::Compound(nbt! {
+            Count: 1i8,
+            Damage: 3i16,
+            Seed: 1234567890123i64,
+            Scale: 0.75f32,
+            Motion: [0.1, 2.5, 1e20],
+            Name: "Line\nwith \"quotes\"",
+            Bytes: [B; 1, 2, 3],
+            Longs: [L; 4, 5],
+            Nested: {
+                Empty: [],
+                Ints: [I; 7, 0, 1]
+            }
+        });
+        assert_eq!(NbtTag::from_str(&tag.clone().stringify()?)?, tag);
+
+        let parsed = NbtTag::from_str(
+            "{'quoted key': 'single \\' quotes', flag: true, plain: minecraft:stone, int: 01}",
+        );
+        assert!(parsed.is_err());
+        let parsed = NbtTag::from_str(
+            "{'quoted key': 'single \\' quotes', flag: true, plain: stone, octal: 01}",
+        )?;
+        assert_eq!(
+            parsed,
+            NbtTag::Compound(nbt! {
+                "quoted key": "single ' quotes",
+                flag: true,
+                plain: "stone",
+                octal: "01"
+            })
+        );
+
+        // numbers follow the game's patterns, where doubles without suffix need a dot
+        let mut numbers = Compound::default();
+        numbers.insert("a", "1e5");
+        numbers.insert("b", 1e5);
+        numbers.insert("c", 0.05);
+        numbers.insert("d", 1e5f32);
+        numbers.insert("e", 2.0);
+        assert_eq!(
+            NbtTag::from_str("{a:1e5,b:1.e5,c:.5e-1,d:1e5f,e:2D}")?,
+            NbtTag::Compound(numbers)
+        );
+
+        let err = NbtTag::from_str("{a:1,b:[1,2b]}").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("at position 10: {a:1,b:[1,<--[HERE]"));
+
+        // non-ASCII characters after a value are reported without splitting them
+        for src in ["{a:1é}", "[1,2é]", "[I;1é]", "{a:1\u{3000}"] {
+            assert!(NbtTag::from_str(src).is_err());
+        }
+        let err = NbtTag::from_str("{a:1é}").unwrap_err();
+        assert!(err.to_string().contains("{a:1<--[HERE]"));
+
+        // deep nesting is an error instead of a stack overflow
+        assert!(NbtTag::from_str(&"[".repeat(200_000)).is_err());
+        assert!(NbtTag::from_str(&format!("{}{}", "[".repeat(512), "]".repeat(512))).is_ok());
+        assert!(NbtPath::from_str(&format!("a[{}]", "{b:".repeat(1000))).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_snbt_writer() -> anyhow::Result<()> {
+        let mut comp = nbt! {
+            Float: 1e-10f32,
+            Double: 0.1,
+            Path: "C:\\flux",
+            Quotes: "say \"hi\""
+        };
+        comp.insert("display name", "it's");
+        comp.insert("minecraft:key", NbtTag::Empty);
+        let tag = NbtTag::Compound(comp);
+
+        let snbt = tag.clone().stringify()?;
+        assert_eq!(
+            snbt,
+            r#"{Float:1e-10f,Double:0.1d,Path:"C:\\flux",Quotes:'say "hi"',"display name":"it's"}"#
+        );
+        let mut expected = tag.clone();
+        if let NbtTag::Compound(comp) = &mut expected {
+            comp.remove("minecraft:key");
+        }
+        assert_eq!(NbtTag::from_str(&snbt)?, expected);
+
+        // only backslashes and quotes are escaped, since older parsers reject `\n`
+        let lore = NbtTag::String("first\nsecond\t\\".into());
+        assert_eq!(lore.clone().stringify()?, "\"first\nsecond\t\\\\\"");
+        assert_eq!(NbtTag::from_str(&lore.clone().stringify()?)?, lore);
+
+        let mut buf = vec![];
+        let nan = StringNbtWriter::new(&mut buf).write_tag(None, &NbtTag::Double(f64::NAN));
+        assert!(nan.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pretty_snbt() -> anyhow::Result<()> {
+        let tag = NbtTag::Compound(nbt! {
+            id: "minecraft:zombie",
+            Tags: ["a", "b"],
+            Empty: {},
+            Attributes: [
+                {
+                    Name: "generic.max_health",
+                    Base: 40.0
+                }
+            ],
+            UUID: [I; 1, 2, 3, 4]
+        });
+        let pretty = tag
+            .clone()
+            .stringify_with(SnbtOptions::pretty(2).sort_keys(true))?;
+        assert_eq!(
+            pretty,
+            r#"{
+  Attributes: [
+    {
+      Base: 40.0d,
+      Name: "generic.max_health"
+    }
+  ],
+  Empty: {},
+  Tags: ["a", "b"],
+  UUID: [I; 1, 2, 3, 4],
+  id: "minecraft:zombie"
+}"#
+        );
+        let mut sorted = tag;
+        sorted.sort_keys();
+        assert_eq!(NbtTag::from_str(&pretty)?, sorted);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compound_order() {
+        let mut tag = NbtTag::Compound(nbt! {
+            id: "minecraft:zombie",
+            Health: 20i16,
+            Attributes: [
+                {
+                    Name: "generic.max_health",
+                    Base: 40.0
+                }
+            ]
+        });
+        assert_eq!(
+            tag.clone().stringify().unwrap(),
+            "{id:\"minecraft:zombie\",Health:20s,Attributes:[{Name:\"generic.max_health\",Base:40.0d}]}"
+        );
+        tag.sort_keys();
+        assert_eq!(
+            tag.stringify().unwrap(),
+            "{Attributes:[{Base:40.0d,Name:\"generic.max_health\"}],Health:20s,id:\"minecraft:zombie\"}"
+        );
+    }
+
+    #[test]
+    fn test_serde_nbt() -> anyhow::Result<()> {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Profession {
+            Farmer,
+            Librarian,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Villager {
+            profession: Profession,
+            #[serde(with = "crate::nbt::typed::byte")]
+            level: i32,
+            #[serde(with = "crate::nbt::typed::short", default)]
+            cooldown: Option<u32>,
+            #[serde(with = "crate::nbt::typed::int_array")]
+            uuid: Vec<i32>,
+            tags: Vec<String>,
+            custom_name: Option<String>,
+            no_ai: bool,
+        }
+
+        let villager = Villager {
+            profession: Profession::Librarian,
+            level: 3,
+            cooldown: Some(200),
+            uuid: vec![1, 2, 3, 4],
+            tags: vec!["trader".into()],
+            custom_name: None,
+            no_ai: true,
+        };
+        let tag = to_nbt(&villager)?;
+        assert_eq!(
+            tag,
+            NbtTag::Compound(nbt! {
+                Profession: "Librarian",
+                Level: 3i8,
+                Cooldown: 200i16,
+                Uuid: [I; 1, 2, 3, 4],
+                Tags: ["trader"],
+                NoAi: true
+            })
+        );
+        assert_eq!(from_nbt::<Villager>(tag)?, villager);
+        assert_eq!(from_snbt::<Villager>(&to_snbt(&villager)?)?, villager);
+
+        let mut buf = vec![];
+        to_binary(&mut buf, None, &villager)?;
+        let (_, read) = from_binary::<_, Villager>(buf.as_slice())?;
+        assert_eq!(read, villager);
+
+        let mut farmer =
+            from_snbt::<Villager>("{Profession:Farmer,Level:1b,Uuid:[I;0,0,0,1],Tags:[],NoAi:0b}")?;
+        assert_eq!(farmer.cooldown, None);
+        assert_eq!(farmer.profession, Profession::Farmer);
+        farmer.level = 300;
+        assert!(to_nbt(&farmer).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressed_nbt() -> anyhow::Result<()> {
+        let tag = NbtTag::Compound(nbt! {
+            DataVersion: 3120,
+            Data: {
+                LevelName: "world"
+            }
+        });
+        for compression in [
+            NbtCompression::None,
+            NbtCompression::Gzip,
+            NbtCompression::Zlib,
+        ] {
+            let mut writer = CompressedNbtWriter::new(vec![], compression);
+            writer.write_tag(None, &tag)?;
+            let buf = writer.into_inner();
+            assert_eq!(NbtCompression::detect(&buf), compression);
+
+            let (_, read) = CompressedNbtReader::new(buf.as_slice()).read_tag()?;
+            assert_eq!(read, tag);
+        }
+
+        // root tags with different compression in one stream, read a byte at a time
+        struct ByteReader<'a>(&'a [u8]);
+        impl std::io::Read for ByteReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let len = buf.len().min(self.0.len()).min(1);
+                buf[..len].copy_from_slice(&self.0[..len]);
+                self.0 = &self.0[len..];
+                Ok(len)
+            }
+        }
+        let mut stream = vec![];
+        for compression in [
+            NbtCompression::Gzip,
+            NbtCompression::Zlib,
+            NbtCompression::None,
+            NbtCompression::Gzip,
+        ] {
+            CompressedNbtWriter::new(&mut stream, compression).write_tag(None, &tag)?;
+        }
+        let mut reader = CompressedNbtReader::new(ByteReader(&stream));
+        for _ in 0..4 {
+            assert_eq!(reader.read_tag()?.1, tag);
+        }
+        assert!(reader.read_tag().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compound_accessors() -> anyhow::Result<()> {
+        let uuid = uuid::Uuid::new_v4();
+        let mut comp = nbt! {
+            id: "minecraft:zombie",
+            Health: 20.0f32,
+            UUID: uuid,
+            Tags: ["a", "b"],
+            display: {
+                Name: "Bob"
+            }
+        };
+        assert_eq!(comp.get_float("Health"), Some(20.0));
+        assert_eq!(comp.get_int("Health"), None);
+        assert_eq!(comp.get_string("id"), Some("minecraft:zombie"));
+        assert_eq!(comp.get_list("Tags").map(|it| it.len()), Some(2));
+
+        comp.merge(nbt! {
+            Health: 10.0f32,
+            display: {
+                Lore: ["line"]
+            }
+        });
+        let display = comp.get_compound("display").unwrap();
+        assert!(display.contains("Name") && display.contains("Lore"));
+        assert_eq!(comp.get_float("Health"), Some(10.0));
+
+        assert_eq!(comp.remove("Health"), Some(NbtTag::Float(10.0)));
+        comp.insert("NoAI", true);
+        assert_eq!(comp.get_bool("NoAI"), Some(true));
+
+        let id = Identifier::try_from(comp.get("id").unwrap().clone())?;
+        assert_eq!(id, Identifier::minecraft("zombie"));
+        let tags: Vec<String> = comp.get("Tags").unwrap().clone().try_into()?;
+        assert_eq!(tags, vec!["a", "b"]);
+        let read_uuid = uuid::Uuid::try_from(comp.get("UUID").unwrap().clone())?;
+        assert_eq!(read_uuid, uuid);
+        assert!(i32::try_from(NbtTag::Byte(1)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nbt_errors() -> anyhow::Result<()> {
+        let passenger = NbtTag::Compound(nbt! {
+            HandItems: [(NbtTag::Int(1)), (NbtTag::String("sword".into()))]
+        });
+        let tag = NbtTag::Compound(nbt! {
+            Passengers: [(NbtTag::Compound(nbt! {})), (NbtTag::Compound(nbt! {})), passenger]
+        });
+
+        let err = tag.clone().stringify().unwrap_err();
+        assert!(matches!(
+            err,
+            NbtError::HeterogeneousList {
+                expected: 3,
+                found: 8,
+                ..
+            }
+        ));
+        assert_eq!(
+            err.path().map(NbtPath::to_string).as_deref(),
+            Some("Passengers[2].HandItems[1]")
+        );
+        let err = BinaryNbtWriter::new(vec![])
+            .write_tag(None, &tag)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "List contains tags of different types: expected id 3, found id 8 at Passengers[2].HandItems[1]"
+        );
+
+        let long = NbtTag::Compound(nbt! { Name: (NbtTag::String("a".repeat(70000))) });
+        let err = BinaryNbtWriter::new(vec![])
+            .write_tag(None, &long)
+            .unwrap_err();
+        assert!(matches!(err, NbtError::StringTooLong { len: 70000, .. }));
+
+        let err = NbtTag::from_str("{a:{b:[1,2,}}").unwrap_err();
+        assert_eq!(
+            err.path().map(NbtPath::to_string).as_deref(),
+            Some("a.b[2]")
+        );
+
+        let err =
+            Vec::<i32>::try_from(NbtTag::List(vec![NbtTag::Int(1), NbtTag::Byte(2)])).unwrap_err();
+        assert!(matches!(err, NbtError::Parse { .. }));
+        assert_eq!(err.path().map(NbtPath::to_string).as_deref(), Some("[1]"));
+        let err = BlockState::try_from(NbtTag::Compound(nbt! {
+            Name: "minecraft:stone",
+            Properties: { facing: 1i8 }
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err.path().map(NbtPath::to_string).as_deref(),
+            Some("Properties.facing")
+        );
+
+        let err = from_json(
+            &serde_json::json!({ "a": [{ "b": [1, "x"] }] }),
+            NumberPolicy::Int,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            NbtError::HeterogeneousList {
+                expected: 3,
+                found: 8,
+                ..
+            }
+        ));
+        assert_eq!(
+            err.path().map(NbtPath::to_string).as_deref(),
+            Some("a[0].b")
+        );
+        let err = to_json(&NbtTag::Compound(nbt! { a: [1.0, (f64::NAN)] })).unwrap_err();
+        assert!(matches!(err, NbtError::Unsupported { .. }));
+        assert_eq!(err.path().map(NbtPath::to_string).as_deref(), Some("a[1]"));
+
+        assert!(matches!(
+            NbtPath::from_str("Items[").unwrap_err(),
+            NbtError::Parse { .. }
+        ));
+        let mut tag = NbtTag::Compound(nbt! { Tags: ["a", "b"] });
+        let err = NbtPath::from_str("Tags[0]")?
+            .set(&mut tag, NbtTag::Int(1))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            NbtError::HeterogeneousList {
+                expected: 8,
+                found: 3,
+                ..
+            }
+        ));
+        assert!(matches!(
+            NbtPath::from_str("Tags")?.merge(&mut tag, nbt! {}),
+            Err(NbtError::Unsupported { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_nbt() -> anyhow::Result<()> {
+        let json = serde_json::json!({
+            "count": 3,
+            "big": 100000,
+            "chance": 0.5,
+            "enabled": true,
+            "skipped": null,
+            "values": [1, 300, 2],
+            "names": ["a", "b"]
+        });
+        let tag = from_json(&json, NumberPolicy::Narrowest)?;
+        let comp = tag.as_compound().unwrap();
+        assert_eq!(comp.get_byte("count"), Some(3));
+        assert_eq!(comp.get_int("big"), Some(100000));
+        assert_eq!(comp.get_double("chance"), Some(0.5));
+        assert_eq!(comp.get_bool("enabled"), Some(true));
+        assert!(!comp.contains("skipped"));
+        assert_eq!(
+            comp.get_list("values"),
+            Some(&[NbtTag::Short(1), NbtTag::Short(300), NbtTag::Short(2)][..])
+        );
+        assert_eq!(
+            from_json(&json, NumberPolicy::Long)?
+                .as_compound()
+                .unwrap()
+                .get_long("count"),
+            Some(3)
+        );
+        assert!(from_json(&serde_json::json!([1, "a"]), NumberPolicy::Int).is_err());
+
+        let back = to_json(&NbtTag::Compound(nbt! {
+            count: 3i8,
+            chance: 0.1f32,
+            ids: (NbtTag::IntArray(vec![1, 2]))
+        }))?;
+        assert_eq!(
+            back,
+            serde_json::json!({ "count": 3, "chance": 0.1, "ids": [1, 2] })
+        );
+        assert!(to_json(&NbtTag::Double(f64::NAN)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nbt_macro() -> anyhow::Result<()> {
+        let extra = nbt! { Silent: 1b, Glowing: 1b };
+        let name: Option<&str> = None;
+        let tags = vec!["b", "c"];
+        let pos = Vec3D(1.0, 2.0, 3.0);
+        let comp = nbt! {
+            NoAI: 1b,
+            Fire: -20s,
+            Age: 600l,
+            Health: 20f,
+            Scale: 0.5d,
+            Count: 3i8,
+            Pos: pos,
+            Motion: (Vec3D(0.0, 0.5, 0.0)),
+            CustomName?: name,
+            id?: Some("minecraft:zombie"),
+            UUID: [I; ..[1, 2, 3, 4]],
+            Offsets: [L; 1 + 1, -5],
+            Tags: ["a", ..tags],
+            Rotation: [-90f, 0f],
+            Escaped: "tab\tand \u{e9}",
+            ..extra
+        };
+        assert_eq!(
+            NbtTag::Compound(comp).stringify()?,
+            concat!(
+                r#"{NoAI:1b,Fire:-20s,Age:600L,Health:20.0f,Scale:0.5d,Count:3b,Pos:[1.0d,2.0d,3.0d],Motion:[0.0d,0.5d,0.0d],id:"minecraft:zombie",UUID:[I;1,2,3,4],Offsets:[L;2L,-5L],Tags:["a","b","c"],Rotation:[-90.0f,0.0f],Escaped:"tab"#,
+                "\t",
+                r#"and é",Silent:1b,Glowing:1b}"#
+            )
+        );
+
+        let literals = nbt! {
+            a: 10b,
+            b: 0b101,
+            c: 0xffu8,
+            d: -128b,
+            e: 1_000_000l,
+            f: 'c',
+            g: r#"raw "quoted""#,
+            h: "\x41\u{1F600}\
+                continued",
+            i: true
+        };
+        assert_eq!(
+            NbtTag::Compound(literals).stringify()?,
+            r#"{a:10b,b:5,c:-1b,d:-128b,e:1000000L,f:"c",g:'raw "quoted"',h:"A😀continued",i:1b}"#
+        );
+        let floats = nbt! {
+            a: 0.1f,
+            b: 3.4028235e38f,
+            c: 1e-45f,
+            d: 0.1,
+            e: 1.7976931348623157e308d,
+            f: 5e-324d,
+            g: -0f,
+            h: 2.5e-3f64
+        };
+        let expected = [
+            NbtTag::Float(0.1),
+            NbtTag::Float(f32::MAX),
+            NbtTag::Float(f32::from_bits(1)),
+            NbtTag::Double(0.1),
+            NbtTag::Double(f64::MAX),
+            NbtTag::Double(f64::from_bits(1)),
+            NbtTag::Float(-0.0),
+            NbtTag::Double(0.0025),
+        ];
+        for ((_, tag), expected) in floats.iter().zip(expected) {
+            assert_eq!(tag, &expected);
+        }
+        assert!(matches!(floats.get("g"), Some(NbtTag::Float(value)) if value.is_sign_negative()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_components() -> anyhow::Result<()> {
+        let text = TextComponent::translatable(
+            "chat.type.text",
+            vec![
+                TextComponent::selector("@p").color(TextColor::Gold),
+                TextComponent::score("@s", "kills"),
+            ],
+        )
+        .fallback("<%s> %s")?
+        .bold(true)
+        .click(ClickEvent::RunCommand("/kill @s".to_string()))
+        .hover(HoverEvent::ShowText(Box::new(
+            TextComponent::keybind(Keybind::Jump).color(TextColor::Rgb(255, 0, 128)),
+        )))
+        .append(
+            TextComponent::nbt("Items[0]", NbtSource::Storage("flux:data".to_string()))
+                .interpret(true)?,
+        );
+
+        assert_eq!(
+            text.to_tag(MinecraftVersion::v1_19)?,
+            NbtTag::String(
+                r##"{"translate":"chat.type.text","fallback":"<%s> %s","with":[{"selector":"@p","color":"gold"},{"score":{"name":"@s","objective":"kills"}}],"bold":true,"clickEvent":{"action":"run_command","value":"/kill @s"},"hoverEvent":{"action":"show_text","contents":{"keybind":"key.jump","color":"#FF0080"}},"extra":[{"nbt":"Items[0]","interpret":true,"storage":"flux:data"}]}"##
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            text.to_tag(MinecraftVersion::v1_20_3)?.stringify()?,
+            r##"{translate:"chat.type.text",fallback:"<%s> %s",with:[{selector:"@p",color:"gold"},{score:{name:"@s",objective:"kills"}}],bold:1b,clickEvent:{action:"run_command",value:"/kill @s"},hoverEvent:{action:"show_text",contents:{keybind:"key.jump",color:"#FF0080"}},extra:[{nbt:"Items[0]",interpret:1b,storage:"flux:data"}]}"##
+        );
+        assert!(TextComponent::text("a").fallback("b").is_err());
+        assert!(TextComponent::score("@s", "kills")
+            .separator(TextComponent::text(", "))
+            .is_err());
+        assert!(TextComponent::selector("@a").interpret(true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nbt_diff() {
+        let old = NbtTag::Compound(nbt! {
+            Health: 20.0f32,
+            Tags: ["a"],
+            display: {
+                Name: "Bob",
+                Lore: ["line"]
+            }
+        });
+        let new = NbtTag::Compound(nbt! {
+            Health: 10.0f32,
+            Tags: ["a"],
+            display: {
+                Name: "Bob"
+            },
+            NoAI: true
+        });
+        let commands = diff(&old, &new)
+            .iter()
+            .map(|patch| patch.command("entity @s").unwrap())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            commands,
+            vec![
+                "data remove entity @s display.Lore",
+                "data merge entity @s {Health:10.0f,NoAI:1b}"
+            ]
+        );
+        assert!(diff(&new, &new).is_empty());
+
+        let empty = NbtTag::Compound(nbt! {
+            Health: NbtTag::Empty,
+            Tags: ["a"],
+            display: {
+                Name: "Bob",
+                Lore: NbtTag::Empty
+            },
+            Fire: NbtTag::Empty
+        });
+        let commands = diff(&old, &empty)
+            .iter()
+            .map(|patch| patch.command("entity @s").unwrap())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            commands,
+            vec![
+                "data remove entity @s Health",
+                "data remove entity @s display.Lore"
+            ]
+        );
+
+        let patches = diff(&old, &NbtTag::Int(1));
+        assert_eq!(patches.len(), 1);
+        assert!(patches[0].command("entity @s").is_err());
+    }
+
+    #[test]
+    fn test_entity_tracking_tag() -> anyhow::Result<()> {
+        let writer = Arc::new(Mutex::new(FunctionWriter::new(Vec::new())));
+        let mut world = WorldAccess::new(writer.clone());
+        let mut entity = Entity::new(EntityType::Zombie);
+        entity.provide_meta(|| {
+            EntityMeta::GeneralZombie(GeneralZombie::new().tags(vec!["a".to_string()]))
+        });
+        entity.summon(&mut world, Location::from_str("~ ~ ~").unwrap())?;
+        entity.modify_meta(|meta| {
+            if let EntityMeta::GeneralZombie(zombie) = meta {
+                return EntityMeta::GeneralZombie(zombie.tags(vec![]));
+            }
+            unreachable!()
+        });
+        entity.save(&mut world)?;
+
+        let marker = format!("fluxd{}", entity.id);
+        let written = String::from_utf8(writer.lock().unwrap().get_ref().clone())?;
+        let lines = written.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            format!(
+                "summon minecraft:zombie ~ ~ ~ {{Tags:[\"{}\",\"a\"]}}",
+                marker
+            )
+        );
+        assert_eq!(
+            lines[1],
+            format!(
+                "execute if entity @e[tag={m}] as @e[tag={m}] run data merge entity @s {{Tags:[\"{m}\"]}}",
+                m = marker
+            )
+        );
+        assert_eq!(lines.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_region_file() -> anyhow::Result<()> {
+        let stone = BlockState::new(Identifier::minecraft("stone"));
+        let stairs =
+            BlockState::new(Identifier::minecraft("oak_stairs")).property("facing", "north");
+
+        let mut chunk = Chunk::new(3, 5, 3120);
+        chunk.set_block(1, 64, 2, stone.clone())?;
+        chunk.set_block(15, -64, 15, stairs.clone())?;
+        for x in 0..16 {
+            chunk.set_block(x, 65, 0, BlockState::new(Identifier::minecraft("glass")))?;
+        }
+        chunk.set_block(1, 64, 2, BlockState::air())?;
+        chunk.set_block(1, 64, 2, stone.clone())?;
+
+        let mut region = RegionFile::new();
+        region.set_chunk(3, 5, chunk);
+        let mut gzip = Chunk::new(36, 5, 3120);
+        gzip.compression = NbtCompression::Gzip;
+        region.set_chunk(36, 5, gzip);
+
+        let mut buf = vec![];
+        region.write(&mut buf)?;
+        assert_eq!(buf.len() % 4096, 0);
+        let read = RegionFile::read(buf.as_slice())?;
+        assert_eq!(read, region);
+        assert_eq!(read.chunks().count(), 2);
+        assert_eq!(read.chunk(4, 5).and_then(Chunk::x_pos), Some(36));
+
+        let chunk = read.chunk(3, 5).unwrap();
+        assert_eq!(chunk.x_pos(), Some(3));
+        assert_eq!(chunk.block(1, 64, 2)?, Some(stone));
+        assert_eq!(chunk.block(15, -64, 15)?, Some(stairs));
+        assert_eq!(chunk.block(0, 64, 0)?, Some(BlockState::air()));
+        assert_eq!(
+            chunk.block(7, 65, 0)?.map(|it| it.to_string()),
+            Some("minecraft:glass".to_string())
+        );
+        assert_eq!(chunk.block(0, 100, 0)?, None);
+        let section = chunk.sections()[0].as_compound().unwrap();
+        assert_eq!(
+            section
+                .get_compound("biomes")
+                .and_then(|it| it.get_list("palette")),
+            Some([NbtTag::String("minecraft:plains".to_string())].as_slice())
+        );
+        assert!(RegionFile::read([0u8; 100].as_slice()).is_err());
+
+        // indices outside of the palette are errors instead of panics
+        let gold = BlockState::new(Identifier::minecraft("gold_block"));
+        let mut corrupt = Chunk::new(0, 0, 3120);
+        corrupt.set_block(0, 0, 0, gold.clone())?;
+        corrupt.data.get_list_mut("sections").unwrap()[0]
+            .as_compound_mut()
+            .and_then(|it| it.get_compound_mut("block_states"))
+            .unwrap()
+            .insert("data", NbtTag::LongArray(vec![0x5555_5555_5555_5555; 256]));
+        assert!(corrupt.block(0, 0, 0).is_err());
+        assert!(corrupt.set_block(1, 0, 0, gold.clone()).is_err());
+
+        let mut old = Chunk::new(0, 0, 2586);
+        old.data.insert("Level", nbt! { Sections: [] });
+        assert!(old.block(0, 0, 0).is_err());
+        assert!(old.set_block(0, 0, 0, gold).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_structure() -> anyhow::Result<()> {
+        let mut structure = Structure::new(Vec3I(3, 2, 3));
+        structure.set_block(Vec3I(0, 0, 0), Material::Stone)?;
+        structure.set_block(Vec3I(2, 0, 2), Material::Stone)?;
+        structure.set_block_entity(
+            Vec3I(1, 1, 1),
+            BlockState::new(Identifier::minecraft("chest")).property("facing", "west"),
+            nbt! { Lock: "key" },
+        )?;
+        structure.add_entity(
+            Vec3D(1.5, 0.0, 1.5),
+            EntityType::Zombie,
+            EntityMeta::new(EntityType::Zombie),
+        )?;
+        assert!(structure
+            .set_block(Vec3I(3, 0, 0), Material::Stone)
+            .is_err());
+        assert_eq!(structure.palette().len(), 2);
+
+        let mut buf = vec![];
+        structure.write(&mut buf)?;
+        assert_eq!(NbtCompression::detect(&buf), NbtCompression::Gzip);
+        let read = Structure::read(buf.as_slice())?;
+        assert_eq!(read, structure);
+        assert_eq!(read.entities()[0].block_pos, Vec3I(1, 0, 1));
+        assert_eq!(
+            read.entities()[0].nbt.get_string("id"),
+            Some("minecraft:zombie")
+        );
+        assert_eq!(
+            read.block(Vec3I(2, 0, 2)),
+            Some(&BlockState::new(Identifier::minecraft("stone")))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_schematic() -> anyhow::Result<()> {
+        // 3x1x2 floor of stone with a chest in the corner
+        let mut palette = Compound::default();
+        palette.insert("minecraft:stone", 0);
+        palette.insert("minecraft:chest[facing=west]", 1);
+        let mut chest = nbt! { Id: "minecraft:chest", Lock: "key" };
+        chest.insert("Pos", NbtTag::IntArray(vec![2, 0, 1]));
+        let mut root = nbt! {
+            Version: 2,
+            Width: 3i16,
+            Height: 1i16,
+            Length: 2i16,
+            BlockEntities: [(NbtTag::Compound(chest))]
+        };
+        root.insert("Palette", palette);
+        root.insert("BlockData", NbtTag::ByteArray(vec![0, 0, 0, 0, 0, 1]));
+        let mut buf = vec![];
+        CompressedNbtWriter::new(&mut buf, NbtCompression::Gzip)
+            .write_tag(Some("Schematic"), &NbtTag::Compound(root))?;
+
+        let schematic = Schematic::read(buf.as_slice())?;
+        assert_eq!(schematic.size(), Vec3I(3, 1, 2));
+        assert_eq!(
+            schematic.commands(Location::relative(0, 0, 0))?,
+            vec![
+                "fill ~ ~ ~ ~2 ~ ~ minecraft:stone",
+                "fill ~ ~ ~1 ~1 ~ ~1 minecraft:stone",
+                "setblock ~2 ~ ~1 minecraft:chest[facing=west]{Lock:\"key\"}",
+            ]
+        );
+        let structure = schematic.to_structure()?;
+        assert_eq!(structure.palette().len(), 2);
+        assert_eq!(structure.blocks().count(), 6);
+
+        // rows wider than a single fill are split
+        let mut wide = nbt! { Version: 2, Width: (40000u16 as i16), Height: 1i16, Length: 1i16 };
+        wide.insert("Palette", nbt! { "minecraft:stone": 0 });
+        wide.insert("BlockData", NbtTag::ByteArray(vec![0; 40000]));
+        let mut buf = vec![];
+        CompressedNbtWriter::new(&mut buf, NbtCompression::Gzip)
+            .write_tag(None, &NbtTag::Compound(wide))?;
+        assert_eq!(
+            Schematic::read(buf.as_slice())?.commands(Location::relative(0, 0, 0))?,
+            vec![
+                "fill ~ ~ ~ ~32767 ~ ~ minecraft:stone",
+                "fill ~32768 ~ ~ ~39999 ~ ~ minecraft:stone",
+            ]
+        );
+
+        // oversized schematics are errors instead of overflows
+        let write = |root: Compound| -> anyhow::Result<Vec<u8>> {
+            let mut buf = vec![];
+            CompressedNbtWriter::new(&mut buf, NbtCompression::Gzip)
+                .write_tag(None, &NbtTag::Compound(root))?;
+            Ok(buf)
+        };
+        let mut huge = nbt! { Version: 2, Width: -1i16, Height: -1i16, Length: -1i16 };
+        huge.insert("Palette", nbt! { "minecraft:stone": 0 });
+        assert!(Schematic::read(write(huge)?.as_slice()).is_err());
+        let huge = nbt! {
+            Regions: {
+                a: {
+                    Position: { x: 0, y: 0, z: 0 },
+                    Size: { x: 2147483647, y: 2147483647, z: 2147483647 }
+                }
+            }
+        };
+        assert!(Schematic::read(write(huge)?.as_slice()).is_err());
+        let far = nbt! {
+            Regions: {
+                a: {
+                    Position: { x: -2147483648, y: 0, z: 0 },
+                    Size: { x: -2147483648, y: 1, z: 1 }
+                },
+                b: {
+                    Position: { x: 2147483647, y: 0, z: 0 },
+                    Size: { x: 1, y: 1, z: 1 }
+                }
+            }
+        };
+        assert!(Schematic::read(write(far)?.as_slice()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nbt_path() -> anyhow::Result<()> {
+        let mut tag = NbtTag::Compound(nbt! {
+            Items: [
+                { Slot: 0i8, id: "minecraft:stone" },
+                { Slot: 1i8, id: "minecraft:dirt" }
+            ]
+        });
+
+        let path = NbtPath::from_str("Items[{Slot:1b}].tag.display.Name")?;
+        assert_eq!(path.to_string(), "Items[{Slot:1b}].tag.display.Name");
+        assert_eq!(path.set(&mut tag, NbtTag::String("Dirt".into()))?, 1);
+        assert_eq!(path.get(&tag), vec![&NbtTag::String("Dirt".into())]);
+
+        let ids = NbtPath::from_str("Items[].id")?;
+        assert_eq!(ids.get(&tag).len(), 2);
+        assert_eq!(
+            NbtPath::from_str("Items[-1].Slot")?.get(&tag),
+            vec![&NbtTag::Byte(1)]
+        );
+
+        NbtPath::from_str("Tags")?.insert(&mut tag, -1, NbtTag::String("b".into()))?;
+        NbtPath::from_str("Tags")?.insert(&mut tag, 0, NbtTag::String("a".into()))?;
+        assert_eq!(
+            NbtPath::from_str("Tags[1]")?.get(&tag),
+            vec![&NbtTag::String("b".into())]
+        );
+        assert!(NbtPath::from_str("Tags")?
+            .insert(&mut tag, 0, NbtTag::Int(1))
+            .is_err());
+
+        assert_eq!(NbtPath::from_str("Items[0]")?.remove(&mut tag), 1);
+        assert_eq!(
+            ids.get(&tag),
+            vec![&NbtTag::String("minecraft:dirt".into())]
+        );
+
+        NbtPath::from_str("Items[0].tag")?.merge(&mut tag, nbt! { Unbreakable: 1i8 })?;
+        assert_eq!(
+            NbtPath::from_str("{Tags:[\"a\"]}.Items[0].tag.display")?
+                .get(&tag)
+                .len(),
+            1
+        );
+
+        assert!(NbtPath::from_str("Items[").is_err());
+        assert!(NbtPath::from_str("Items.").is_err());
         Ok(())
     }
 }
@@ -1,36 +1,60 @@
 #[doc(hidden)]
 #[macro_export]
 macro_rules! convert_nbt_type {
-    ({
-        $(
-            $k:ident: $v:tt
-        ),* $(,)*
-    }) => {
-        $crate::nbt::NbtTag::Compound(nbt!($($k:$v,)*))
+    ({ $($tks:tt)* }) => {
+        $crate::nbt::NbtTag::Compound($crate::nbt!($($tks)*))
     };
     ($var:ident) => {
         $var.into()
     };
+    // literals are checked from their source text at compile time,
+    // so SNBT suffixes like `1b` work too
     ($lit:literal) => {
-        $lit.into()
+        $crate::__nbt_literal!(stringify!($lit))
     };
-    ([$($ele:tt),* $(,)*]) => {
-        $crate::nbt::NbtTag::List(vec![$($crate::convert_nbt_type!($ele),)*])
+    ([I; ..$arr:expr]) => {
+        $crate::nbt::NbtTag::IntArray(IntoIterator::into_iter($arr).collect())
     };
-    ([I;$($ele:tt),* $(,)*]) => {
+    ([L; ..$arr:expr]) => {
+        $crate::nbt::NbtTag::LongArray(IntoIterator::into_iter($arr).collect())
+    };
+    ([B; ..$arr:expr]) => {
+        $crate::nbt::NbtTag::ByteArray(IntoIterator::into_iter($arr).collect())
+    };
+    ([I; $($ele:expr),* $(,)*]) => {
         $crate::nbt::NbtTag::IntArray(vec![$($ele,)*])
     };
-    ([L;$($ele:tt),* $(,)*]) => {
+    ([L; $($ele:expr),* $(,)*]) => {
         $crate::nbt::NbtTag::LongArray(vec![$($ele,)*])
     };
-    ([B;$($ele:tt),* $(,)*]) => {
+    ([B; $($ele:expr),* $(,)*]) => {
         $crate::nbt::NbtTag::ByteArray(vec![$($ele,)*])
     };
+    ([$($ele:tt)*]) => {{
+        #[allow(unused_mut)]
+        let mut list: Vec<$crate::nbt::NbtTag> = Vec::new();
+        // in a separate block, so clippy does not suggest `vec![]` for the pushes
+        { $crate::__nbt_list!(list; $($ele)*); }
+        $crate::nbt::NbtTag::List(list)
+    }};
     ($($tks:tt)*) => {
-        $($tks)*
+        ($($tks)*).into()
     }
 }
 
+/// Converts the source text of a literal into a tag in a constant, so that
+/// invalid literals like `300b` fail to compile
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __nbt_literal {
+    ($token:expr) => {{
+        const TOKEN: &str = $token;
+        const LITERAL: $crate::nbt::literal::MacroLiteral<{ TOKEN.len() }> =
+            $crate::nbt::literal::MacroLiteral::parse(TOKEN);
+        LITERAL.tag()
+    }};
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! convert_nbt_key {
@@ -45,15 +69,101 @@ macro_rules! convert_nbt_key {
     };
 }
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __nbt_list {
+    ($list:ident; $(,)?) => {};
+    ($list:ident; ..$spread:expr $(, $($rest:tt)*)?) => {
+        for value in $spread {
+            let value: $crate::nbt::NbtTag = value.into();
+            $list.push(value);
+        }
+        $($crate::__nbt_list!($list; $($rest)*);)?
+    };
+    ($list:ident; -$lit:literal $(, $($rest:tt)*)?) => {
+        $list.push($crate::__nbt_literal!(concat!("-", stringify!($lit))));
+        $($crate::__nbt_list!($list; $($rest)*);)?
+    };
+    ($list:ident; $ele:tt $(, $($rest:tt)*)?) => {
+        $list.push($crate::convert_nbt_type!($ele));
+        $($crate::__nbt_list!($list; $($rest)*);)?
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __nbt_entries {
+    ($comp:ident; $(,)?) => {};
+    ($comp:ident; ..$spread:expr $(, $($rest:tt)*)?) => {
+        for (key, value) in $spread {
+            $comp.insert(key, value);
+        }
+        $($crate::__nbt_entries!($comp; $($rest)*);)?
+    };
+    ($comp:ident; $k:tt?: $v:expr $(, $($rest:tt)*)?) => {
+        if let Some(value) = $v {
+            let value: $crate::nbt::NbtTag = value.into();
+            $comp.insert($crate::convert_nbt_key!($k), value);
+        }
+        $($crate::__nbt_entries!($comp; $($rest)*);)?
+    };
+    ($comp:ident; $k:tt: -$lit:literal $(, $($rest:tt)*)?) => {
+        $comp.insert(
+            $crate::convert_nbt_key!($k),
+            $crate::__nbt_literal!(concat!("-", stringify!($lit))),
+        );
+        $($crate::__nbt_entries!($comp; $($rest)*);)?
+    };
+    ($comp:ident; $k:tt: $v:tt $(, $($rest:tt)*)?) => {
+        let value: $crate::nbt::NbtTag = $crate::convert_nbt_type!($v);
+        $comp.insert($crate::convert_nbt_key!($k), value);
+        $($crate::__nbt_entries!($comp; $($rest)*);)?
+    };
+    ($comp:ident; $k:tt: $v:expr $(, $($rest:tt)*)?) => {
+        let value: $crate::nbt::NbtTag = ($v).into();
+        $comp.insert($crate::convert_nbt_key!($k), value);
+        $($crate::__nbt_entries!($comp; $($rest)*);)?
+    };
+}
+
+/// Builds a [Compound](crate::nbt::Compound) in a SNBT-like syntax:
+///
+/// ```ignore
+/// let comp = nbt! {
+///     id: "minecraft:zombie",
+///     // numbers can have SNBT suffixes
+///     Health: 20f,
+///     NoAI: 1b,
+///     // any expression, plain variables need no temporary
+///     Pos: self.pos,
+///     // only inserted if `Some`
+///     CustomName?: self.name,
+///     UUID: [I; ..ints],
+///     Tags: ["first", ..tags],
+///     // copies all tags of another compound
+///     ..extra
+/// };
+/// ```
+///
+/// Literals are checked at compile time, so one that does not fit its type fails to compile:
+///
+/// ```compile_fail
+/// let comp = flux::nbt! { Count: 300b };
+/// ```
 #[macro_export]
 macro_rules! nbt {
     (
         $($k:tt: $v:tt),* $(,)*
     ) => {
-        $crate::nbt::Compound::new(std::collections::HashMap::<String, NbtTag>::from([
+        $crate::nbt::Compound::new([
             $(
             ($crate::convert_nbt_key!($k).into(), $crate::convert_nbt_type!($v)),
             )*
-        ]))
+        ])
     };
+    ($($tks:tt)*) => {{
+        let mut comp = $crate::nbt::Compound::default();
+        $crate::__nbt_entries!(comp; $($tks)*);
+        comp
+    }};
 }
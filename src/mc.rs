@@ -1,5 +1,8 @@
+use crate::nbt::error::NbtError;
 use crate::nbt::NbtTag;
+use anyhow::bail;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 pub mod block;
 pub mod commands;
@@ -9,6 +12,10 @@ pub mod enchant;
 pub mod entity;
 pub mod item;
 pub mod material;
+pub mod region;
+pub mod schematic;
+pub mod scoreboard;
+pub mod structure;
 pub mod world;
 
 #[derive(Debug, Clone, PartialOrd, PartialEq)]
@@ -51,6 +58,46 @@ impl Into<NbtTag> for Identifier {
     }
 }
 
+crate::__to_tag_by_clone!(Identifier);
+
+impl FromStr for Identifier {
+    type Err = anyhow::Error;
+
+    /// Parses identifier in the `namespace:path` format. Identifiers without
+    /// namespace are in the `minecraft` namespace, like in the game.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (namespace, path) = s.split_once(':').unwrap_or(("minecraft", s));
+        let valid = |str: &str, extra: &str| {
+            str.chars().all(|c| {
+                c.is_ascii_lowercase()
+                    || c.is_ascii_digit()
+                    || "_-.".contains(c)
+                    || extra.contains(c)
+            })
+        };
+        if namespace.is_empty() || path.is_empty() || !valid(namespace, "") || !valid(path, "/") {
+            bail!("Invalid identifier: {}", s)
+        }
+        Ok(Identifier::new(namespace, path))
+    }
+}
+
+impl TryFrom<NbtTag> for Identifier {
+    type Error = NbtError;
+
+    fn try_from(tag: NbtTag) -> Result<Self, Self::Error> {
+        match tag {
+            NbtTag::String(str) => {
+                Identifier::from_str(&str).map_err(|err| NbtError::parse(err.to_string()))
+            }
+            other => Err(NbtError::parse(format!(
+                "Expected string, got tag with id {}",
+                other.id()
+            ))),
+        }
+    }
+}
+
 pub trait Identified {
     fn id(&self) -> Identifier;
 }
@@ -1,6 +1,12 @@
+use crate::mc::material::Material;
+use crate::mc::{Identified, Identifier};
 use crate::nbt;
-use crate::nbt::NbtTag;
+use crate::nbt::error::NbtError;
+use crate::nbt::{Compound, NbtTag};
 use crate::utils::{Vec3D, Vec3I};
+use anyhow::bail;
+use indexmap::IndexMap;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 macro_rules! num_coord {
@@ -115,14 +121,35 @@ impl Location {
             z: Coordinate::local(z),
         }
     }
+
+    pub fn x(&self) -> Coordinate {
+        self.x
+    }
+
+    pub fn y(&self) -> Coordinate {
+        self.y
+    }
+
+    pub fn z(&self) -> Coordinate {
+        self.z
+    }
+
+    /// Moves this location by the offset, keeping its coordinates relative or local
+    pub fn offset(&self, x: i32, y: i32, z: i32) -> Self {
+        Self {
+            x: self.x.offset(x),
+            y: self.y.offset(y),
+            z: self.z.offset(z),
+        }
+    }
 }
 
 impl ToString for Location {
     fn to_string(&self) -> String {
         let mut buf = String::new();
         buf.push_str(&format!("{} ", self.x.to_string()));
-        buf.push_str(&format!("{} ", self.x.to_string()));
-        buf.push_str(&self.x.to_string());
+        buf.push_str(&format!("{} ", self.y.to_string()));
+        buf.push_str(&self.z.to_string());
         buf
     }
 }
@@ -170,6 +197,25 @@ impl Coordinate {
             local: true,
         }
     }
+
+    pub fn offset(&self, by: i32) -> Self {
+        Self {
+            pos: self.pos + by,
+            ..*self
+        }
+    }
+
+    pub fn pos(&self) -> i32 {
+        self.pos
+    }
+
+    pub fn is_relative(&self) -> bool {
+        self.relative
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.local
+    }
 }
 
 impl ToString for Coordinate {
@@ -180,7 +226,7 @@ impl ToString for Coordinate {
         } else if self.relative {
             s.push_str("~");
         };
-        if self.pos == 0 {
+        if self.pos == 0 && (self.local || self.relative) {
             s.to_string()
         } else {
             s.push_str(self.pos.to_string().as_str());
@@ -226,13 +272,142 @@ impl Into<NbtTag> for Location {
         assert!(!self.y.local && self.y.relative, "Can not convert location into a Location Compound if it has local/relative coordinates!");
         assert!(!self.z.local && self.z.relative, "Can not convert location into a Location Compound if it has local/relative coordinates!");
 
-        let x = self.x.pos;
-        let y = self.y.pos;
-        let z = self.z.pos;
         NbtTag::Compound(nbt! {
-            X: x,
-            Y: y,
-            Z: z
+            X: self.x.pos,
+            Y: self.y.pos,
+            Z: self.z.pos
         })
     }
 }
+
+/// Block with its state properties, e.g. `minecraft:oak_stairs[facing=north]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockState {
+    name: Identifier,
+    properties: IndexMap<String, String>,
+}
+
+impl BlockState {
+    pub fn new(name: Identifier) -> Self {
+        Self {
+            name,
+            properties: IndexMap::new(),
+        }
+    }
+
+    pub fn air() -> Self {
+        Self::new(Identifier::minecraft("air"))
+    }
+
+    pub fn property<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> Self {
+        self.properties.insert(key.into(), value.into());
+        self.clone()
+    }
+
+    pub fn name(&self) -> &Identifier {
+        &self.name
+    }
+
+    pub fn get_property(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+
+    pub fn properties(&self) -> &IndexMap<String, String> {
+        &self.properties
+    }
+}
+
+impl Display for BlockState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}", self.name))?;
+        if !self.properties.is_empty() {
+            let props = self
+                .properties
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<String>>()
+                .join(",");
+            f.write_fmt(format_args!("[{}]", props))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BlockState {
+    type Err = anyhow::Error;
+
+    /// Parses block state in the command format, e.g. `minecraft:oak_stairs[facing=north]`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, props) = match s.split_once('[') {
+            Some((name, props)) => match props.strip_suffix(']') {
+                Some(props) => (name, props),
+                None => bail!("Unclosed properties of block state: {}", s),
+            },
+            None => (s, ""),
+        };
+        let mut state = BlockState::new(Identifier::from_str(name)?);
+        for prop in props.split(',').filter(|it| !it.is_empty()) {
+            match prop.split_once('=') {
+                Some((k, v)) => {
+                    state.properties.insert(k.trim().into(), v.trim().into());
+                }
+                None => bail!("Invalid property {} of block state: {}", prop, s),
+            }
+        }
+        Ok(state)
+    }
+}
+
+impl Into<BlockState> for Material {
+    fn into(self) -> BlockState {
+        BlockState::new(self.id())
+    }
+}
+
+impl Into<BlockState> for Identifier {
+    fn into(self) -> BlockState {
+        BlockState::new(self)
+    }
+}
+
+impl Into<NbtTag> for BlockState {
+    fn into(self) -> NbtTag {
+        let mut comp = Compound::default();
+        comp.insert("Name", self.name);
+        if !self.properties.is_empty() {
+            comp.insert(
+                "Properties",
+                Compound::new(
+                    self.properties
+                        .into_iter()
+                        .map(|(k, v)| (k, NbtTag::String(v))),
+                ),
+            );
+        }
+        NbtTag::Compound(comp)
+    }
+}
+
+crate::__to_tag_by_clone!(Location, BlockState);
+
+impl TryFrom<NbtTag> for BlockState {
+    type Error = NbtError;
+
+    /// Reads block state in the format used by chunk palettes and structures
+    fn try_from(tag: NbtTag) -> Result<Self, Self::Error> {
+        let mut comp = Compound::try_from(tag)?;
+        let name = match comp.remove("Name") {
+            Some(name) => Identifier::try_from(name).map_err(|err| err.at_key("Name"))?,
+            None => return Err(NbtError::parse("Block state is missing its name")),
+        };
+        let mut state = BlockState::new(name);
+        if let Some(props) = comp.remove("Properties") {
+            let props = Compound::try_from(props).map_err(|err| err.at_key("Properties"))?;
+            for (k, v) in props {
+                let v = String::try_from(v).map_err(|err| err.at_key(&k).at_key("Properties"))?;
+                state.properties.insert(k, v);
+            }
+        }
+        Ok(state)
+    }
+}
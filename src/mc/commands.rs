@@ -1,45 +1,59 @@
+pub mod block;
+pub mod execute;
+
+use anyhow::bail;
+use uuid::Uuid;
+
+use crate::mc::commands::execute::Anchor;
+use crate::mc::enchant::Enchantment;
+use crate::mc::entity::effect::Effect;
+use crate::mc::entity::{Attribute, AttributeOperation, IntoSelector};
 use crate::mc::Identified;
+use crate::modules::MinecraftVersion;
+use crate::nbt::NbtTag;
 use crate::prelude::*;
 
 pub trait CommandLike {
-    fn compile(&mut self) -> String
+    /// Compiles this into a command or its argument. Fails if NBT inside it can not
+    /// be stringified, e.g. when a meta contains a NaN float
+    fn compile(&mut self) -> anyhow::Result<String>
     where
         Self: Sized;
 }
 
 impl CommandLike for ItemStack {
-    fn compile(&mut self) -> String
+    fn compile(&mut self) -> anyhow::Result<String>
     where
         Self: Sized,
     {
-        self.stringified()
+        Ok(self.stringified()?)
     }
 }
 
 impl CommandLike for Material {
-    fn compile(&mut self) -> String
+    fn compile(&mut self) -> anyhow::Result<String>
     where
         Self: Sized,
     {
-        self.id().to_string()
+        Ok(self.id().to_string())
     }
 }
 
 impl CommandLike for EntityType {
-    fn compile(&mut self) -> String
+    fn compile(&mut self) -> anyhow::Result<String>
     where
         Self: Sized,
     {
-        self.id().to_string()
+        Ok(self.id().to_string())
     }
 }
 
 impl CommandLike for Location {
-    fn compile(&mut self) -> String
+    fn compile(&mut self) -> anyhow::Result<String>
     where
         Self: Sized,
     {
-        self.to_string()
+        Ok(self.to_string())
     }
 }
 
@@ -47,11 +61,11 @@ impl<T> CommandLike for T
 where
     T: Into<String> + Clone,
 {
-    fn compile(&mut self) -> String
+    fn compile(&mut self) -> anyhow::Result<String>
     where
         Self: Sized,
     {
-        Clone::clone(self).into()
+        Ok(Clone::clone(self).into())
     }
 }
 
@@ -61,6 +75,10 @@ macro_rules! declare_commands {
             $(generic [$gen_type:ident : $gen_bound:ident $([$_i_gen:ident])?])? command $command_name:literal $struct_name:ident($(
                 $(opt $opt_type:ident $opt_name:ident)?
                 $(req $def_type:ident $def_name:ident)?
+                $(sel $sel_name:ident)?
+                $(opt_sel $opt_sel_name:ident)?
+                $(val $val_type:ident $val_name:ident)?
+                $(opt_val $opt_val_type:ident $opt_val_name:ident)?
             ),* $(,)*)
         );* $(;)*
     ) => {
@@ -70,27 +88,57 @@ macro_rules! declare_commands {
                 $(
                 $($opt_name: Option<Option<$opt_type>>,)?
                 $($def_name: Option<$def_type>,)?
+                $($sel_name: Option<String>,)?
+                $($opt_sel_name: Option<Option<String>>,)?
+                $($val_name: Option<$val_type>,)?
+                $($opt_val_name: Option<Option<$opt_val_type>>,)?
                 )*
             }
 
             impl $(<$gen_type>)? CommandLike for $struct_name $(<$gen_type>)? where $($gen_type: $gen_bound $(<$_i_gen>)? + Clone)? {
-                fn compile(&mut self) -> String {
-                    let mut buf = String::new();
-                    buf.push_str($command_name);
-                    $(
-                        $(
-                            if let Some(s) = self.$opt_name.as_ref() {
-                                if let Some(mut d) = s.clone() {
-                                    buf.push_str(format!(" {}", d.compile()).as_str());
-                                };
-                            };
-                        )?
-                        $(
-                            let mut $def_name = Clone::clone(&self.$def_name).unwrap();
-                            buf.push_str(format!(" {}", $def_name.compile()).as_str());
-                        )?
-                    )*
-                    buf
+                fn compile(&mut self) -> anyhow::Result<String> {
+                    // compiled arguments in order, `None` for optional ones that are not set
+                    let args: Vec<(&str, Option<String>)> = vec![$(
+                        $((stringify!($opt_name), match self.$opt_name.as_mut() {
+                            Some(Some(d)) => Some(d.compile()?),
+                            _ => None,
+                        }))?
+                        $((stringify!($def_name), match self.$def_name.as_mut() {
+                            Some(d) => Some(d.compile()?),
+                            None => anyhow::bail!("`{}` is required for `{}`", stringify!($def_name), $command_name),
+                        }))?
+                        $((stringify!($sel_name), match self.$sel_name.as_ref() {
+                            Some(selector) => Some(selector.clone()),
+                            None => anyhow::bail!("`{}` is required for `{}`", stringify!($sel_name), $command_name),
+                        }))?
+                        $((stringify!($opt_sel_name), self.$opt_sel_name.clone().flatten()))?
+                        $((stringify!($val_name), match self.$val_name.as_ref() {
+                            Some(d) => Some(d.to_string()),
+                            None => anyhow::bail!("`{}` is required for `{}`", stringify!($val_name), $command_name),
+                        }))?
+                        $((stringify!($opt_val_name), match self.$opt_val_name.as_ref() {
+                            Some(Some(d)) => Some(d.to_string()),
+                            _ => None,
+                        }))?
+                    ),*];
+
+                    let mut buf = String::from($command_name);
+                    // arguments are positional, so optional ones can not be skipped
+                    // if a later one is set
+                    let mut skipped = None;
+                    for (name, arg) in args {
+                        match arg {
+                            Some(arg) => {
+                                if let Some(skipped) = skipped {
+                                    anyhow::bail!("`{}` has to be set before `{}`", skipped, name)
+                                }
+                                buf.push(' ');
+                                buf.push_str(&arg);
+                            }
+                            None => skipped = Some(name),
+                        }
+                    }
+                    Ok(buf)
                 }
             }
 
@@ -100,15 +148,30 @@ macro_rules! declare_commands {
                         $(
                             $($opt_name: None,)?
                             $($def_name: None,)?
+                            $($sel_name: None,)?
+                            $($opt_sel_name: None,)?
+                            $($val_name: None,)?
+                            $($opt_val_name: None,)?
                         )*
                     }
                 }
 
-                pub fn new($($($opt_name: Option<$opt_type>,)? $($def_name: $def_type,)?)*) -> Self where Self: Sized {
+                pub fn new($(
+                    $($opt_name: Option<$opt_type>,)?
+                    $($def_name: $def_type,)?
+                    $($sel_name: impl IntoSelector,)?
+                    $($opt_sel_name: Option<String>,)?
+                    $($val_name: $val_type,)?
+                    $($opt_val_name: Option<$opt_val_type>,)?
+                )*) -> Self where Self: Sized {
                     Self {
                         $(
                             $($opt_name: Some($opt_name),)?
                             $($def_name: Some($def_name),)?
+                            $($sel_name: Some($sel_name.selector()),)?
+                            $($opt_sel_name: Some($opt_sel_name),)?
+                            $($val_name: Some($val_name),)?
+                            $($opt_val_name: Some($opt_val_name),)?
                         )*
                     }
                 }
@@ -127,12 +190,286 @@ macro_rules! declare_commands {
                             self.clone()
                         }
                     )?
+
+                    $(
+                        pub fn $sel_name(&mut self, value: impl IntoSelector) -> Self where Self: Sized {
+                            self.$sel_name = Some(value.selector());
+                            self.clone()
+                        }
+                    )?
+
+                    $(
+                        pub fn $opt_sel_name(&mut self, value: impl IntoSelector) -> Self where Self: Sized {
+                            self.$opt_sel_name = Some(Some(value.selector()));
+                            self.clone()
+                        }
+                    )?
+
+                    $(
+                        pub fn $val_name(&mut self, value: $val_type) -> Self where Self: Sized {
+                            self.$val_name = Some(value);
+                            self.clone()
+                        }
+                    )?
+
+                    $(
+                        pub fn $opt_val_name(&mut self, value: Option<$opt_val_type>) -> Self where Self: Sized {
+                            self.$opt_val_name = Some(value);
+                            self.clone()
+                        }
+                    )?
                 )*
             }
         )*
     }
 }
 
+/// Where `tp` moves the targets, and where they face afterwards
+#[derive(Debug, Clone, PartialEq)]
+pub enum Teleport {
+    /// Position of the entity matched by the selector
+    Entity(String),
+    Location(Location),
+    /// Location with yaw and pitch
+    Rotated(Location, f32, f32),
+    /// Location, facing another location
+    Facing(Location, Location),
+    /// Location, facing the eyes or feet of the entity matched by the selector
+    FacingEntity(Location, String, Anchor),
+}
+
+impl Teleport {
+    pub fn entity<S: IntoSelector>(selector: S) -> Self {
+        Teleport::Entity(selector.selector())
+    }
+
+    pub fn facing_entity<S: IntoSelector>(at: Location, selector: S, anchor: Anchor) -> Self {
+        Teleport::FacingEntity(at, selector.selector(), anchor)
+    }
+}
+
+impl CommandLike for Teleport {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        Ok(match self {
+            Teleport::Entity(selector) => selector.clone(),
+            Teleport::Location(at) => at.to_string(),
+            Teleport::Rotated(at, yaw, pitch) => format!("{} {} {}", at.to_string(), yaw, pitch),
+            Teleport::Facing(at, facing) => {
+                format!("{} facing {}", at.to_string(), facing.to_string())
+            }
+            Teleport::FacingEntity(at, selector, anchor) => {
+                format!("{} facing entity {} {}", at.to_string(), selector, anchor)
+            }
+        })
+    }
+}
+
+impl CommandLike for Effect {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        Ok(self.id().to_string())
+    }
+}
+
+/// Duration of effects given by `effect give`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EffectDuration {
+    Seconds(i32),
+    /// Since 1.19.4
+    Infinite,
+}
+
+impl CommandLike for EffectDuration {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        Ok(match self {
+            EffectDuration::Seconds(seconds) => seconds.to_string(),
+            EffectDuration::Infinite => "infinite".to_string(),
+        })
+    }
+}
+
+impl CommandLike for Enchantment {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        Ok(format!("{} {}", self.id(), self.level()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagAction {
+    Add(String),
+    Remove(String),
+    List,
+}
+
+impl CommandLike for TagAction {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        Ok(match self {
+            TagAction::Add(tag) => format!("add {}", tag),
+            TagAction::Remove(tag) => format!("remove {}", tag),
+            TagAction::List => "list".to_string(),
+        })
+    }
+}
+
+impl CommandLike for Attribute {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        Ok(self.to_string())
+    }
+}
+
+/// How `attribute` refers to a modifier of an attribute
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModifierId {
+    /// Used before 1.21
+    Uuid(Uuid),
+    /// Used since 1.21
+    Id(Identifier),
+}
+
+impl ModifierId {
+    fn compile(&self, version: MinecraftVersion) -> anyhow::Result<String> {
+        match self {
+            ModifierId::Uuid(uuid) if !version.attribute_modifier_ids() => {
+                Ok(uuid.hyphenated().to_string())
+            }
+            ModifierId::Id(id) if version.attribute_modifier_ids() => Ok(id.to_string()),
+            ModifierId::Uuid(_) => bail!("Attribute modifiers are identified by ids since 1.21"),
+            ModifierId::Id(_) => bail!("Attribute modifiers are identified by UUIDs before 1.21"),
+        }
+    }
+}
+
+/// What `attribute` does with the attribute of the target. Values that are read
+/// are multiplied by the scale, if it is set.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeAction {
+    /// Value with all modifiers applied
+    Get(Option<f64>),
+    GetBase(Option<f64>),
+    SetBase(f64),
+    /// Modifiers have a name before 1.21, and none since then
+    AddModifier {
+        id: ModifierId,
+        name: Option<String>,
+        amount: f64,
+        operation: AttributeOperation,
+    },
+    RemoveModifier(ModifierId),
+    GetModifier(ModifierId, Option<f64>),
+}
+
+fn scaled(scale: &Option<f64>) -> String {
+    scale.map(|scale| format!(" {}", scale)).unwrap_or_default()
+}
+
+impl AttributeAction {
+    /// Compiles the action for the version, which decides how modifiers are identified
+    /// and what their operations are called
+    pub fn command(&self, version: MinecraftVersion) -> anyhow::Result<String> {
+        Ok(match self {
+            AttributeAction::Get(scale) => format!("get{}", scaled(scale)),
+            AttributeAction::GetBase(scale) => format!("base get{}", scaled(scale)),
+            AttributeAction::SetBase(value) => format!("base set {}", value),
+            AttributeAction::AddModifier {
+                id,
+                name,
+                amount,
+                operation,
+            } => {
+                let name = match (version.attribute_modifier_ids(), name) {
+                    (false, Some(name)) => {
+                        format!(" {}", NbtTag::String(name.clone()).stringify()?)
+                    }
+                    (false, None) => bail!("Attribute modifiers need a name before 1.21"),
+                    (true, Some(_)) => bail!("Attribute modifiers have no name since 1.21"),
+                    (true, None) => String::new(),
+                };
+                let operation = match (version.attribute_modifier_ids(), operation) {
+                    (false, AttributeOperation::Add) => "add",
+                    (false, AttributeOperation::MultiplyBase) => "multiply_base",
+                    (false, AttributeOperation::Multiply) => "multiply",
+                    (true, AttributeOperation::Add) => "add_value",
+                    (true, AttributeOperation::MultiplyBase) => "add_multiplied_base",
+                    (true, AttributeOperation::Multiply) => "add_multiplied_total",
+                };
+                format!(
+                    "modifier add {}{} {} {}",
+                    id.compile(version)?,
+                    name,
+                    amount,
+                    operation
+                )
+            }
+            AttributeAction::RemoveModifier(id) => {
+                format!("modifier remove {}", id.compile(version)?)
+            }
+            AttributeAction::GetModifier(id, scale) => {
+                format!(
+                    "modifier value get {}{}",
+                    id.compile(version)?,
+                    scaled(scale)
+                )
+            }
+        })
+    }
+}
+
+impl CommandLike for AttributeAction {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        self.command(MinecraftVersion::Latest)
+    }
+}
+
+/// Where the damage dealt by `damage` comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum DamageSource {
+    At(Location),
+    /// Entity matched by the first selector, optionally caused by the one matched by the second,
+    /// e.g. a skeleton that shot an arrow
+    By(String, Option<String>),
+}
+
+impl DamageSource {
+    pub fn by<S: IntoSelector>(entity: S) -> Self {
+        DamageSource::By(entity.selector(), None)
+    }
+
+    pub fn by_from<S: IntoSelector, C: IntoSelector>(entity: S, cause: C) -> Self {
+        DamageSource::By(entity.selector(), Some(cause.selector()))
+    }
+}
+
+impl CommandLike for DamageSource {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        Ok(match self {
+            DamageSource::At(at) => format!("at {}", at.to_string()),
+            DamageSource::By(entity, None) => format!("by {}", entity),
+            DamageSource::By(entity, Some(cause)) => format!("by {} from {}", entity, cause),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RideAction {
+    /// Mounts the entity matched by the selector
+    Mount(String),
+    Dismount,
+}
+
+impl RideAction {
+    pub fn mount<S: IntoSelector>(vehicle: S) -> Self {
+        RideAction::Mount(vehicle.selector())
+    }
+}
+
+impl CommandLike for RideAction {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        Ok(match self {
+            RideAction::Mount(vehicle) => format!("mount {}", vehicle),
+            RideAction::Dismount => "dismount".to_string(),
+        })
+    }
+}
+
 declare_commands! {
     generic[T: Into[String]] command "give" GiveCommand(
         req T selector,
@@ -143,5 +480,78 @@ declare_commands! {
         req EntityType entity,
         opt Location pos,
         opt EntityMeta nbt
+    );
+
+    command "kill" KillCommand(
+        sel targets
+    );
+
+    command "tp" TeleportCommand(
+        sel targets,
+        req Teleport destination
+    );
+
+    command "effect give" EffectGiveCommand(
+        sel targets,
+        req Effect effect,
+        opt EffectDuration duration,
+        opt_val u8 amplifier,
+        opt_val bool hide_particles
+    );
+
+    command "effect clear" EffectClearCommand(
+        sel targets,
+        opt Effect effect
+    );
+
+    command "enchant" EnchantCommand(
+        sel targets,
+        req Enchantment enchantment
+    );
+
+    command "tag" TagCommand(
+        sel targets,
+        req TagAction action
+    );
+
+    // compiles for the latest version, see `AttributeCommand::command`
+    command "attribute" AttributeCommand(
+        sel target,
+        req Attribute attribute,
+        req AttributeAction action
+    );
+
+    command "damage" DamageCommand(
+        sel target,
+        val f32 amount,
+        opt Identifier damage_type,
+        opt DamageSource source
+    );
+
+    command "ride" RideCommand(
+        sel target,
+        req RideAction action
+    );
+
+    // members default to the executing entity
+    command "team join" TeamJoinCommand(
+        req String team,
+        opt_sel members
     )
 }
+
+impl AttributeCommand {
+    /// Compiles the command for the version, since the syntax of modifiers changed in 1.21
+    pub fn command(&mut self, version: MinecraftVersion) -> anyhow::Result<String> {
+        match (&self.target, &self.attribute, &self.action) {
+            (Some(target), Some(attribute), Some(action)) => Ok(format!(
+                "attribute {} {} {}",
+                target,
+                attribute.to_string(),
+                action.command(version)?
+            )),
+            // reports the missing argument
+            _ => self.compile(),
+        }
+    }
+}
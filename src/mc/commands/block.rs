@@ -0,0 +1,581 @@
+//! Commands that edit blocks: `setblock`, `fill`, `clone` and `place`.
+//!
+//! Fills larger than [MAX_FILL_VOLUME] are split into several commands,
+//! which are compiled into separate lines.
+
+use std::fmt::{Display, Formatter};
+
+use anyhow::bail;
+
+use crate::mc::block::{BlockState, Coordinate, Location};
+use crate::mc::commands::CommandLike;
+use crate::mc::Identifier;
+use crate::nbt::{Compound, NbtTag};
+
+/// Maximum amount of blocks a single `fill` command can change
+pub const MAX_FILL_VOLUME: usize = 32768;
+
+/// What `setblock` does with the block that is already there
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SetBlockMode {
+    #[default]
+    Replace,
+    /// Breaks the old block, dropping its items
+    Destroy,
+    /// Only places the block if there is air
+    Keep,
+}
+
+impl Display for SetBlockMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SetBlockMode::Replace => "replace",
+            SetBlockMode::Destroy => "destroy",
+            SetBlockMode::Keep => "keep",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetBlockCommand {
+    pos: Location,
+    block: BlockState,
+    nbt: Option<Compound>,
+    mode: SetBlockMode,
+}
+
+impl SetBlockCommand {
+    pub fn new<B: Into<BlockState>>(pos: Location, block: B) -> Self {
+        Self {
+            pos,
+            block: block.into(),
+            nbt: None,
+            mode: SetBlockMode::default(),
+        }
+    }
+
+    /// Data of the block entity
+    pub fn nbt(&mut self, nbt: Compound) -> Self {
+        self.nbt = Some(nbt);
+        self.clone()
+    }
+
+    pub fn mode(&mut self, mode: SetBlockMode) -> Self {
+        self.mode = mode;
+        self.clone()
+    }
+}
+
+impl CommandLike for SetBlockCommand {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        let mut buf = format!("setblock {} {}", self.pos.to_string(), self.block);
+        if let Some(nbt) = &self.nbt {
+            buf.push_str(&NbtTag::Compound(nbt.clone()).stringify()?);
+        }
+        if self.mode != SetBlockMode::Replace {
+            buf.push_str(&format!(" {}", self.mode));
+        }
+        Ok(buf)
+    }
+}
+
+/// What `fill` does with the blocks in the region
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FillMode {
+    /// Replaces all blocks, or only the ones that match the block predicate
+    Replace(Option<String>),
+    /// Breaks the old blocks, dropping their items
+    Destroy,
+    /// Only fills the outer layer, replacing the inside with air
+    Hollow,
+    /// Only fills air
+    Keep,
+    /// Only fills the outer layer, keeping the inside
+    Outline,
+    /// Replaces all blocks, without writing the mode
+    #[default]
+    All,
+}
+
+impl FillMode {
+    /// Replaces only blocks that match the predicate, e.g. a block state or `#minecraft:logs`
+    pub fn replace<B: ToString>(filter: B) -> Self {
+        FillMode::Replace(Some(filter.to_string()))
+    }
+}
+
+impl Display for FillMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FillMode::Replace(None) => f.write_str("replace"),
+            FillMode::Replace(Some(filter)) => write!(f, "replace {}", filter),
+            FillMode::Destroy => f.write_str("destroy"),
+            FillMode::Hollow => f.write_str("hollow"),
+            FillMode::Keep => f.write_str("keep"),
+            FillMode::Outline => f.write_str("outline"),
+            FillMode::All => Ok(()),
+        }
+    }
+}
+
+/// Cuboid inside the filled region, relative to its lowest corner
+#[derive(Debug, Copy, Clone)]
+struct Cuboid {
+    start: [i32; 3],
+    size: [i32; 3],
+}
+
+impl Cuboid {
+    fn volume(&self) -> usize {
+        self.size.iter().map(|len| *len as usize).product()
+    }
+
+    /// Splits this cuboid into ones that can be filled with a single command,
+    /// keeping them as long as possible along x, then z, then y
+    fn split(&self) -> Vec<Cuboid> {
+        let [sx, sy, sz] = self.size;
+        let max = MAX_FILL_VOLUME as i32;
+        let cx = sx.min(max);
+        let cz = sz.min(max / cx);
+        let cy = sy.min(max / (cx * cz));
+        let mut parts = vec![];
+        for y in (0..sy).step_by(cy as usize) {
+            for z in (0..sz).step_by(cz as usize) {
+                for x in (0..sx).step_by(cx as usize) {
+                    parts.push(Cuboid {
+                        start: [self.start[0] + x, self.start[1] + y, self.start[2] + z],
+                        size: [cx.min(sx - x), cy.min(sy - y), cz.min(sz - z)],
+                    });
+                }
+            }
+        }
+        parts
+    }
+
+    /// Outer layer of this cuboid, as cuboids that do not overlap
+    fn shell(&self) -> Vec<Cuboid> {
+        let [x, y, z] = self.start;
+        let [sx, sy, sz] = self.size;
+        let cuboid = |start: [i32; 3], size: [i32; 3]| Cuboid { start, size };
+        vec![
+            cuboid([x, y, z], [sx, 1, sz]),
+            cuboid([x, y + sy - 1, z], [sx, 1, sz]),
+            cuboid([x, y + 1, z], [1, sy - 2, sz]),
+            cuboid([x + sx - 1, y + 1, z], [1, sy - 2, sz]),
+            cuboid([x + 1, y + 1, z], [sx - 2, sy - 2, 1]),
+            cuboid([x + 1, y + 1, z + sz - 1], [sx - 2, sy - 2, 1]),
+        ]
+    }
+
+    fn inside(&self) -> Cuboid {
+        Cuboid {
+            start: [self.start[0] + 1, self.start[1] + 1, self.start[2] + 1],
+            size: [self.size[0] - 2, self.size[1] - 2, self.size[2] - 2],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FillCommand {
+    from: Location,
+    to: Location,
+    block: BlockState,
+    mode: FillMode,
+}
+
+impl FillCommand {
+    pub fn new<B: Into<BlockState>>(from: Location, to: Location, block: B) -> Self {
+        Self {
+            from,
+            to,
+            block: block.into(),
+            mode: FillMode::default(),
+        }
+    }
+
+    pub fn mode(&mut self, mode: FillMode) -> Self {
+        self.mode = mode;
+        self.clone()
+    }
+
+    fn command(&self, from: Location, to: Location, block: &BlockState, mode: &FillMode) -> String {
+        let mut buf = format!("fill {} {} {}", from.to_string(), to.to_string(), block);
+        if *mode != FillMode::All {
+            buf.push_str(&format!(" {}", mode));
+        }
+        buf
+    }
+
+    /// Compiles this fill into commands, splitting it if it changes more than
+    /// [MAX_FILL_VOLUME] blocks. Split fills need both corners to be absolute or relative,
+    /// and hollow and outline fills are split into their outer layer and inside.
+    pub fn commands(&self) -> anyhow::Result<Vec<String>> {
+        let axes = [
+            (self.from.x(), self.to.x()),
+            (self.from.y(), self.to.y()),
+            (self.from.z(), self.to.z()),
+        ];
+        // corners can be on opposite ends of the i32 range, so lengths need more bits
+        let lengths = axes.map(|(from, to)| (from.pos() as i64 - to.pos() as i64).abs() + 1);
+        let volume = lengths.iter().try_fold(1usize, |volume, len| {
+            usize::try_from(*len)
+                .ok()
+                .and_then(|len| volume.checked_mul(len))
+        });
+        let volume = match volume {
+            Some(volume) => volume,
+            None => bail!("Fill is too large to be compiled"),
+        };
+        if volume <= MAX_FILL_VOLUME {
+            return Ok(vec![self.command(
+                self.from,
+                self.to,
+                &self.block,
+                &self.mode,
+            )]);
+        }
+
+        let mut min = [Coordinate::new(0); 3];
+        let mut size = [0; 3];
+        for (idx, (from, to)) in axes.into_iter().enumerate() {
+            // local coordinates are rotated, so the region can not be split in them
+            if from.is_local() || to.is_local() || from.is_relative() != to.is_relative() {
+                bail!("Fills larger than {} blocks can only be split if both corners use absolute or relative coordinates", MAX_FILL_VOLUME)
+            }
+            min[idx] = if from.pos() <= to.pos() { from } else { to };
+            size[idx] = match i32::try_from(lengths[idx]) {
+                Ok(len) => len,
+                Err(_) => bail!("Fill is too large to be compiled"),
+            };
+        }
+        let region = Cuboid {
+            start: [0; 3],
+            size,
+        };
+        let corner = |x: i32, y: i32, z: i32| {
+            Location::new(min[0].offset(x), min[1].offset(y), min[2].offset(z))
+        };
+        let compile = |cuboid: Cuboid, block: &BlockState, mode: &FillMode| {
+            cuboid
+                .split()
+                .into_iter()
+                .filter(|part| part.volume() > 0)
+                .map(|part| {
+                    let [x, y, z] = part.start;
+                    let [sx, sy, sz] = part.size;
+                    self.command(
+                        corner(x, y, z),
+                        corner(x + sx - 1, y + sy - 1, z + sz - 1),
+                        block,
+                        mode,
+                    )
+                })
+                .collect::<Vec<String>>()
+        };
+
+        let thin = size.iter().any(|len| *len <= 2);
+        Ok(match &self.mode {
+            // the whole region is the outer layer
+            FillMode::Hollow | FillMode::Outline if thin => {
+                compile(region, &self.block, &FillMode::All)
+            }
+            FillMode::Hollow | FillMode::Outline => {
+                let mut commands = vec![];
+                for side in region.shell() {
+                    commands.extend(compile(side, &self.block, &FillMode::All));
+                }
+                if self.mode == FillMode::Hollow {
+                    commands.extend(compile(region.inside(), &BlockState::air(), &FillMode::All));
+                }
+                commands
+            }
+            mode => compile(region, &self.block, mode),
+        })
+    }
+}
+
+impl CommandLike for FillCommand {
+    /// Compiles this fill, with each part on a separate line if it had to be split
+    fn compile(&mut self) -> anyhow::Result<String> {
+        Ok(self.commands()?.join("\n"))
+    }
+}
+
+/// Which blocks `clone` copies
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum CloneMask {
+    #[default]
+    Replace,
+    /// Copies all blocks except air
+    Masked,
+    /// Copies only blocks that match the block predicate
+    Filtered(String),
+}
+
+/// How `clone` treats the source region
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum CloneMode {
+    #[default]
+    Normal,
+    /// Allows the source and destination regions to overlap
+    Force,
+    /// Replaces the copied blocks in the source region with air
+    Move,
+}
+
+impl Display for CloneMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CloneMode::Normal => "normal",
+            CloneMode::Force => "force",
+            CloneMode::Move => "move",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloneCommand {
+    begin: Location,
+    end: Location,
+    destination: Location,
+    source_dimension: Option<Identifier>,
+    target_dimension: Option<Identifier>,
+    mask: CloneMask,
+    mode: CloneMode,
+}
+
+impl CloneCommand {
+    /// Copies the region from begin to end, so that its lowest corner is at the destination
+    pub fn new(begin: Location, end: Location, destination: Location) -> Self {
+        Self {
+            begin,
+            end,
+            destination,
+            source_dimension: None,
+            target_dimension: None,
+            mask: CloneMask::default(),
+            mode: CloneMode::default(),
+        }
+    }
+
+    /// Dimension to copy the blocks from, since 1.20.2
+    pub fn from_dimension(&mut self, dimension: Identifier) -> Self {
+        self.source_dimension = Some(dimension);
+        self.clone()
+    }
+
+    /// Dimension to copy the blocks to, since 1.20.2
+    pub fn to_dimension(&mut self, dimension: Identifier) -> Self {
+        self.target_dimension = Some(dimension);
+        self.clone()
+    }
+
+    pub fn mask(&mut self, mask: CloneMask) -> Self {
+        self.mask = mask;
+        self.clone()
+    }
+
+    pub fn mode(&mut self, mode: CloneMode) -> Self {
+        self.mode = mode;
+        self.clone()
+    }
+}
+
+impl CommandLike for CloneCommand {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        let mut buf = String::from("clone");
+        if let Some(dimension) = &self.source_dimension {
+            buf.push_str(&format!(" from {}", dimension));
+        }
+        buf.push_str(&format!(
+            " {} {}",
+            self.begin.to_string(),
+            self.end.to_string()
+        ));
+        if let Some(dimension) = &self.target_dimension {
+            buf.push_str(&format!(" to {}", dimension));
+        }
+        buf.push_str(&format!(" {}", self.destination.to_string()));
+        // the mode can only follow the mask, so default masks are written for other modes
+        match &self.mask {
+            CloneMask::Replace if self.mode == CloneMode::Normal => {}
+            CloneMask::Replace => buf.push_str(" replace"),
+            CloneMask::Masked => buf.push_str(" masked"),
+            CloneMask::Filtered(filter) => buf.push_str(&format!(" filtered {}", filter)),
+        }
+        if self.mode != CloneMode::Normal {
+            buf.push_str(&format!(" {}", self.mode));
+        }
+        Ok(buf)
+    }
+}
+
+/// Rotation of templates placed by `place template`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StructureRotation {
+    None,
+    Clockwise90,
+    Rotate180,
+    Counterclockwise90,
+}
+
+impl Display for StructureRotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StructureRotation::None => "none",
+            StructureRotation::Clockwise90 => "clockwise_90",
+            StructureRotation::Rotate180 => "180",
+            StructureRotation::Counterclockwise90 => "counterclockwise_90",
+        })
+    }
+}
+
+/// Mirroring of templates placed by `place template`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StructureMirror {
+    None,
+    FrontBack,
+    LeftRight,
+}
+
+impl Display for StructureMirror {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StructureMirror::None => "none",
+            StructureMirror::FrontBack => "front_back",
+            StructureMirror::LeftRight => "left_right",
+        })
+    }
+}
+
+/// `place` command, since 1.19. Positions default to the current one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaceCommand {
+    /// Configured feature, e.g. `minecraft:oak`
+    Feature(Identifier, Option<Location>),
+    /// Jigsaw structure generated from the start pool, up to the depth
+    Jigsaw {
+        pool: Identifier,
+        target: Identifier,
+        max_depth: i32,
+        pos: Option<Location>,
+    },
+    /// Configured structure, e.g. `minecraft:village_plains`
+    Structure(Identifier, Option<Location>),
+    /// Structure template, e.g. one written by
+    /// [create_structure](crate::modules::context::GlobalModuleContext::create_structure)
+    Template {
+        id: Identifier,
+        pos: Option<Location>,
+        rotation: Option<StructureRotation>,
+        mirror: Option<StructureMirror>,
+        integrity: Option<f32>,
+        seed: Option<i32>,
+    },
+}
+
+impl PlaceCommand {
+    pub fn template(id: Identifier) -> Self {
+        PlaceCommand::Template {
+            id,
+            pos: None,
+            rotation: None,
+            mirror: None,
+            integrity: None,
+            seed: None,
+        }
+    }
+
+    /// Sets the position of any kind of placement
+    pub fn at(&mut self, at: Location) -> Self {
+        match self {
+            PlaceCommand::Feature(_, pos)
+            | PlaceCommand::Jigsaw { pos, .. }
+            | PlaceCommand::Structure(_, pos)
+            | PlaceCommand::Template { pos, .. } => *pos = Some(at),
+        }
+        self.clone()
+    }
+
+    /// Sets rotation, mirroring, integrity from 0 to 1 and seed of a template placement.
+    /// Fails for other kinds of placements, which can not be transformed.
+    pub fn transform(
+        &mut self,
+        rotation: StructureRotation,
+        mirror: StructureMirror,
+        integrity: f32,
+        seed: i32,
+    ) -> anyhow::Result<Self> {
+        match self {
+            PlaceCommand::Template {
+                rotation: r,
+                mirror: m,
+                integrity: i,
+                seed: s,
+                ..
+            } => {
+                *r = Some(rotation);
+                *m = Some(mirror);
+                *i = Some(integrity);
+                *s = Some(seed);
+            }
+            _ => bail!("Only templates can be transformed"),
+        }
+        Ok(self.clone())
+    }
+}
+
+fn position(pos: &Option<Location>) -> String {
+    pos.map(|pos| format!(" {}", pos.to_string()))
+        .unwrap_or_default()
+}
+
+impl CommandLike for PlaceCommand {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        Ok(match self {
+            PlaceCommand::Feature(id, pos) => format!("place feature {}{}", id, position(pos)),
+            PlaceCommand::Jigsaw {
+                pool,
+                target,
+                max_depth,
+                pos,
+            } => format!(
+                "place jigsaw {} {} {}{}",
+                pool,
+                target,
+                max_depth,
+                position(pos)
+            ),
+            PlaceCommand::Structure(id, pos) => {
+                format!("place structure {}{}", id, position(pos))
+            }
+            PlaceCommand::Template {
+                id,
+                pos,
+                rotation,
+                mirror,
+                integrity,
+                seed,
+            } => {
+                // arguments are positional, so the ones before a set argument get defaults
+                let args = [
+                    pos.map(|pos| pos.to_string()),
+                    rotation.map(|rotation| rotation.to_string()),
+                    mirror.map(|mirror| mirror.to_string()),
+                    integrity.map(|integrity| integrity.to_string()),
+                    seed.map(|seed| seed.to_string()),
+                ];
+                let defaults = ["~ ~ ~", "none", "none", "1", "0"];
+                let count = args
+                    .iter()
+                    .rposition(Option::is_some)
+                    .map_or(0, |idx| idx + 1);
+                let mut buf = format!("place template {}", id);
+                for (arg, default) in args.into_iter().zip(defaults).take(count) {
+                    buf.push_str(&format!(" {}", arg.unwrap_or(default.to_string())));
+                }
+                buf
+            }
+        })
+    }
+}
@@ -0,0 +1,525 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::bail;
+
+use crate::mc::commands::CommandLike;
+use crate::mc::data::DataTarget;
+use crate::mc::entity::types::EntityType;
+use crate::mc::entity::IntoSelector;
+use crate::mc::{Identified, Identifier};
+use crate::nbt::path::{try_path, NbtPath};
+use crate::prelude::Location;
+
+/// Part of the entity that `anchored` and `facing entity` aim from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Anchor {
+    Feet,
+    Eyes,
+}
+
+impl Display for Anchor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Anchor::Feet => "feet",
+            Anchor::Eyes => "eyes",
+        })
+    }
+}
+
+/// Entity related to the executor, used by `execute on`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Relation {
+    Attacker,
+    Controller,
+    Leasher,
+    Origin,
+    Owner,
+    Passengers,
+    Target,
+    Vehicle,
+}
+
+impl Display for Relation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Relation::Attacker => "attacker",
+            Relation::Controller => "controller",
+            Relation::Leasher => "leasher",
+            Relation::Origin => "origin",
+            Relation::Owner => "owner",
+            Relation::Passengers => "passengers",
+            Relation::Target => "target",
+            Relation::Vehicle => "vehicle",
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "=",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater => ">",
+        })
+    }
+}
+
+/// Inclusive integer range, e.g. `1..5`, `..0` or `10`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IntRange {
+    min: Option<i32>,
+    max: Option<i32>,
+}
+
+impl IntRange {
+    /// Range between the bounds, failing if it has none or the minimum is above the maximum
+    pub fn new(min: Option<i32>, max: Option<i32>) -> anyhow::Result<Self> {
+        match (min, max) {
+            (None, None) => bail!("Range must have at least one bound"),
+            (Some(min), Some(max)) if min > max => {
+                bail!(
+                    "Minimum {} of a range is larger than its maximum {}",
+                    min,
+                    max
+                )
+            }
+            _ => Ok(Self { min, max }),
+        }
+    }
+
+    pub fn exactly(value: i32) -> Self {
+        Self {
+            min: Some(value),
+            max: Some(value),
+        }
+    }
+}
+
+impl Display for IntRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) if min == max => write!(f, "{}", min),
+            (Some(min), Some(max)) => write!(f, "{}..{}", min, max),
+            (Some(min), None) => write!(f, "{}..", min),
+            (None, Some(max)) => write!(f, "..{}", max),
+            (None, None) => f.write_str(".."),
+        }
+    }
+}
+
+/// Test of a score in `if score`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScoreTest {
+    /// Compares with the score of another holder
+    Compare(Comparison, String, String),
+    /// Checks that the score is in the range
+    Matches(IntRange),
+}
+
+/// Condition of `execute if` and `execute unless`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecuteCondition {
+    /// Block at the location matches the block predicate, e.g. a block state or `#minecraft:logs`
+    Block(Location, String),
+    /// Blocks in the region from start to end match the blocks in the destination region.
+    /// Air is ignored if the comparison is masked.
+    Blocks {
+        start: Location,
+        end: Location,
+        destination: Location,
+        masked: bool,
+    },
+    /// Selector matches at least one entity
+    Entity(String),
+    Score {
+        holder: String,
+        objective: String,
+        test: ScoreTest,
+    },
+    /// Path exists in the data target
+    Data(DataTarget, NbtPath),
+    Predicate(Identifier),
+}
+
+impl ExecuteCondition {
+    pub fn block<B: ToString>(at: Location, block: B) -> Self {
+        ExecuteCondition::Block(at, block.to_string())
+    }
+
+    pub fn entity<S: IntoSelector>(selector: S) -> Self {
+        ExecuteCondition::Entity(selector.selector())
+    }
+
+    pub fn score_matches<S: IntoSelector, O: Into<String>>(
+        holder: S,
+        objective: O,
+        range: IntRange,
+    ) -> Self {
+        ExecuteCondition::Score {
+            holder: holder.selector(),
+            objective: objective.into(),
+            test: ScoreTest::Matches(range),
+        }
+    }
+
+    pub fn score_compare<H, O, S, SO>(
+        holder: H,
+        objective: O,
+        comparison: Comparison,
+        source: S,
+        source_objective: SO,
+    ) -> Self
+    where
+        H: IntoSelector,
+        O: Into<String>,
+        S: IntoSelector,
+        SO: Into<String>,
+    {
+        ExecuteCondition::Score {
+            holder: holder.selector(),
+            objective: objective.into(),
+            test: ScoreTest::Compare(comparison, source.selector(), source_objective.into()),
+        }
+    }
+
+    pub fn data<P>(target: DataTarget, path: P) -> anyhow::Result<Self>
+    where
+        P: TryInto<NbtPath>,
+        P::Error: Into<anyhow::Error>,
+    {
+        Ok(ExecuteCondition::Data(target, try_path(path)?))
+    }
+}
+
+impl Display for ExecuteCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecuteCondition::Block(at, block) => write!(f, "block {} {}", at.to_string(), block),
+            ExecuteCondition::Blocks {
+                start,
+                end,
+                destination,
+                masked,
+            } => write!(
+                f,
+                "blocks {} {} {} {}",
+                start.to_string(),
+                end.to_string(),
+                destination.to_string(),
+                if *masked { "masked" } else { "all" }
+            ),
+            ExecuteCondition::Entity(selector) => write!(f, "entity {}", selector),
+            ExecuteCondition::Score {
+                holder,
+                objective,
+                test,
+            } => {
+                write!(f, "score {} {} ", holder, objective)?;
+                match test {
+                    ScoreTest::Compare(comparison, source, source_objective) => {
+                        write!(f, "{} {} {}", comparison, source, source_objective)
+                    }
+                    ScoreTest::Matches(range) => write!(f, "matches {}", range),
+                }
+            }
+            ExecuteCondition::Data(target, path) => write!(f, "data {} {}", target, path),
+            ExecuteCondition::Predicate(id) => write!(f, "predicate {}", id),
+        }
+    }
+}
+
+/// Numeric type that `execute store` writes into NBT
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StoreType {
+    Byte,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+}
+
+impl Display for StoreType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StoreType::Byte => "byte",
+            StoreType::Short => "short",
+            StoreType::Int => "int",
+            StoreType::Long => "long",
+            StoreType::Float => "float",
+            StoreType::Double => "double",
+        })
+    }
+}
+
+/// Where `execute store` puts the result or success of the command
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreTarget {
+    Score {
+        holder: String,
+        objective: String,
+    },
+    /// NBT at the path of the target, multiplied by the scale
+    Data {
+        target: DataTarget,
+        path: NbtPath,
+        store_type: StoreType,
+        scale: f64,
+    },
+    /// Current value or the maximum of the bossbar
+    Bossbar {
+        id: Identifier,
+        max: bool,
+    },
+}
+
+impl StoreTarget {
+    pub fn score<S: IntoSelector, O: Into<String>>(holder: S, objective: O) -> Self {
+        StoreTarget::Score {
+            holder: holder.selector(),
+            objective: objective.into(),
+        }
+    }
+
+    pub fn data<P>(
+        target: DataTarget,
+        path: P,
+        store_type: StoreType,
+        scale: f64,
+    ) -> anyhow::Result<Self>
+    where
+        P: TryInto<NbtPath>,
+        P::Error: Into<anyhow::Error>,
+    {
+        Ok(StoreTarget::Data {
+            target,
+            path: try_path(path)?,
+            store_type,
+            scale,
+        })
+    }
+}
+
+impl Display for StoreTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreTarget::Score { holder, objective } => {
+                write!(f, "score {} {}", holder, objective)
+            }
+            StoreTarget::Data {
+                target,
+                path,
+                store_type,
+                scale,
+            } => write!(f, "{} {} {} {}", target, path, store_type, scale),
+            StoreTarget::Bossbar { id, max } => {
+                write!(f, "bossbar {} {}", id, if *max { "max" } else { "value" })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Subcommand {
+    As(String),
+    At(String),
+    Positioned(Location),
+    PositionedAs(String),
+    Rotated(f32, f32),
+    RotatedAs(String),
+    Facing(Location),
+    FacingEntity(String, Anchor),
+    Anchored(Anchor),
+    Align(String),
+    In(Identifier),
+    On(Relation),
+    Summon(EntityType),
+    If(ExecuteCondition),
+    Unless(ExecuteCondition),
+    StoreResult(StoreTarget),
+    StoreSuccess(StoreTarget),
+}
+
+impl Display for Subcommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Subcommand::As(selector) => write!(f, "as {}", selector),
+            Subcommand::At(selector) => write!(f, "at {}", selector),
+            Subcommand::Positioned(pos) => write!(f, "positioned {}", pos.to_string()),
+            Subcommand::PositionedAs(selector) => write!(f, "positioned as {}", selector),
+            Subcommand::Rotated(yaw, pitch) => write!(f, "rotated {} {}", yaw, pitch),
+            Subcommand::RotatedAs(selector) => write!(f, "rotated as {}", selector),
+            Subcommand::Facing(pos) => write!(f, "facing {}", pos.to_string()),
+            Subcommand::FacingEntity(selector, anchor) => {
+                write!(f, "facing entity {} {}", selector, anchor)
+            }
+            Subcommand::Anchored(anchor) => write!(f, "anchored {}", anchor),
+            Subcommand::Align(axes) => write!(f, "align {}", axes),
+            Subcommand::In(dimension) => write!(f, "in {}", dimension),
+            Subcommand::On(relation) => write!(f, "on {}", relation),
+            Subcommand::Summon(entity) => write!(f, "summon {}", entity.id()),
+            Subcommand::If(condition) => write!(f, "if {}", condition),
+            Subcommand::Unless(condition) => write!(f, "unless {}", condition),
+            Subcommand::StoreResult(target) => write!(f, "store result {}", target),
+            Subcommand::StoreSuccess(target) => write!(f, "store success {}", target),
+        }
+    }
+}
+
+/// Builder of `/execute` commands. Subcommands are written in the order they are added:
+///
+/// ```ignore
+/// let cmd = ExecuteCommand::new()
+///     .as_(Selector::AllPlayers)
+///     .at(Selector::Executor)
+///     .if_(ExecuteCondition::block(Location::relative(0, -1, 0), "minecraft:stone"))
+///     .run(SummonCommand::new(EntityType::Pig, None, None))?
+///     .compile()?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExecuteCommand {
+    subcommands: Vec<Subcommand>,
+    run: Option<String>,
+}
+
+impl ExecuteCommand {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(&mut self, subcommand: Subcommand) -> Self {
+        self.subcommands.push(subcommand);
+        self.clone()
+    }
+
+    /// Changes the executor to each entity matched by the selector
+    pub fn as_<S: IntoSelector>(&mut self, selector: S) -> Self {
+        self.push(Subcommand::As(selector.selector()))
+    }
+
+    /// Changes the position, rotation and dimension to the ones of each matched entity
+    pub fn at<S: IntoSelector>(&mut self, selector: S) -> Self {
+        self.push(Subcommand::At(selector.selector()))
+    }
+
+    pub fn positioned(&mut self, pos: Location) -> Self {
+        self.push(Subcommand::Positioned(pos))
+    }
+
+    pub fn positioned_as<S: IntoSelector>(&mut self, selector: S) -> Self {
+        self.push(Subcommand::PositionedAs(selector.selector()))
+    }
+
+    pub fn rotated(&mut self, yaw: f32, pitch: f32) -> Self {
+        self.push(Subcommand::Rotated(yaw, pitch))
+    }
+
+    pub fn rotated_as<S: IntoSelector>(&mut self, selector: S) -> Self {
+        self.push(Subcommand::RotatedAs(selector.selector()))
+    }
+
+    pub fn facing(&mut self, pos: Location) -> Self {
+        self.push(Subcommand::Facing(pos))
+    }
+
+    pub fn facing_entity<S: IntoSelector>(&mut self, selector: S, anchor: Anchor) -> Self {
+        self.push(Subcommand::FacingEntity(selector.selector(), anchor))
+    }
+
+    pub fn anchored(&mut self, anchor: Anchor) -> Self {
+        self.push(Subcommand::Anchored(anchor))
+    }
+
+    /// Floors the position on the axes, e.g. `xz`, failing for anything but distinct axes
+    pub fn align<S: Into<String>>(&mut self, axes: S) -> anyhow::Result<Self> {
+        let axes = axes.into();
+        let mut seen = String::new();
+        for axis in axes.chars() {
+            if !"xyz".contains(axis) || seen.contains(axis) {
+                bail!("Axes {:?} can only contain each of x, y and z once", axes)
+            }
+            seen.push(axis);
+        }
+        if axes.is_empty() {
+            bail!("Axes to align to can not be empty")
+        }
+        Ok(self.push(Subcommand::Align(axes)))
+    }
+
+    /// Changes the dimension, e.g. `minecraft:the_nether`
+    pub fn in_(&mut self, dimension: Identifier) -> Self {
+        self.push(Subcommand::In(dimension))
+    }
+
+    /// Changes the executor to the related entity, since 1.19.4
+    pub fn on(&mut self, relation: Relation) -> Self {
+        self.push(Subcommand::On(relation))
+    }
+
+    /// Summons the entity and makes it the executor, since 1.19.4
+    pub fn summon(&mut self, entity: EntityType) -> Self {
+        self.push(Subcommand::Summon(entity))
+    }
+
+    pub fn if_(&mut self, condition: ExecuteCondition) -> Self {
+        self.push(Subcommand::If(condition))
+    }
+
+    pub fn unless(&mut self, condition: ExecuteCondition) -> Self {
+        self.push(Subcommand::Unless(condition))
+    }
+
+    /// Stores the result of the command, e.g. the amount of matched entities
+    pub fn store_result(&mut self, target: StoreTarget) -> Self {
+        self.push(Subcommand::StoreResult(target))
+    }
+
+    /// Stores 1 if the command succeeded and 0 otherwise
+    pub fn store_success(&mut self, target: StoreTarget) -> Self {
+        self.push(Subcommand::StoreSuccess(target))
+    }
+
+    /// Sets the command to run, compiling it right away
+    pub fn run<C: CommandLike>(&mut self, mut command: C) -> anyhow::Result<Self> {
+        let command = command.compile()?;
+        // split fills compile into several lines, which can not all be run
+        if command.contains('\n') {
+            bail!("Only a single command can be run by execute")
+        }
+        self.run = Some(command);
+        Ok(self.clone())
+    }
+}
+
+impl CommandLike for ExecuteCommand {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        let mut buf = String::from("execute");
+        for subcommand in &self.subcommands {
+            buf.push_str(&format!(" {}", subcommand));
+        }
+        match &self.run {
+            Some(run) => buf.push_str(&format!(" run {}", run)),
+            // without a command, only conditions can end the chain
+            None if !matches!(
+                self.subcommands.last(),
+                Some(Subcommand::If(_) | Subcommand::Unless(_))
+            ) =>
+            {
+                bail!("Execute command must end with a condition or a command to run")
+            }
+            None => {}
+        }
+        Ok(buf)
+    }
+}
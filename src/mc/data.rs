@@ -1,15 +1,246 @@
+use crate::mc::block::Location;
+use crate::mc::commands::CommandLike;
+use crate::mc::entity::IntoSelector;
 use crate::mc::Identifier;
 use crate::modules::functions::FunctionWriter;
-use crate::nbt::{IntoTag, NbtTag};
+use crate::nbt::diff::diff;
+use crate::nbt::path::{try_path, NbtPath};
+use crate::nbt::{Compound, IntoTag, NbtTag};
 use anyhow::bail;
-use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::io::Write;
 use std::sync::{Arc, Mutex};
 
+/// Holder of NBT that `/data` and `/execute` commands can access
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataTarget {
+    /// Block entity at the location
+    Block(Location),
+    /// Entity matched by the selector, which must match at most one entity
+    Entity(String),
+    /// Command storage with the id
+    Storage(Identifier),
+}
+
+impl DataTarget {
+    pub fn entity<S: IntoSelector>(selector: S) -> Self {
+        DataTarget::Entity(selector.selector())
+    }
+}
+
+impl Display for DataTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataTarget::Block(at) => write!(f, "block {}", at.to_string()),
+            DataTarget::Entity(selector) => write!(f, "entity {}", selector),
+            DataTarget::Storage(id) => write!(f, "storage {}", id),
+        }
+    }
+}
+
+/// Where `data modify` takes the new value from
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataSource {
+    /// Literal value
+    Value(NbtTag),
+    /// Tag at the path of the target, or the whole target without a path
+    From(DataTarget, Option<NbtPath>),
+    /// Tag at the path of the target, converted into a string and sliced from start
+    /// to end, which can be negative to count from the end. Since 1.19.4.
+    String {
+        target: DataTarget,
+        path: Option<NbtPath>,
+        start: Option<i32>,
+        end: Option<i32>,
+    },
+}
+
+impl DataSource {
+    pub fn value<V: IntoTag>(value: V) -> Self {
+        DataSource::Value(value.nbt())
+    }
+
+    pub fn from_target<P>(target: DataTarget, path: P) -> anyhow::Result<Self>
+    where
+        P: TryInto<NbtPath>,
+        P::Error: Into<anyhow::Error>,
+    {
+        Ok(DataSource::From(target, Some(try_path(path)?)))
+    }
+
+    pub fn string<P>(target: DataTarget, path: P) -> anyhow::Result<Self>
+    where
+        P: TryInto<NbtPath>,
+        P::Error: Into<anyhow::Error>,
+    {
+        Ok(DataSource::String {
+            target,
+            path: Some(try_path(path)?),
+            start: None,
+            end: None,
+        })
+    }
+
+    fn compile(&self) -> anyhow::Result<String> {
+        Ok(match self {
+            DataSource::Value(value) => format!("value {}", value.stringify()?),
+            DataSource::From(target, None) => format!("from {}", target),
+            DataSource::From(target, Some(path)) => format!("from {} {}", target, path),
+            DataSource::String {
+                target,
+                path,
+                start,
+                end,
+            } => {
+                let mut buf = format!("string {}", target);
+                match (path, start, end) {
+                    (None, Some(_), _) | (_, None, Some(_)) => {
+                        bail!("String source can only have an end after a start, and a start after a path")
+                    }
+                    _ => {}
+                }
+                for arg in [
+                    path.as_ref().map(NbtPath::to_string),
+                    start.map(|start| start.to_string()),
+                    end.map(|end| end.to_string()),
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    buf.push_str(&format!(" {}", arg));
+                }
+                buf
+            }
+        })
+    }
+}
+
+/// How `data modify` puts the source into the path
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ModifyOperation {
+    /// Adds to the end of the list
+    Append,
+    /// Adds to the start of the list
+    Prepend,
+    /// Inserts into the list at the index, which can be negative to count from the end
+    Insert(i32),
+    /// Merges into the compound
+    Merge,
+    /// Replaces the tag
+    Set,
+}
+
+impl Display for ModifyOperation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModifyOperation::Append => f.write_str("append"),
+            ModifyOperation::Prepend => f.write_str("prepend"),
+            ModifyOperation::Insert(idx) => write!(f, "insert {}", idx),
+            ModifyOperation::Merge => f.write_str("merge"),
+            ModifyOperation::Set => f.write_str("set"),
+        }
+    }
+}
+
+/// Single `/data` command
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataCommand {
+    /// Returns the tag at the path, or the whole target. Numbers are multiplied by the scale.
+    Get {
+        target: DataTarget,
+        path: Option<NbtPath>,
+        scale: Option<f64>,
+    },
+    /// Merges the compound into the target
+    Merge(DataTarget, Compound),
+    Remove(DataTarget, NbtPath),
+    Modify {
+        target: DataTarget,
+        path: NbtPath,
+        operation: ModifyOperation,
+        source: DataSource,
+    },
+}
+
+impl DataCommand {
+    pub fn get<P>(target: DataTarget, path: P) -> anyhow::Result<Self>
+    where
+        P: TryInto<NbtPath>,
+        P::Error: Into<anyhow::Error>,
+    {
+        Ok(DataCommand::Get {
+            target,
+            path: Some(try_path(path)?),
+            scale: None,
+        })
+    }
+
+    pub fn remove<P>(target: DataTarget, path: P) -> anyhow::Result<Self>
+    where
+        P: TryInto<NbtPath>,
+        P::Error: Into<anyhow::Error>,
+    {
+        Ok(DataCommand::Remove(target, try_path(path)?))
+    }
+
+    pub fn modify<P>(
+        target: DataTarget,
+        path: P,
+        operation: ModifyOperation,
+        source: DataSource,
+    ) -> anyhow::Result<Self>
+    where
+        P: TryInto<NbtPath>,
+        P::Error: Into<anyhow::Error>,
+    {
+        Ok(DataCommand::Modify {
+            target,
+            path: try_path(path)?,
+            operation,
+            source,
+        })
+    }
+}
+
+impl CommandLike for DataCommand {
+    fn compile(&mut self) -> anyhow::Result<String> {
+        Ok(match self {
+            DataCommand::Get {
+                target,
+                path,
+                scale,
+            } => match (path, scale) {
+                (None, None) => format!("data get {}", target),
+                (Some(path), None) => format!("data get {} {}", target, path),
+                (Some(path), Some(scale)) => format!("data get {} {} {}", target, path, scale),
+                (None, Some(_)) => bail!("Only tags at a path can be scaled"),
+            },
+            DataCommand::Merge(target, comp) => format!(
+                "data merge {} {}",
+                target,
+                NbtTag::Compound(comp.clone()).stringify()?
+            ),
+            DataCommand::Remove(target, path) => format!("data remove {} {}", target, path),
+            DataCommand::Modify {
+                target,
+                path,
+                operation,
+                source,
+            } => format!(
+                "data modify {} {} {} {}",
+                target,
+                path,
+                operation,
+                source.compile()?
+            ),
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DataAccess<W> {
     name: Identifier,
-    cache: HashMap<String, NbtTag>,
+    cache: NbtTag,
     writer: Arc<Mutex<FunctionWriter<W>>>,
 }
 
@@ -20,41 +251,62 @@ where
     pub fn new(named: Identifier, writer: Arc<Mutex<FunctionWriter<W>>>) -> Self {
         Self {
             name: named,
-            cache: Default::default(),
+            cache: NbtTag::Compound(Compound::default()),
             writer,
         }
     }
 
-    pub fn set<K: Into<String>, V: IntoTag>(&mut self, key: K, value: V) -> anyhow::Result<()> {
-        let k = key.into();
+    fn target(&self) -> DataTarget {
+        DataTarget::Storage(self.name.clone())
+    }
+
+    pub fn set<K, V>(&mut self, key: K, value: V) -> anyhow::Result<()>
+    where
+        K: TryInto<NbtPath>,
+        K::Error: Into<anyhow::Error>,
+        V: IntoTag,
+    {
+        let k = try_path(key)?;
         let v = value.nbt();
 
-        let _ = self.cache.insert(k.clone(), v.clone());
+        k.set(&mut self.cache, v.clone())?;
+        let mut cmd =
+            DataCommand::modify(self.target(), k, ModifyOperation::Set, DataSource::Value(v))?;
         self.writer
             .lock()
             .unwrap()
-            .write_line(format!(
-                "data modify storage {} {} set value {}",
-                self.name,
-                k,
-                v.stringify()
-            ))
-            .map_err(|it| anyhow::Error::from(it))?;
+            .write_line(format!("{}\n", cmd.compile()?))?;
 
         Ok(())
     }
 
-    pub fn get<K: Into<String>>(&self, key: K) -> anyhow::Result<NbtTag> {
-        let strkey = key.into();
-        if let Some(v) = self.cache.get(&strkey) {
-            Ok(v.clone())
+    pub fn get<K>(&self, key: K) -> anyhow::Result<NbtTag>
+    where
+        K: TryInto<NbtPath>,
+        K::Error: Into<anyhow::Error>,
+    {
+        let path = try_path(key)?;
+        if let Some(v) = path.get(&self.cache).first() {
+            Ok((*v).clone())
         } else {
-            bail!("Data storage did not contain tag with key {}", strkey)
+            bail!("Data storage did not contain tag with key {}", path)
         }
     }
 
+    /// Replaces the whole storage with the compound, only writing commands for the changed tags
+    pub fn replace(&mut self, data: Compound) -> anyhow::Result<()> {
+        let data = NbtTag::Compound(data);
+        let mut writer = self.writer.lock().unwrap();
+        for patch in diff(&self.cache, &data) {
+            writer.write_line(format!("{}\n", patch.command(self.target().to_string())?))?;
+        }
+        drop(writer);
+        self.cache = data;
+        Ok(())
+    }
+
     pub fn clear(&mut self) {
-        self.cache.clear();
+        self.cache = NbtTag::Compound(Compound::default());
         self.writer.lock().unwrap().close()
     }
 }
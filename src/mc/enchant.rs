@@ -78,6 +78,10 @@ impl Enchantment {
             lvl: level,
         }
     }
+
+    pub fn level(&self) -> i32 {
+        self.lvl
+    }
 }
 
 impl Identified for Enchantment {
@@ -88,11 +92,11 @@ impl Identified for Enchantment {
 
 impl Into<NbtTag> for Enchantment {
     fn into(self) -> NbtTag {
-        let id = self.id();
-        let lvl = self.lvl;
         NbtTag::Compound(nbt! {
-            id: id,
-            lvl: lvl
+            id: self.id(),
+            lvl: self.lvl
         })
     }
 }
+
+crate::__to_tag_by_clone!(Enchant, Enchantment);
@@ -4,13 +4,15 @@ pub mod model;
 pub mod types;
 
 use crate::mc::world::WorldAccess;
-use std::collections::HashMap;
+use indexmap::IndexMap;
 use std::hash::Hash;
 use std::io::Write;
 
+use crate::mc::item::MetaContainer;
 use crate::nbt;
+use crate::nbt::diff::diff;
 use crate::nbt::NbtTag;
-use crate::prelude::{EntityMeta, EntityType, Identified};
+use crate::prelude::{EntityMeta, EntityType, Identified, Location};
 
 pub trait IntoSelector: Clone {
     fn selector(&self) -> String;
@@ -41,21 +43,21 @@ impl IntoSelector for Selector {
             Selector::AllPlayers => "@a",
             Selector::NearestPlayer => "@p",
             Selector::RandomPlayer => "@r",
-            Selector::Executor => "@c",
+            Selector::Executor => "@s",
         }
         .to_string()
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct FullSelector<S>(Selector, HashMap<S, S>);
+pub struct FullSelector<S>(Selector, IndexMap<S, S>);
 
 impl<S> FullSelector<S>
 where
     S: Into<String> + Eq + Hash,
 {
     pub fn new<const N: usize>(selector: Selector, params: [(S, S); N]) -> Self {
-        Self(selector, HashMap::from(params))
+        Self(selector, IndexMap::from(params))
     }
 }
 
@@ -102,17 +104,15 @@ impl AttributeModifier {
 
 impl Into<NbtTag> for AttributeModifier {
     fn into(self) -> NbtTag {
-        let attr = self.attribute;
-        let amount = self.amount;
-        let oper = self.operation;
         NbtTag::Compound(nbt! {
-            AttributeName: attr,
-            Amount: amount,
-            Operation: oper
+            AttributeName: self.attribute,
+            Amount: self.amount,
+            Operation: self.operation
         })
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum AttributeOperation {
     Add,
     MultiplyBase,
@@ -180,12 +180,15 @@ impl Into<NbtTag> for Attribute {
     }
 }
 
+crate::__to_tag_by_clone!(Attribute, AttributeModifier);
+
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct Entity {
     ty: EntityType,
     pub(crate) meta: EntityMeta,
     pub(crate) id: u64,
+    synced: Option<NbtTag>,
 }
 
 impl Entity {
@@ -194,6 +197,7 @@ impl Entity {
             ty,
             meta: EntityMeta::new(ty),
             id: rand::random(),
+            synced: None,
         }
     }
 
@@ -209,13 +213,58 @@ impl Entity {
         self.meta = modifier(&mut self.meta)
     }
 
-    pub fn save<W: Write>(&mut self, world: &mut WorldAccess<W>) {
+    /// Meta tag of this entity, including the tag used to find it in the world
+    pub(crate) fn tracked_tag(&self) -> NbtTag {
+        let mut tag = self.meta.tag();
+        let marker = NbtTag::String(format!("fluxd{}", self.id));
+        if let NbtTag::Compound(comp) = &mut tag {
+            match comp.get_mut("Tags") {
+                Some(NbtTag::List(tags)) => tags.insert(0, marker),
+                _ => {
+                    comp.insert("Tags", NbtTag::List(vec![marker]));
+                }
+            }
+        }
+        tag
+    }
+
+    /// Summons this entity, remembering its meta, so that later saves only write the changes
+    pub fn summon<W: Write>(
+        &mut self,
+        world: &mut WorldAccess<W>,
+        at: Location,
+    ) -> anyhow::Result<()> {
+        let tag = self.tracked_tag();
+        world.summon_meta(at, self.ty, &tag)?;
+        self.synced = Some(tag);
+        Ok(())
+    }
+
+    /// Writes changes made to the meta since the last save or summon. If the entity was
+    /// not summoned through [Entity::summon], the whole meta is merged
+    pub fn save<W: Write>(&mut self, world: &mut WorldAccess<W>) -> anyhow::Result<()> {
         let sel = format!("@e[tag=fluxd{}]", self.id);
-        world.write_line(format!(
-            "execute if entity {} as {} run data merge entity @s {}",
-            sel,
-            sel,
-            self.meta.stringified()
-        ))
+        // the marker tag is part of both sides, so patches to `Tags` keep it
+        let tag = self.tracked_tag();
+        match &self.synced {
+            Some(synced) => {
+                for patch in diff(synced, &tag) {
+                    world.write_line(format!(
+                        "execute if entity {} as {} run {}",
+                        sel,
+                        sel,
+                        patch.command("entity @s")?
+                    ))
+                }
+            }
+            None => world.write_line(format!(
+                "execute if entity {} as {} run data merge entity @s {}",
+                sel,
+                sel,
+                tag.stringify()?
+            )),
+        }
+        self.synced = Some(tag);
+        Ok(())
     }
 }
@@ -1,5 +1,8 @@
+use convert_case::{Case, Casing};
+
 use crate::__meta_struct;
 use crate::mc::item::MetaContainer;
+use crate::mc::{Identified, Identifier};
 use crate::nbt::{NbtTag, NbtWriter};
 
 #[repr(u8)]
@@ -40,12 +43,20 @@ pub enum Effect {
     Darkness,
 }
 
+impl Identified for Effect {
+    fn id(&self) -> Identifier {
+        Identifier::minecraft(format!("{:?}", self).to_case(Case::Snake))
+    }
+}
+
 impl Into<NbtTag> for Effect {
     fn into(self) -> NbtTag {
         NbtTag::Byte(self as i8)
     }
 }
 
+crate::__to_tag_by_clone!(Effect);
+
 __meta_struct! {
     PotionEffect {
         Ambient by is_ambient: bool,
@@ -7,10 +7,11 @@ use crate::mc::entity::Attribute;
 use crate::mc::item::MetaContainer;
 use crate::mc::item::Slot;
 use crate::mc::{Identified, Identifier};
-use crate::nbt::NbtTag;
+use crate::nbt::error::{NbtError, NbtResult};
 use crate::nbt::NbtWriter;
+use crate::nbt::{NbtTag, ToTag};
 use crate::prelude::*;
-use crate::snbt::StringNbtWriter;
+use crate::snbt::{SnbtOptions, StringNbtWriter};
 use crate::utils::GeneralColor;
 use crate::utils::Vec3F;
 use crate::utils::{Either, Positive, Vec3D, Vec3I};
@@ -123,11 +124,9 @@ impl EntityAttribute {
 
 impl Into<NbtTag> for EntityAttribute {
     fn into(self) -> NbtTag {
-        let name = self.attr.to_string();
-        let base = self.base;
         NbtTag::Compound(nbt! {
-            Name: name,
-            Base: base
+            Name: self.attr.to_string(),
+            Base: self.base
         })
     }
 }
@@ -177,6 +176,30 @@ impl Into<NbtTag> for HandItems {
     }
 }
 
+impl ToTag for HandItems {
+    fn to_tag(&self) -> NbtTag {
+        NbtTag::List(vec![self.main_hand.to_tag(), self.off_hand.to_tag()])
+    }
+}
+
+impl ToTag for Equipment {
+    fn to_tag(&self) -> NbtTag {
+        NbtTag::List(vec![
+            self.feet.to_tag(),
+            self.legs.to_tag(),
+            self.chest.to_tag(),
+            self.head.to_tag(),
+        ])
+    }
+}
+
+crate::__to_tag_by_clone!(
+    EntityRotation,
+    ArmorDropChances,
+    EntityAttribute,
+    HandDropChances
+);
+
 macro_rules! entities {
     (
         $(
@@ -460,7 +483,7 @@ macro_rules! meta_enum {
         }
 
         impl MetaContainer for EntityMeta {
-            fn write_meta<W>(&mut self, writer: &mut W) -> anyhow::Result<()> where W: NbtWriter {
+            fn write_meta<W>(&mut self, writer: &mut W) -> $crate::nbt::error::NbtResult<()> where W: NbtWriter {
                 use $crate::mc::entity::meta::EntityMeta::*;
                 match self {
                     $( $rh(value)  => value.write_meta(writer) ),*
@@ -468,30 +491,43 @@ macro_rules! meta_enum {
             }
 
             fn tag(&self) -> NbtTag {
-                Clone::clone(self).into()
+                use $crate::mc::entity::meta::EntityMeta::*;
+                match self {
+                    $( $rh(value)  => value.tag() ),*
+                }
+            }
+        }
+
+        impl ToTag for EntityMeta {
+            fn to_tag(&self) -> NbtTag {
+                self.tag()
             }
         }
     };
 }
 
 impl EntityMeta {
-    pub fn stringified(&mut self) -> String {
+    pub fn stringified(&mut self) -> NbtResult<String> {
+        self.stringified_with(SnbtOptions::compact())
+    }
+
+    /// Stringifies this meta with provided formatting, e.g. to pretty print it
+    pub fn stringified_with(&mut self, options: SnbtOptions) -> NbtResult<String> {
         let mut buf = vec![];
-        let mut writer = StringNbtWriter::new(&mut buf);
+        let mut writer = StringNbtWriter::with_options(&mut buf, options);
 
-        self.write_meta(&mut writer).unwrap();
+        self.write_meta(&mut writer)?;
 
-        let str = String::from_utf8(buf).unwrap();
-        str
+        String::from_utf8(buf).map_err(|_| NbtError::unsupported("SNBT output is not valid UTF-8"))
     }
 }
 
 impl CommandLike for EntityMeta {
-    fn compile(&mut self) -> String
+    fn compile(&mut self) -> anyhow::Result<String>
     where
         Self: Sized,
     {
-        self.stringified()
+        Ok(self.stringified()?)
     }
 }
 
@@ -1461,22 +1497,19 @@ pub struct AllayEntityVibrationSource(Uuid, f32);
 
 impl Into<NbtTag> for AllayBlockVibrationSource {
     fn into(self) -> NbtTag {
-        let pos: Vec3I = self.0.into();
         NbtTag::Compound(nbt! {
             type: "block",
-            pos: pos,
+            pos: Into::<Vec3I>::into(self.0),
         })
     }
 }
 
 impl Into<NbtTag> for AllayEntityVibrationSource {
     fn into(self) -> NbtTag {
-        let source_entity = self.0;
-        let y_offset = self.1;
         NbtTag::Compound(nbt! {
             type: "entity",
-            source_entity: source_entity,
-            y_offset: y_offset
+            source_entity: self.0,
+            y_offset: self.1
         })
     }
 }
@@ -1577,3 +1610,22 @@ impl Into<NbtTag> for GossipType {
         NbtTag::String(format!("{:?}", self).to_case(Case::Snake))
     }
 }
+
+crate::__to_tag_by_clone!(
+    ArrowStatus,
+    PuffState,
+    RabbitType,
+    ParrotColor,
+    PandaGene,
+    MooshroomColor,
+    LlamaColor,
+    FrogColor,
+    FoxColor,
+    DragonPhase,
+    CatVariant,
+    AxolotlColor,
+    AllayBlockVibrationSource,
+    AllayEntityVibrationSource,
+    VillagerLevel,
+    GossipType,
+);
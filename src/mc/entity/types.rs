@@ -150,3 +150,5 @@ impl Into<NbtTag> for EntityType {
         NbtTag::String(self.id().to_string())
     }
 }
+
+crate::__to_tag_by_clone!(EntityType);
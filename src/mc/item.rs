@@ -8,8 +8,9 @@ use crate::mc::entity::AttributeModifier;
 use crate::mc::material::Material;
 use crate::mc::{Identified, Identifier};
 use crate::nbt;
-use crate::nbt::{NbtTag, NbtWriter};
-use crate::snbt::StringNbtWriter;
+use crate::nbt::error::{NbtError, NbtResult};
+use crate::nbt::{NbtTag, NbtWriter, ToTag};
+use crate::snbt::{SnbtOptions, StringNbtWriter};
 
 #[derive(Debug, Clone)]
 pub struct ItemStack {
@@ -49,18 +50,24 @@ impl ItemStack {
         self.clone()
     }
 
-    pub fn stringified(&mut self) -> String {
+    pub fn stringified(&mut self) -> NbtResult<String> {
+        self.stringified_with(SnbtOptions::compact())
+    }
+
+    /// Stringifies this item with provided formatting, e.g. to pretty print its meta
+    pub fn stringified_with(&mut self, options: SnbtOptions) -> NbtResult<String> {
         let mut buf = vec![];
-        let mut str = StringNbtWriter::new(&mut buf);
-        self.meta.write_meta(&mut str).unwrap();
-        let str = String::from_utf8(buf).unwrap();
+        let mut str = StringNbtWriter::with_options(&mut buf, options);
+        self.meta.write_meta(&mut str)?;
+        let str = String::from_utf8(buf)
+            .map_err(|_| NbtError::unsupported("SNBT output is not valid UTF-8"))?;
 
-        format!(
+        Ok(format!(
             "{mat}{meta} {amount}",
             mat = self.mat.id().to_string(),
             meta = str,
             amount = self.amount.to_string()
-        )
+        ))
     }
 }
 
@@ -69,17 +76,27 @@ impl Into<NbtTag> for ItemStack {
         if self.mat == Material::Air {
             return NbtTag::Compound(nbt!());
         }
-        let count = self.amount;
-        let tag = self.meta;
-        let id = self.mat.id().to_string();
         return NbtTag::Compound(nbt! {
-            Count: count,
-            id: id,
-            tag: tag
+            Count: self.amount,
+            id: self.mat.id().to_string(),
+            tag: self.meta
         });
     }
 }
 
+impl ToTag for ItemStack {
+    fn to_tag(&self) -> NbtTag {
+        if self.mat == Material::Air {
+            return NbtTag::Compound(nbt!());
+        }
+        NbtTag::Compound(nbt! {
+            Count: self.amount,
+            id: self.mat.id().to_string(),
+            tag: self.meta.tag()
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ItemMeta {
     Default(DefaultMeta),
@@ -88,12 +105,15 @@ pub enum ItemMeta {
 
 impl Into<NbtTag> for ItemMeta {
     fn into(self) -> NbtTag {
-        self.tag()
+        match self {
+            ItemMeta::Default(m) => m.into(),
+            ItemMeta::Skull(m) => m.into(),
+        }
     }
 }
 
 impl MetaContainer for ItemMeta {
-    fn write_meta<W>(&mut self, writer: &mut W) -> anyhow::Result<()>
+    fn write_meta<W>(&mut self, writer: &mut W) -> NbtResult<()>
     where
         W: NbtWriter,
     {
@@ -112,7 +132,7 @@ impl MetaContainer for ItemMeta {
 }
 
 pub trait MetaContainer {
-    fn write_meta<W>(&mut self, writer: &mut W) -> anyhow::Result<()>
+    fn write_meta<W>(&mut self, writer: &mut W) -> NbtResult<()>
     where
         W: NbtWriter;
 
@@ -143,11 +163,9 @@ impl ItemDisplay {
 
 impl Into<NbtTag> for ItemDisplay {
     fn into(self) -> NbtTag {
-        let name = self.name;
-        let lore = self.lore;
         NbtTag::Compound(nbt! {
-            Name: name,
-            Lore: lore
+            Name: self.name,
+            Lore: self.lore
         })
     }
 }
@@ -189,16 +207,13 @@ impl SkullData {
 
 impl Into<NbtTag> for SkullData {
     fn into(self) -> NbtTag {
-        let id = self.id;
-        let name = self.name;
-        let texture = self.texture;
         NbtTag::Compound(nbt! {
-            Id: id,
-            Name: name,
+            Id: self.id,
+            Name: self.name,
             Properties: {
                 textures: [
                     {
-                        Value: texture
+                        Value: self.texture
                     }
                 ]
             }
@@ -215,19 +230,29 @@ impl Into<NbtTag> for Slot {
         if stack.mat == Material::Air {
             return NbtTag::Compound(nbt!());
         }
-        let count = stack.amount;
-        let tag = stack.meta;
-        let id = stack.mat.id().to_string();
-        let slot = self.1;
         return NbtTag::Compound(nbt! {
-            Count: count,
-            id: id,
-            tag: tag,
-            Slot: slot
+            Count: stack.amount,
+            id: stack.mat.id().to_string(),
+            tag: stack.meta,
+            Slot: self.1
         });
     }
 }
 
+impl ToTag for Slot {
+    fn to_tag(&self) -> NbtTag {
+        let mut tag = self.0.to_tag();
+        if let NbtTag::Compound(comp) = &mut tag {
+            if !comp.is_empty() {
+                comp.insert("Slot", self.1);
+            }
+        }
+        tag
+    }
+}
+
+crate::__to_tag_by_clone!(ItemDisplay, SkullOwner, SkullData);
+
 macro_rules! meta_impl {
     (
         $(
@@ -1248,6 +1248,8 @@ impl Into<NbtTag> for Material {
     }
 }
 
+crate::__to_tag_by_clone!(Material);
+
 impl ToString for Material {
     fn to_string(&self) -> String {
         format!("{:?}", self)
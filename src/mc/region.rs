@@ -0,0 +1,385 @@
+//! Anvil region files (`.mca`), which store 32x32 chunks of a world.
+//!
+//! ```ignore
+//! let mut region = RegionFile::read(File::open("world/region/r.0.0.mca")?)?;
+//! let chunk = region.chunk_mut(3, 5).unwrap();
+//! chunk.set_block(1, 64, 1, BlockState::new(Identifier::minecraft("gold_block")))?;
+//! region.write(File::create("world/region/r.0.0.mca")?)?;
+//! ```
+
+use std::io::{Read, Write};
+
+use anyhow::bail;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::mc::block::BlockState;
+use crate::nbt::compression::{CompressedNbtReader, CompressedNbtWriter, NbtCompression};
+use crate::nbt::{Compound, NbtReader, NbtTag, NbtWriter};
+
+const SECTOR_SIZE: usize = 4096;
+const CHUNKS: usize = 1024;
+
+/// Single chunk stored in a region file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    /// Last time the chunk was saved, in seconds since the epoch
+    pub timestamp: u32,
+    pub compression: NbtCompression,
+    pub data: Compound,
+}
+
+/// Region file with up to 32x32 chunks
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionFile {
+    chunks: Vec<Option<Chunk>>,
+}
+
+/// Index of a chunk in the region. Chunk coordinates are wrapped, so both
+/// the absolute and the in-region coordinates can be used.
+fn chunk_index(x: i32, z: i32) -> usize {
+    (x & 31) as usize + (z & 31) as usize * 32
+}
+
+impl Default for RegionFile {
+    fn default() -> Self {
+        Self {
+            chunks: vec![None; CHUNKS],
+        }
+    }
+}
+
+impl RegionFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the whole region file. Chunks stored in external `.mcc` files and
+    /// chunks compressed with LZ4 are not supported.
+    pub fn read<R: Read>(mut read: R) -> anyhow::Result<Self> {
+        let mut buf = vec![];
+        read.read_to_end(&mut buf)?;
+        let mut region = Self::new();
+        if buf.is_empty() {
+            return Ok(region);
+        }
+        if buf.len() < SECTOR_SIZE * 2 {
+            bail!("Region file is too short to contain its header!")
+        }
+
+        let (locations, timestamps) = buf[..SECTOR_SIZE * 2].split_at(SECTOR_SIZE);
+        for idx in 0..CHUNKS {
+            let location = (&locations[idx * 4..]).read_u32::<BigEndian>()?;
+            let timestamp = (&timestamps[idx * 4..]).read_u32::<BigEndian>()?;
+            let offset = (location >> 8) as usize * SECTOR_SIZE;
+            if location == 0 {
+                continue;
+            }
+            if offset < SECTOR_SIZE * 2 || offset + 5 > buf.len() {
+                bail!("Chunk {} points outside of the region file!", idx)
+            }
+
+            let mut header = &buf[offset..offset + 5];
+            let len = header.read_u32::<BigEndian>()? as usize;
+            let compression = match header.read_u8()? {
+                1 => NbtCompression::Gzip,
+                2 => NbtCompression::Zlib,
+                3 => NbtCompression::None,
+                4 => bail!(
+                    "Chunk {} is compressed with LZ4, which is not supported!",
+                    idx
+                ),
+                ty if ty & 0x80 != 0 => {
+                    bail!(
+                        "Chunk {} is stored in an external file, which is not supported!",
+                        idx
+                    )
+                }
+                ty => bail!("Unknown compression type {} of chunk {}!", ty, idx),
+            };
+            if len == 0 || offset + 4 + len > buf.len() {
+                bail!("Chunk {} has invalid length {}!", idx, len)
+            }
+
+            let data = &buf[offset + 5..offset + 4 + len];
+            let (_, tag) = CompressedNbtReader::with_compression(data, compression).read_tag()?;
+            region.chunks[idx] = Some(Chunk {
+                timestamp,
+                compression,
+                data: Compound::try_from(tag)?,
+            });
+        }
+        Ok(region)
+    }
+
+    /// Writes the region file, placing the chunks one after another
+    pub fn write<W: Write>(&self, mut write: W) -> anyhow::Result<()> {
+        let mut locations = Vec::with_capacity(SECTOR_SIZE);
+        let mut timestamps = Vec::with_capacity(SECTOR_SIZE);
+        let mut body = vec![];
+        for chunk in &self.chunks {
+            let chunk = match chunk {
+                Some(chunk) => chunk,
+                None => {
+                    locations.write_u32::<BigEndian>(0)?;
+                    timestamps.write_u32::<BigEndian>(0)?;
+                    continue;
+                }
+            };
+
+            let mut writer = CompressedNbtWriter::new(vec![], chunk.compression);
+            writer.write_tag(None, &NbtTag::Compound(chunk.data.clone()))?;
+            let data = writer.into_inner();
+
+            let sectors = (data.len() + 5).div_ceil(SECTOR_SIZE);
+            if sectors > 255 {
+                bail!("Chunk is too large to be stored in a region file!")
+            }
+            let offset = body.len() / SECTOR_SIZE + 2;
+            locations.write_u32::<BigEndian>((offset as u32) << 8 | sectors as u32)?;
+            timestamps.write_u32::<BigEndian>(chunk.timestamp)?;
+
+            body.write_u32::<BigEndian>(data.len() as u32 + 1)?;
+            body.write_u8(match chunk.compression {
+                NbtCompression::Gzip => 1,
+                NbtCompression::Zlib => 2,
+                NbtCompression::None => 3,
+            })?;
+            body.extend(data);
+            body.resize(body.len().div_ceil(SECTOR_SIZE) * SECTOR_SIZE, 0);
+        }
+        write.write_all(&locations)?;
+        write.write_all(&timestamps)?;
+        write.write_all(&body)?;
+        Ok(())
+    }
+
+    pub fn chunk(&self, x: i32, z: i32) -> Option<&Chunk> {
+        self.chunks[chunk_index(x, z)].as_ref()
+    }
+
+    pub fn chunk_mut(&mut self, x: i32, z: i32) -> Option<&mut Chunk> {
+        self.chunks[chunk_index(x, z)].as_mut()
+    }
+
+    /// Sets the chunk, returning the one that was stored there before
+    pub fn set_chunk(&mut self, x: i32, z: i32, chunk: Chunk) -> Option<Chunk> {
+        self.chunks[chunk_index(x, z)].replace(chunk)
+    }
+
+    pub fn remove_chunk(&mut self, x: i32, z: i32) -> Option<Chunk> {
+        self.chunks[chunk_index(x, z)].take()
+    }
+
+    /// Iterates over all present chunks with their in-region coordinates
+    pub fn chunks(&self) -> impl Iterator<Item = (i32, i32, &Chunk)> {
+        self.chunks.iter().enumerate().filter_map(|(idx, chunk)| {
+            chunk
+                .as_ref()
+                .map(|chunk| ((idx % 32) as i32, (idx / 32) as i32, chunk))
+        })
+    }
+}
+
+/// Bits used by each palette index in the packed block data
+fn bits_per_block(palette_len: usize) -> usize {
+    let bits = usize::BITS - (palette_len.max(1) - 1).leading_zeros();
+    (bits as usize).max(4)
+}
+
+fn unpack_indices(data: &[i64], palette_len: usize) -> anyhow::Result<Vec<usize>> {
+    if palette_len <= 1 {
+        return Ok(vec![0; 4096]);
+    }
+    let bits = bits_per_block(palette_len);
+    let per_long = 64 / bits;
+    if data.len() != 4096_usize.div_ceil(per_long) {
+        bail!("Invalid length {} of block state data", data.len())
+    }
+    let mask = (1u64 << bits) - 1;
+    let indices = (0..4096)
+        .map(|idx| ((data[idx / per_long] as u64 >> (idx % per_long * bits)) & mask) as usize)
+        .collect::<Vec<usize>>();
+    if let Some(idx) = indices.iter().find(|idx| **idx >= palette_len) {
+        bail!("Block state index {} is outside of the palette", idx)
+    }
+    Ok(indices)
+}
+
+fn pack_indices(indices: &[usize], palette_len: usize) -> Vec<i64> {
+    let bits = bits_per_block(palette_len);
+    let per_long = 64 / bits;
+    let mut data = vec![0i64; 4096_usize.div_ceil(per_long)];
+    for (idx, value) in indices.iter().enumerate() {
+        data[idx / per_long] |= (*value as i64) << (idx % per_long * bits);
+    }
+    data
+}
+
+impl Chunk {
+    /// Creates an empty chunk, that the game will fill in the missing data for.
+    /// `DataVersion` should match the version of the world.
+    pub fn new(x: i32, z: i32, data_version: i32) -> Self {
+        let mut data = Compound::default();
+        data.insert("DataVersion", data_version);
+        data.insert("xPos", x);
+        data.insert("zPos", z);
+        data.insert("Status", "minecraft:full");
+        data.insert("sections", NbtTag::List(vec![]));
+        Self {
+            timestamp: 0,
+            compression: NbtCompression::Zlib,
+            data,
+        }
+    }
+
+    pub fn x_pos(&self) -> Option<i32> {
+        self.data.get_int("xPos")
+    }
+
+    pub fn z_pos(&self) -> Option<i32> {
+        self.data.get_int("zPos")
+    }
+
+    /// Sections of the chunk. Chunks saved before 1.18 keep them under `Level`,
+    /// so they have none here.
+    pub fn sections(&self) -> &[NbtTag] {
+        self.data.get_list("sections").unwrap_or_default()
+    }
+
+    pub fn block_entities(&self) -> &[NbtTag] {
+        self.data.get_list("block_entities").unwrap_or_default()
+    }
+
+    /// Entities stored in the chunk. Since 1.17 they are stored in separate
+    /// region files in the `entities` directory under the `Entities` key.
+    pub fn entities(&self) -> &[NbtTag] {
+        self.data
+            .get_list("Entities")
+            .or_else(|| self.data.get_list("entities"))
+            .unwrap_or_default()
+    }
+
+    /// Fails for chunks saved before 1.18, which store their blocks in a different layout
+    fn check_layout(&self) -> anyhow::Result<()> {
+        if self.data.contains("Level") {
+            bail!("Chunks saved before 1.18 are not supported")
+        }
+        Ok(())
+    }
+
+    fn section(&self, y: i32) -> Option<&Compound> {
+        let section_y = y.div_euclid(16);
+        self.sections()
+            .iter()
+            .filter_map(NbtTag::as_compound)
+            .find(|section| section.get_byte("Y").map(i32::from) == Some(section_y))
+    }
+
+    /// Returns block at the chunk-relative x and z, and the absolute y
+    pub fn block(&self, x: i32, y: i32, z: i32) -> anyhow::Result<Option<BlockState>> {
+        self.check_layout()?;
+        let states = match self
+            .section(y)
+            .and_then(|it| it.get_compound("block_states"))
+        {
+            Some(states) => states,
+            None => return Ok(None),
+        };
+        let palette = states.get_list("palette").unwrap_or_default();
+        let data = states.get_long_array("data").unwrap_or_default();
+        let indices = unpack_indices(data, palette.len())?;
+        match palette.get(indices[block_index(x, y, z)]) {
+            Some(state) => Ok(Some(BlockState::try_from(state.clone())?)),
+            None => bail!("Block state palette of the section is empty"),
+        }
+    }
+
+    /// Sets block at the chunk-relative x and z, and the absolute y, creating the section if
+    /// it is missing, with plains as its biome. Heightmaps and lighting are not updated,
+    /// the game recalculates them.
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, state: BlockState) -> anyhow::Result<()> {
+        self.check_layout()?;
+        let section_y = y.div_euclid(16);
+        if i8::try_from(section_y).is_err() {
+            bail!("Block at y {} is outside of the world", y)
+        }
+        if self.section(y).is_none() {
+            let mut section = Compound::default();
+            section.insert("Y", section_y as i8);
+            let mut states = Compound::default();
+            states.insert("palette", vec![BlockState::air()]);
+            section.insert("block_states", states);
+            let mut biomes = Compound::default();
+            biomes.insert("palette", vec!["minecraft:plains"]);
+            section.insert("biomes", biomes);
+            match self.data.get_list_mut("sections") {
+                Some(sections) => sections.push(NbtTag::Compound(section)),
+                None => {
+                    self.data.insert("sections", vec![section]);
+                }
+            }
+        }
+        // chunks are not relit by the game if they claim to be lit already
+        self.data.remove("isLightOn");
+
+        let section = self
+            .data
+            .get_list_mut("sections")
+            .unwrap()
+            .iter_mut()
+            .filter_map(NbtTag::as_compound_mut)
+            .find(|section| section.get_byte("Y").map(i32::from) == Some(section_y))
+            .unwrap();
+        if section.get_compound("block_states").is_none() {
+            let mut states = Compound::default();
+            states.insert("palette", vec![BlockState::air()]);
+            section.insert("block_states", states);
+        }
+        let states = section.get_compound_mut("block_states").unwrap();
+
+        let mut palette = states
+            .get_list("palette")
+            .unwrap_or_default()
+            .iter()
+            .cloned()
+            .map(BlockState::try_from)
+            .collect::<Result<Vec<BlockState>, _>>()?;
+        let data = states.get_long_array("data").unwrap_or_default();
+        let mut indices = unpack_indices(data, palette.len())?;
+
+        let idx = match palette.iter().position(|it| *it == state) {
+            Some(idx) => idx,
+            None => {
+                palette.push(state);
+                palette.len() - 1
+            }
+        };
+        indices[block_index(x, y, z)] = idx;
+
+        // drop palette entries that are no longer used
+        let mut remap = vec![None; palette.len()];
+        let mut compact = vec![];
+        for index in indices.iter_mut() {
+            let old = *index;
+            *index = *remap[old].get_or_insert_with(|| {
+                compact.push(palette[old].clone());
+                compact.len() - 1
+            });
+        }
+
+        if compact.len() > 1 {
+            states.insert(
+                "data",
+                NbtTag::LongArray(pack_indices(&indices, compact.len())),
+            );
+        } else {
+            states.remove("data");
+        }
+        states.insert("palette", compact);
+        Ok(())
+    }
+}
+
+fn block_index(x: i32, y: i32, z: i32) -> usize {
+    ((y & 15) * 256 + (z & 15) * 16 + (x & 15)) as usize
+}
@@ -0,0 +1,461 @@
+//! Import of schematics made in external tools: Sponge schematics (`.schem`, versions 2 and 3)
+//! and Litematica schematics (`.litematic`).
+//!
+//! ```ignore
+//! let schematic = Schematic::read(File::open("castle.schem")?)?;
+//! for command in schematic.commands(Location::relative(0, 0, 0))? {
+//!     world.write_line(command);
+//! }
+//! ```
+
+use std::io::Read;
+use std::str::FromStr;
+
+use anyhow::bail;
+
+use crate::mc::block::{BlockState, Location};
+use crate::mc::commands::block::MAX_FILL_VOLUME;
+use crate::mc::structure::Structure;
+use crate::mc::Identifier;
+use crate::nbt::compression::CompressedNbtReader;
+use crate::nbt::{Compound, NbtReader, NbtTag};
+use crate::utils::Vec3I;
+
+/// Cuboid of blocks loaded from a schematic
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+    size: Vec3I,
+    palette: Vec<BlockState>,
+    /// Palette indices in YZX order, `None` for positions the schematic does not change
+    blocks: Vec<Option<usize>>,
+    block_entities: Vec<(Vec3I, Compound)>,
+}
+
+fn read_root<R: Read>(read: R) -> anyhow::Result<Compound> {
+    let (_, tag) = CompressedNbtReader::new(read).read_tag()?;
+    Ok(Compound::try_from(tag)?)
+}
+
+fn read_size(value: Option<i16>) -> anyhow::Result<i32> {
+    match value {
+        // sizes are unsigned shorts in the Sponge format
+        Some(size) => Ok(size as u16 as i32),
+        None => bail!("Schematic is missing its size"),
+    }
+}
+
+/// Number of blocks in a box of the size, failing if it does not fit into memory
+fn volume(size: Vec3I) -> anyhow::Result<usize> {
+    let dims = [size.0, size.1, size.2].map(usize::try_from);
+    match dims {
+        [Ok(x), Ok(y), Ok(z)] => match x.checked_mul(y).and_then(|it| it.checked_mul(z)) {
+            Some(volume) => Ok(volume),
+            None => bail!("Schematic of size {:?} is too large", size),
+        },
+        _ => bail!("Schematic has negative size {:?}", size),
+    }
+}
+
+fn read_varints(data: &[i8], len: usize) -> anyhow::Result<Vec<usize>> {
+    // every value takes at least a byte, so a bogus length does not allocate more than the data
+    let mut values = Vec::with_capacity(len.min(data.len()));
+    let mut value = 0usize;
+    let mut shift = 0;
+    for byte in data.iter().map(|b| *b as u8) {
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            values.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+            if shift > 28 {
+                bail!("VarInt in schematic block data is too long")
+            }
+        }
+    }
+    if values.len() != len {
+        bail!(
+            "Schematic block data has {} blocks instead of {}",
+            values.len(),
+            len
+        )
+    }
+    Ok(values)
+}
+
+fn read_xyz(comp: Option<&Compound>) -> anyhow::Result<Vec3I> {
+    match comp.map(|it| (it.get_int("x"), it.get_int("y"), it.get_int("z"))) {
+        Some((Some(x), Some(y), Some(z))) => Ok(Vec3I(x, y, z)),
+        _ => bail!("Expected a compound with x, y and z in schematic"),
+    }
+}
+
+impl Schematic {
+    pub fn size(&self) -> Vec3I {
+        self.size
+    }
+
+    fn index(&self, x: i32, y: i32, z: i32) -> usize {
+        let (width, length) = (self.size.0 as usize, self.size.2 as usize);
+        (y as usize * length + z as usize) * width + x as usize
+    }
+
+    /// Returns the block at the position inside the schematic
+    pub fn block(&self, pos: Vec3I) -> Option<&BlockState> {
+        if pos.0 < 0
+            || pos.1 < 0
+            || pos.2 < 0
+            || pos.0 >= self.size.0
+            || pos.1 >= self.size.1
+            || pos.2 >= self.size.2
+        {
+            return None;
+        }
+        self.blocks[self.index(pos.0, pos.1, pos.2)].map(|idx| &self.palette[idx])
+    }
+
+    pub fn block_entities(&self) -> &[(Vec3I, Compound)] {
+        &self.block_entities
+    }
+
+    /// Reads a schematic, detecting whether it is a Sponge or a Litematica one
+    pub fn read<R: Read>(read: R) -> anyhow::Result<Self> {
+        let root = read_root(read)?;
+        if root.contains("Regions") {
+            Self::from_litematic(root)
+        } else {
+            Self::from_sponge(root)
+        }
+    }
+
+    pub fn read_sponge<R: Read>(read: R) -> anyhow::Result<Self> {
+        Self::from_sponge(read_root(read)?)
+    }
+
+    pub fn read_litematic<R: Read>(read: R) -> anyhow::Result<Self> {
+        Self::from_litematic(read_root(read)?)
+    }
+
+    fn from_sponge(mut root: Compound) -> anyhow::Result<Self> {
+        // version 3 nests everything into the `Schematic` compound
+        if let Some(NbtTag::Compound(inner)) = root.remove("Schematic") {
+            root = inner;
+        }
+        let size = Vec3I(
+            read_size(root.get_short("Width"))?,
+            read_size(root.get_short("Height"))?,
+            read_size(root.get_short("Length"))?,
+        );
+        let container = match root.get_compound("Blocks") {
+            Some(blocks) => blocks,
+            None => &root,
+        };
+        let palette_comp = match container.get_compound("Palette") {
+            Some(palette) => palette,
+            None => bail!("Schematic is missing its palette"),
+        };
+        let mut palette = vec![None; palette_comp.len()];
+        for (state, idx) in palette_comp.iter() {
+            let idx = match idx.as_int() {
+                Some(idx) if idx >= 0 && (idx as usize) < palette.len() => idx as usize,
+                _ => bail!("Invalid palette index of block state {}", state),
+            };
+            palette[idx] = Some(BlockState::from_str(state)?);
+        }
+        let palette = palette
+            .into_iter()
+            .collect::<Option<Vec<BlockState>>>()
+            .ok_or_else(|| anyhow::anyhow!("Schematic palette has gaps in its indices"))?;
+
+        let data = container
+            .get_byte_array("Data")
+            .or_else(|| container.get_byte_array("BlockData"))
+            .unwrap_or_default();
+        let blocks = read_varints(data, volume(size)?)?
+            .into_iter()
+            .map(|idx| {
+                if idx < palette.len() {
+                    Ok(Some(idx))
+                } else {
+                    bail!("Block data references missing palette index {}", idx)
+                }
+            })
+            .collect::<anyhow::Result<Vec<Option<usize>>>>()?;
+
+        let mut block_entities = vec![];
+        let entities = container
+            .get_list("BlockEntities")
+            .or_else(|| container.get_list("TileEntities"))
+            .unwrap_or_default();
+        for entity in entities {
+            let mut entity = Compound::try_from(entity.clone())?;
+            let pos = match entity.remove("Pos").map(Vec::<i32>::try_from).transpose()? {
+                Some(pos) if pos.len() == 3 => Vec3I(pos[0], pos[1], pos[2]),
+                _ => bail!("Block entity in schematic is missing its position"),
+            };
+            entity.remove("Id");
+            // version 3 keeps the block entity data separately
+            let data = match entity.remove("Data") {
+                Some(data) => Compound::try_from(data)?,
+                None => entity,
+            };
+            block_entities.push((pos, data));
+        }
+
+        Ok(Self {
+            size,
+            palette,
+            blocks,
+            block_entities,
+        })
+    }
+
+    fn from_litematic(root: Compound) -> anyhow::Result<Self> {
+        let regions = match root.get_compound("Regions") {
+            Some(regions) => regions,
+            None => bail!("Litematic schematic is missing its regions"),
+        };
+
+        // regions can have negative sizes, so they are normalized to their minimum corner first
+        let mut parsed = vec![];
+        for (name, region) in regions.iter() {
+            let region = match region.as_compound() {
+                Some(region) => region,
+                None => bail!("Region {} is not a compound", name),
+            };
+            let pos = read_xyz(region.get_compound("Position"))?;
+            let size = read_xyz(region.get_compound("Size"))?;
+            let min = |pos: i32, size: i32| {
+                if size < 0 {
+                    pos.checked_add(size + 1)
+                } else {
+                    Some(pos)
+                }
+            };
+            let (corner, size) = match (
+                (min(pos.0, size.0), min(pos.1, size.1), min(pos.2, size.2)),
+                (
+                    size.0.checked_abs(),
+                    size.1.checked_abs(),
+                    size.2.checked_abs(),
+                ),
+            ) {
+                ((Some(x), Some(y), Some(z)), (Some(width), Some(height), Some(length))) => {
+                    (Vec3I(x, y, z), Vec3I(width, height, length))
+                }
+                _ => bail!("Region {} is out of bounds", name),
+            };
+            parsed.push((region, corner, size));
+        }
+        if parsed.is_empty() {
+            bail!("Litematic schematic has no regions")
+        }
+        // bounds of all regions on an axis, widened so that far apart regions do not overflow
+        let extent = |axis: fn(Vec3I) -> i32| -> anyhow::Result<(i32, i32)> {
+            let min = parsed.iter().map(|it| axis(it.1) as i64).min().unwrap();
+            let max = parsed
+                .iter()
+                .map(|it| axis(it.1) as i64 + axis(it.2) as i64)
+                .max()
+                .unwrap();
+            match i32::try_from(max - min) {
+                Ok(size) => Ok((min as i32, size)),
+                Err(_) => bail!("Litematic schematic is too large"),
+            }
+        };
+        let (x, width) = extent(|it| it.0)?;
+        let (y, height) = extent(|it| it.1)?;
+        let (z, length) = extent(|it| it.2)?;
+        let origin = Vec3I(x, y, z);
+        let size = Vec3I(width, height, length);
+
+        let mut schematic = Self {
+            size,
+            palette: vec![],
+            blocks: vec![None; volume(size)?],
+            block_entities: vec![],
+        };
+        for (region, corner, region_size) in parsed {
+            let palette = Vec::<BlockState>::try_from(
+                region
+                    .get("BlockStatePalette")
+                    .cloned()
+                    .unwrap_or(NbtTag::List(vec![])),
+            )?;
+            let volume = volume(region_size)?;
+            let bits = (usize::BITS - (palette.len().max(1) - 1).leading_zeros()).max(2) as usize;
+            let data = region.get_long_array("BlockStates").unwrap_or_default();
+            if volume
+                .checked_mul(bits)
+                .is_none_or(|len| data.len() < len.div_ceil(64))
+            {
+                bail!("Litematic region has not enough block data")
+            }
+            // unlike chunks, litematica packs the values tightly, so they can span two longs
+            let mask = (1u64 << bits) - 1;
+            let offset = Vec3I(
+                corner.0 - origin.0,
+                corner.1 - origin.1,
+                corner.2 - origin.2,
+            );
+            let mut remap = vec![None; palette.len()];
+            for idx in 0..volume {
+                let start = idx * bits;
+                let (long, bit) = (start / 64, start % 64);
+                let mut value = (data[long] as u64) >> bit;
+                if bit + bits > 64 {
+                    value |= (data[long + 1] as u64) << (64 - bit);
+                }
+                let value = (value & mask) as usize;
+                let state = match palette.get(value) {
+                    Some(state) => state,
+                    None => bail!("Block data references missing palette index {}", value),
+                };
+                // air in litematics is not a part of the build
+                if *state.name() == Identifier::minecraft("air") {
+                    continue;
+                }
+                let state = *remap[value].get_or_insert_with(|| {
+                    schematic.palette.push(state.clone());
+                    schematic.palette.len() - 1
+                });
+
+                let (width, length) = (region_size.0 as usize, region_size.2 as usize);
+                let x = (idx % width) as i32;
+                let z = (idx / width % length) as i32;
+                let y = (idx / (width * length)) as i32;
+                let index = schematic.index(x + offset.0, y + offset.1, z + offset.2);
+                schematic.blocks[index] = Some(state);
+            }
+
+            for entity in region.get_list("TileEntities").unwrap_or_default() {
+                let mut entity = Compound::try_from(entity.clone())?;
+                let pos = match (entity.remove("x"), entity.remove("y"), entity.remove("z")) {
+                    (Some(NbtTag::Int(x)), Some(NbtTag::Int(y)), Some(NbtTag::Int(z))) => {
+                        Vec3I(x + offset.0, y + offset.1, z + offset.2)
+                    }
+                    _ => bail!("Block entity in litematic is missing its position"),
+                };
+                entity.remove("id");
+                schematic.block_entities.push((pos, entity));
+            }
+        }
+        Ok(schematic)
+    }
+
+    /// Converts this schematic into a structure template. Positions the schematic does
+    /// not change are left empty, so the structure does not change them either.
+    pub fn to_structure(&self) -> anyhow::Result<Structure> {
+        let mut structure = Structure::new(self.size);
+        for y in 0..self.size.1 {
+            for z in 0..self.size.2 {
+                for x in 0..self.size.0 {
+                    if let Some(state) = self.block(Vec3I(x, y, z)) {
+                        structure.set_block(Vec3I(x, y, z), state.clone())?;
+                    }
+                }
+            }
+        }
+        for (pos, nbt) in &self.block_entities {
+            if let Some(state) = self.block(*pos) {
+                structure.set_block_entity(*pos, state.clone(), nbt.clone())?;
+            }
+        }
+        Ok(structure)
+    }
+
+    /// Compiles this schematic into `fill` and `setblock` commands that place it with its
+    /// lowest corner at the location. Adjacent identical blocks are merged into cuboids,
+    /// and blocks with block entities are placed with their data.
+    pub fn commands(&self, at: Location) -> anyhow::Result<Vec<String>> {
+        let entity_at = |x: i32, y: i32, z: i32| {
+            self.block_entities
+                .iter()
+                .find(|(pos, _)| *pos == Vec3I(x, y, z))
+        };
+        let mut placed = vec![false; self.blocks.len()];
+        for (pos, _) in &self.block_entities {
+            if self.block(*pos).is_some() {
+                placed[self.index(pos.0, pos.1, pos.2)] = true;
+            }
+        }
+
+        let mut commands = vec![];
+        for y in 0..self.size.1 {
+            for z in 0..self.size.2 {
+                for x in 0..self.size.0 {
+                    let idx = self.index(x, y, z);
+                    let state = match self.blocks[idx] {
+                        Some(state) if !placed[idx] => state,
+                        _ => continue,
+                    };
+                    let fits = |x: i32, y: i32, z: i32, placed: &[bool]| {
+                        let idx = self.index(x, y, z);
+                        !placed[idx] && self.blocks[idx] == Some(state)
+                    };
+
+                    // grow the cuboid along x, then z, then y, while it stays uniform
+                    let mut end = Vec3I(x, y, z);
+                    while end.0 + 1 < self.size.0
+                        && (end.0 - x + 2) as usize <= MAX_FILL_VOLUME
+                        && fits(end.0 + 1, y, z, &placed)
+                    {
+                        end.0 += 1;
+                    }
+                    let width = (end.0 - x + 1) as usize;
+                    while end.2 + 1 < self.size.2
+                        && width * (end.2 - z + 2) as usize <= MAX_FILL_VOLUME
+                        && (x..=end.0).all(|x| fits(x, y, end.2 + 1, &placed))
+                    {
+                        end.2 += 1;
+                    }
+                    let area = width * (end.2 - z + 1) as usize;
+                    while end.1 + 1 < self.size.1
+                        && area * (end.1 - y + 2) as usize <= MAX_FILL_VOLUME
+                        && (x..=end.0).all(|x| (z..=end.2).all(|z| fits(x, end.1 + 1, z, &placed)))
+                    {
+                        end.1 += 1;
+                    }
+
+                    for py in y..=end.1 {
+                        for pz in z..=end.2 {
+                            for px in x..=end.0 {
+                                placed[self.index(px, py, pz)] = true;
+                            }
+                        }
+                    }
+                    let state = &self.palette[state];
+                    if end == Vec3I(x, y, z) {
+                        commands.push(format!(
+                            "setblock {} {}",
+                            at.offset(x, y, z).to_string(),
+                            state
+                        ));
+                    } else {
+                        commands.push(format!(
+                            "fill {} {} {}",
+                            at.offset(x, y, z).to_string(),
+                            at.offset(end.0, end.1, end.2).to_string(),
+                            state
+                        ));
+                    }
+                }
+            }
+        }
+
+        for (pos, _) in &self.block_entities {
+            if let (Some(state), Some((_, nbt))) =
+                (self.block(*pos), entity_at(pos.0, pos.1, pos.2))
+            {
+                commands.push(format!(
+                    "setblock {} {}{}",
+                    at.offset(pos.0, pos.1, pos.2).to_string(),
+                    state,
+                    NbtTag::Compound(nbt.clone()).stringify()?
+                ));
+            }
+        }
+        Ok(commands)
+    }
+}
@@ -0,0 +1,568 @@
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use anyhow::bail;
+
+use crate::chat::text::{TextColor, TextComponent};
+use crate::mc::commands::CommandLike;
+use crate::mc::entity::IntoSelector;
+use crate::mc::Identifier;
+use crate::modules::functions::FunctionWriter;
+use crate::modules::MinecraftVersion;
+
+/// Player name, fake player like `#temp`, or a selector that holds scores
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScoreHolder(String);
+
+impl ScoreHolder {
+    pub fn new<S: IntoSelector>(selector: S) -> Self {
+        Self(selector.selector())
+    }
+
+    /// Every holder tracked by the scoreboard, `*`
+    pub fn all() -> Self {
+        Self("*".to_string())
+    }
+}
+
+impl Display for ScoreHolder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Type of statistic tracked by [Criteria::Statistic]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StatType {
+    Custom,
+    Mined,
+    Broken,
+    Crafted,
+    Used,
+    PickedUp,
+    Dropped,
+    Killed,
+    KilledBy,
+}
+
+impl Display for StatType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StatType::Custom => "custom",
+            StatType::Mined => "mined",
+            StatType::Broken => "broken",
+            StatType::Crafted => "crafted",
+            StatType::Used => "used",
+            StatType::PickedUp => "picked_up",
+            StatType::Dropped => "dropped",
+            StatType::Killed => "killed",
+            StatType::KilledBy => "killed_by",
+        })
+    }
+}
+
+/// What updates the scores of an objective
+#[derive(Debug, Clone, PartialEq)]
+pub enum Criteria {
+    /// Only changed by commands
+    Dummy,
+    /// Only changed by commands, but players can change it with `/trigger` once enabled
+    Trigger,
+    DeathCount,
+    PlayerKillCount,
+    TotalKillCount,
+    Health,
+    Xp,
+    Level,
+    Food,
+    Air,
+    Armor,
+    /// Statistic of the type for the block, item, entity or custom stat,
+    /// e.g. `minecraft.mined:minecraft.stone`
+    Statistic(StatType, Identifier),
+    /// Kills of players in the team with the color
+    TeamKill(TextColor),
+    /// Deaths from players in the team with the color
+    KilledByTeam(TextColor),
+}
+
+fn team_color(color: &TextColor) -> anyhow::Result<String> {
+    if let TextColor::Rgb(..) = color {
+        bail!(
+            "Only named colors can be team colors, got {}",
+            color.to_string()
+        )
+    }
+    Ok(color.to_string())
+}
+
+impl Criteria {
+    /// Name of this criteria, failing for team criteria with RGB colors
+    pub fn name(&self) -> anyhow::Result<String> {
+        Ok(match self {
+            Criteria::Dummy => "dummy".to_string(),
+            Criteria::Trigger => "trigger".to_string(),
+            Criteria::DeathCount => "deathCount".to_string(),
+            Criteria::PlayerKillCount => "playerKillCount".to_string(),
+            Criteria::TotalKillCount => "totalKillCount".to_string(),
+            Criteria::Health => "health".to_string(),
+            Criteria::Xp => "xp".to_string(),
+            Criteria::Level => "level".to_string(),
+            Criteria::Food => "food".to_string(),
+            Criteria::Air => "air".to_string(),
+            Criteria::Armor => "armor".to_string(),
+            // identifiers use dots instead of colons inside criteria
+            Criteria::Statistic(stat, id) => {
+                format!("minecraft.{}:{}", stat, id.to_string().replace(':', "."))
+            }
+            Criteria::TeamKill(color) => format!("teamkill.{}", team_color(color)?),
+            Criteria::KilledByTeam(color) => format!("killedByTeam.{}", team_color(color)?),
+        })
+    }
+}
+
+/// Where the scores of an objective are displayed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DisplaySlot {
+    List,
+    Sidebar,
+    BelowName,
+    /// Sidebar shown only to players in the team with the color
+    SidebarTeam(TextColor),
+}
+
+impl DisplaySlot {
+    fn name(&self, version: MinecraftVersion) -> anyhow::Result<String> {
+        Ok(match self {
+            DisplaySlot::List => "list".to_string(),
+            DisplaySlot::Sidebar => "sidebar".to_string(),
+            // renamed to snake case in 1.20.2
+            DisplaySlot::BelowName if version.id() >= MinecraftVersion::v1_20_2.id() => {
+                "below_name".to_string()
+            }
+            DisplaySlot::BelowName => "belowName".to_string(),
+            DisplaySlot::SidebarTeam(color) => format!("sidebar.team.{}", team_color(color)?),
+        })
+    }
+}
+
+/// How the scores of an objective are shown in the player list
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RenderType {
+    Integer,
+    Hearts,
+}
+
+impl Display for RenderType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RenderType::Integer => "integer",
+            RenderType::Hearts => "hearts",
+        })
+    }
+}
+
+/// How scores are formatted in the sidebar and player list, since 1.20.3
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumberFormat {
+    /// Hides the score
+    Blank,
+    /// Shows the component instead of the score
+    Fixed(TextComponent),
+    /// Shows the score with the style of the component, ignoring its content
+    Styled(TextComponent),
+}
+
+impl Display for NumberFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NumberFormat::Blank => f.write_str("blank"),
+            NumberFormat::Fixed(component) => write!(f, "fixed {}", component.to_json()),
+            NumberFormat::Styled(component) => write!(f, "styled {}", component.style_json()),
+        }
+    }
+}
+
+/// Operator of `scoreboard players operation`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Assign,
+    Add,
+    Subtract,
+    Multiply,
+    /// Floor division
+    Divide,
+    /// Floor modulus
+    Modulus,
+    Swap,
+    /// Keeps the smaller score
+    Min,
+    /// Keeps the larger score
+    Max,
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Operation::Assign => "=",
+            Operation::Add => "+=",
+            Operation::Subtract => "-=",
+            Operation::Multiply => "*=",
+            Operation::Divide => "/=",
+            Operation::Modulus => "%=",
+            Operation::Swap => "><",
+            Operation::Min => "<",
+            Operation::Max => ">",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Objective {
+    name: String,
+    criteria: Criteria,
+    display_name: Option<TextComponent>,
+}
+
+impl Objective {
+    pub fn new<S: Into<String>>(name: S, criteria: Criteria) -> Self {
+        Self {
+            name: name.into(),
+            criteria,
+            display_name: None,
+        }
+    }
+
+    /// Objective with the dummy criteria, that is only changed by commands
+    pub fn dummy<S: Into<String>>(name: S) -> Self {
+        Self::new(name, Criteria::Dummy)
+    }
+
+    pub fn display_name(&mut self, name: TextComponent) -> Self {
+        self.display_name = Some(name);
+        self.clone()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn add(&self) -> ScoreboardCommand {
+        ScoreboardCommand::AddObjective(self.clone())
+    }
+
+    pub fn remove(&self) -> ScoreboardCommand {
+        ScoreboardCommand::RemoveObjective(self.name.clone())
+    }
+
+    pub fn set_display(&self, slot: DisplaySlot) -> ScoreboardCommand {
+        ScoreboardCommand::SetDisplay(slot, Some(self.name.clone()))
+    }
+
+    pub fn modify_display_name(&self, name: TextComponent) -> ScoreboardCommand {
+        ScoreboardCommand::ModifyDisplayName(self.name.clone(), name)
+    }
+
+    pub fn modify_render_type(&self, render_type: RenderType) -> ScoreboardCommand {
+        ScoreboardCommand::ModifyRenderType(self.name.clone(), render_type)
+    }
+
+    /// Whether the display name of this objective follows changes to the holder names
+    pub fn modify_display_auto_update(&self, auto_update: bool) -> ScoreboardCommand {
+        ScoreboardCommand::ModifyDisplayAutoUpdate(self.name.clone(), auto_update)
+    }
+
+    /// Sets the default number format of this objective, or resets it
+    pub fn modify_number_format(&self, format: Option<NumberFormat>) -> ScoreboardCommand {
+        ScoreboardCommand::ModifyNumberFormat(self.name.clone(), format)
+    }
+
+    /// Sets the name the holder is shown with in this objective, or resets it
+    pub fn display_name_of(
+        &self,
+        holder: ScoreHolder,
+        name: Option<TextComponent>,
+    ) -> ScoreboardCommand {
+        ScoreboardCommand::DisplayHolderName(holder, self.name.clone(), name)
+    }
+
+    /// Sets the number format of the score of the holder in this objective, or resets it
+    pub fn display_number_format_of(
+        &self,
+        holder: ScoreHolder,
+        format: Option<NumberFormat>,
+    ) -> ScoreboardCommand {
+        ScoreboardCommand::DisplayHolderNumberFormat(holder, self.name.clone(), format)
+    }
+
+    pub fn set(&self, holder: ScoreHolder, score: i32) -> ScoreboardCommand {
+        ScoreboardCommand::Set(holder, self.name.clone(), score)
+    }
+
+    pub fn add_score(&self, holder: ScoreHolder, amount: i32) -> ScoreboardCommand {
+        ScoreboardCommand::Add(holder, self.name.clone(), amount)
+    }
+
+    pub fn remove_score(&self, holder: ScoreHolder, amount: i32) -> ScoreboardCommand {
+        ScoreboardCommand::Remove(holder, self.name.clone(), amount)
+    }
+
+    pub fn reset(&self, holder: ScoreHolder) -> ScoreboardCommand {
+        ScoreboardCommand::Reset(holder, Some(self.name.clone()))
+    }
+
+    pub fn get(&self, holder: ScoreHolder) -> ScoreboardCommand {
+        ScoreboardCommand::Get(holder, self.name.clone())
+    }
+
+    /// Lets the holder change this trigger objective with `/trigger`
+    pub fn enable(&self, holder: ScoreHolder) -> ScoreboardCommand {
+        ScoreboardCommand::Enable(holder, self.name.clone())
+    }
+
+    /// Applies the operation to the score of the target in this objective,
+    /// with the score of the source in the source objective
+    pub fn operation(
+        &self,
+        target: ScoreHolder,
+        operation: Operation,
+        source: ScoreHolder,
+        source_objective: &Objective,
+    ) -> ScoreboardCommand {
+        ScoreboardCommand::Operation {
+            target,
+            objective: self.name.clone(),
+            operation,
+            source,
+            source_objective: source_objective.name.clone(),
+        }
+    }
+}
+
+/// Single `/scoreboard` command
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScoreboardCommand {
+    AddObjective(Objective),
+    RemoveObjective(String),
+    ListObjectives,
+    /// Shows the objective in the slot, or clears the slot
+    SetDisplay(DisplaySlot, Option<String>),
+    ModifyDisplayName(String, TextComponent),
+    /// Since 1.19.4
+    ModifyRenderType(String, RenderType),
+    /// Since 1.20.3
+    ModifyDisplayAutoUpdate(String, bool),
+    /// Sets or resets the number format of the objective, since 1.20.3
+    ModifyNumberFormat(String, Option<NumberFormat>),
+    Set(ScoreHolder, String, i32),
+    Add(ScoreHolder, String, i32),
+    Remove(ScoreHolder, String, i32),
+    /// Resets the score in the objective, or in all objectives
+    Reset(ScoreHolder, Option<String>),
+    Get(ScoreHolder, String),
+    Enable(ScoreHolder, String),
+    Operation {
+        target: ScoreHolder,
+        objective: String,
+        operation: Operation,
+        source: ScoreHolder,
+        source_objective: String,
+    },
+    /// Lists all tracked holders, or the scores of the holder
+    ListScores(Option<ScoreHolder>),
+    /// Sets or resets the name of the holder in the objective, since 1.20.3
+    DisplayHolderName(ScoreHolder, String, Option<TextComponent>),
+    /// Sets or resets the number format of the holder in the objective, since 1.20.3
+    DisplayHolderNumberFormat(ScoreHolder, String, Option<NumberFormat>),
+}
+
+impl ScoreboardCommand {
+    fn objectives(&self) -> Vec<&str> {
+        match self {
+            ScoreboardCommand::AddObjective(objective) => vec![objective.name.as_str()],
+            ScoreboardCommand::RemoveObjective(name)
+            | ScoreboardCommand::SetDisplay(_, Some(name))
+            | ScoreboardCommand::ModifyDisplayName(name, _)
+            | ScoreboardCommand::ModifyRenderType(name, _)
+            | ScoreboardCommand::ModifyDisplayAutoUpdate(name, _)
+            | ScoreboardCommand::ModifyNumberFormat(name, _)
+            | ScoreboardCommand::DisplayHolderName(_, name, _)
+            | ScoreboardCommand::DisplayHolderNumberFormat(_, name, _)
+            | ScoreboardCommand::Set(_, name, _)
+            | ScoreboardCommand::Add(_, name, _)
+            | ScoreboardCommand::Remove(_, name, _)
+            | ScoreboardCommand::Reset(_, Some(name))
+            | ScoreboardCommand::Get(_, name)
+            | ScoreboardCommand::Enable(_, name) => vec![name.as_str()],
+            ScoreboardCommand::Operation {
+                objective,
+                source_objective,
+                ..
+            } => vec![objective.as_str(), source_objective.as_str()],
+            _ => vec![],
+        }
+    }
+
+    /// Whether this command needs the number formats and holder names of 1.20.3
+    fn needs_number_formats(&self) -> bool {
+        matches!(
+            self,
+            ScoreboardCommand::ModifyDisplayAutoUpdate(..)
+                | ScoreboardCommand::ModifyNumberFormat(..)
+                | ScoreboardCommand::DisplayHolderName(..)
+                | ScoreboardCommand::DisplayHolderNumberFormat(..)
+        )
+    }
+
+    /// Compiles this command for the version, failing if an objective name is too long for it,
+    /// the version lacks the subcommand, a team color is not named
+    /// or a score can not be added or removed
+    pub fn command(&self, version: MinecraftVersion) -> anyhow::Result<String> {
+        if self.needs_number_formats() && version.id() < MinecraftVersion::v1_20_3.id() {
+            bail!("Number formats and display names of scores require 1.20.3")
+        }
+        if let Some(max) = version.max_objective_name_length() {
+            for name in self.objectives() {
+                if name.chars().count() > max {
+                    bail!(
+                        "Objective name {} is longer than {} characters, which this version allows",
+                        name,
+                        max
+                    )
+                }
+            }
+        }
+        Ok(match self {
+            ScoreboardCommand::AddObjective(objective) => {
+                let mut cmd = format!(
+                    "scoreboard objectives add {} {}",
+                    objective.name,
+                    objective.criteria.name()?
+                );
+                if let Some(name) = &objective.display_name {
+                    cmd.push_str(&format!(" {}", name.to_json()));
+                }
+                cmd
+            }
+            ScoreboardCommand::RemoveObjective(name) => {
+                format!("scoreboard objectives remove {}", name)
+            }
+            ScoreboardCommand::ListObjectives => "scoreboard objectives list".to_string(),
+            ScoreboardCommand::SetDisplay(slot, objective) => {
+                let mut cmd = format!("scoreboard objectives setdisplay {}", slot.name(version)?);
+                if let Some(objective) = objective {
+                    cmd.push_str(&format!(" {}", objective));
+                }
+                cmd
+            }
+            ScoreboardCommand::ModifyDisplayName(name, display) => format!(
+                "scoreboard objectives modify {} displayname {}",
+                name,
+                display.to_json()
+            ),
+            ScoreboardCommand::ModifyRenderType(name, render_type) => format!(
+                "scoreboard objectives modify {} rendertype {}",
+                name, render_type
+            ),
+            ScoreboardCommand::ModifyDisplayAutoUpdate(name, auto_update) => format!(
+                "scoreboard objectives modify {} displayautoupdate {}",
+                name, auto_update
+            ),
+            ScoreboardCommand::ModifyNumberFormat(name, format) => {
+                let mut cmd = format!("scoreboard objectives modify {} numberformat", name);
+                if let Some(format) = format {
+                    cmd.push_str(&format!(" {}", format));
+                }
+                cmd
+            }
+            ScoreboardCommand::Set(holder, name, score) => {
+                format!("scoreboard players set {} {} {}", holder, name, score)
+            }
+            ScoreboardCommand::Add(_, _, amount) | ScoreboardCommand::Remove(_, _, amount)
+                if *amount < 0 =>
+            {
+                bail!("Can not add or remove a negative amount {}", amount)
+            }
+            ScoreboardCommand::Add(holder, name, amount) => {
+                format!("scoreboard players add {} {} {}", holder, name, amount)
+            }
+            ScoreboardCommand::Remove(holder, name, amount) => {
+                format!("scoreboard players remove {} {} {}", holder, name, amount)
+            }
+            ScoreboardCommand::Reset(holder, name) => match name {
+                Some(name) => format!("scoreboard players reset {} {}", holder, name),
+                None => format!("scoreboard players reset {}", holder),
+            },
+            ScoreboardCommand::Get(holder, name) => {
+                format!("scoreboard players get {} {}", holder, name)
+            }
+            ScoreboardCommand::Enable(holder, name) => {
+                format!("scoreboard players enable {} {}", holder, name)
+            }
+            ScoreboardCommand::Operation {
+                target,
+                objective,
+                operation,
+                source,
+                source_objective,
+            } => format!(
+                "scoreboard players operation {} {} {} {} {}",
+                target, objective, operation, source, source_objective
+            ),
+            ScoreboardCommand::ListScores(holder) => match holder {
+                Some(holder) => format!("scoreboard players list {}", holder),
+                None => "scoreboard players list".to_string(),
+            },
+            ScoreboardCommand::DisplayHolderName(holder, name, display) => {
+                let mut cmd = format!("scoreboard players display name {} {}", holder, name);
+                if let Some(display) = display {
+                    cmd.push_str(&format!(" {}", display.to_json()));
+                }
+                cmd
+            }
+            ScoreboardCommand::DisplayHolderNumberFormat(holder, name, format) => {
+                let mut cmd = format!(
+                    "scoreboard players display numberformat {} {}",
+                    holder, name
+                );
+                if let Some(format) = format {
+                    cmd.push_str(&format!(" {}", format));
+                }
+                cmd
+            }
+        })
+    }
+}
+
+impl CommandLike for ScoreboardCommand {
+    /// Compiles this command for the latest version
+    fn compile(&mut self) -> anyhow::Result<String> {
+        self.command(MinecraftVersion::Latest)
+    }
+}
+
+/// Writes scoreboard commands into a function, checking them against the version
+#[derive(Debug, Clone)]
+pub struct Scoreboard<W> {
+    version: MinecraftVersion,
+    writer: Arc<Mutex<FunctionWriter<W>>>,
+}
+
+impl<W> Scoreboard<W>
+where
+    W: Write,
+{
+    pub fn new(version: MinecraftVersion, writer: Arc<Mutex<FunctionWriter<W>>>) -> Self {
+        Self { version, writer }
+    }
+
+    pub fn run(&mut self, command: ScoreboardCommand) -> anyhow::Result<()> {
+        let line = command.command(self.version)?;
+        self.writer
+            .lock()
+            .unwrap()
+            .write_line(format!("{}\n", line))?;
+        Ok(())
+    }
+}
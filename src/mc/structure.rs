@@ -0,0 +1,320 @@
+//! Structure templates, the gzipped NBT files used by structure blocks and `/place template`.
+//!
+//! ```ignore
+//! let mut structure = Structure::new(Vec3I(3, 3, 3));
+//! structure.set_block(Vec3I(1, 0, 1), Material::GoldBlock)?;
+//! structure.add_entity(Vec3D(1.5, 1.0, 1.5), EntityType::Zombie, EntityMeta::new(EntityType::Zombie))?;
+//! ctx.create_structure("treasure", &structure)?;
+//! ```
+
+use std::io::{Read, Write};
+
+use anyhow::bail;
+use indexmap::IndexMap;
+
+use crate::mc::block::BlockState;
+use crate::mc::entity::meta::EntityMeta;
+use crate::mc::entity::types::EntityType;
+use crate::mc::item::MetaContainer;
+use crate::nbt::compression::{CompressedNbtReader, CompressedNbtWriter, NbtCompression};
+use crate::nbt::{Compound, NbtReader, NbtTag, NbtWriter};
+use crate::utils::{Vec3D, Vec3I};
+
+/// Data version of 1.19.2, the latest version flux supports
+pub const DEFAULT_DATA_VERSION: i32 = 3120;
+
+/// Block of a structure, referencing a state in its palette
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructureBlock {
+    pub pos: Vec3I,
+    pub state: usize,
+    /// Block entity data, without the coordinates
+    pub nbt: Option<Compound>,
+}
+
+/// Entity of a structure
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructureEntity {
+    pub pos: Vec3D,
+    pub block_pos: Vec3I,
+    /// Entity data, including its `id`
+    pub nbt: Compound,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Structure {
+    size: Vec3I,
+    data_version: i32,
+    palette: Vec<BlockState>,
+    blocks: IndexMap<(i32, i32, i32), StructureBlock>,
+    entities: Vec<StructureEntity>,
+}
+
+fn int_list(vec: Vec3I) -> NbtTag {
+    NbtTag::List(vec![
+        NbtTag::Int(vec.0),
+        NbtTag::Int(vec.1),
+        NbtTag::Int(vec.2),
+    ])
+}
+
+fn read_int_list(tag: Option<NbtTag>) -> anyhow::Result<Vec3I> {
+    match tag.map(Vec::<i32>::try_from).transpose()?.as_deref() {
+        Some(&[x, y, z]) => Ok(Vec3I(x, y, z)),
+        _ => bail!("Expected a list of 3 integers in structure"),
+    }
+}
+
+fn read_double_list(tag: Option<NbtTag>) -> anyhow::Result<Vec3D> {
+    match tag.map(Vec::<f64>::try_from).transpose()?.as_deref() {
+        Some(&[x, y, z]) => Ok(Vec3D(x, y, z)),
+        _ => bail!("Expected a list of 3 doubles in structure"),
+    }
+}
+
+/// Removes unset meta values, which are skipped when writing anyway
+fn strip_empty(comp: &mut Compound) {
+    comp.tags.retain(|_, v| *v != NbtTag::Empty);
+    for tag in comp.tags.values_mut() {
+        if let NbtTag::Compound(nested) = tag {
+            strip_empty(nested)
+        }
+    }
+}
+
+impl Structure {
+    pub fn new(size: Vec3I) -> Self {
+        Self {
+            size,
+            data_version: DEFAULT_DATA_VERSION,
+            palette: vec![],
+            blocks: IndexMap::new(),
+            entities: vec![],
+        }
+    }
+
+    pub fn size(&self) -> Vec3I {
+        self.size
+    }
+
+    pub fn data_version(&self) -> i32 {
+        self.data_version
+    }
+
+    pub fn set_data_version(&mut self, version: i32) {
+        self.data_version = version
+    }
+
+    pub fn palette(&self) -> &[BlockState] {
+        &self.palette
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &StructureBlock> {
+        self.blocks.values()
+    }
+
+    pub fn entities(&self) -> &[StructureEntity] {
+        &self.entities
+    }
+
+    /// Returns the block state at the position. Positions without blocks are
+    /// left untouched when the structure is placed.
+    pub fn block(&self, pos: Vec3I) -> Option<&BlockState> {
+        self.blocks
+            .get(&(pos.0, pos.1, pos.2))
+            .and_then(|block| self.palette.get(block.state))
+    }
+
+    fn check_bounds(&self, pos: Vec3D) -> anyhow::Result<()> {
+        let size = self.size;
+        if pos.0 < 0.0
+            || pos.1 < 0.0
+            || pos.2 < 0.0
+            || pos.0 >= size.0 as f64
+            || pos.1 >= size.1 as f64
+            || pos.2 >= size.2 as f64
+        {
+            bail!(
+                "Position {:?} is outside of structure with size {:?}",
+                pos,
+                size
+            )
+        }
+        Ok(())
+    }
+
+    fn state_index(&mut self, state: BlockState) -> usize {
+        match self.palette.iter().position(|it| *it == state) {
+            Some(idx) => idx,
+            None => {
+                self.palette.push(state);
+                self.palette.len() - 1
+            }
+        }
+    }
+
+    pub fn set_block<B: Into<BlockState>>(&mut self, pos: Vec3I, block: B) -> anyhow::Result<()> {
+        self.put_block(pos, block.into(), None)
+    }
+
+    /// Sets a block with block entity data, e.g. a chest with its `Items`
+    pub fn set_block_entity<B: Into<BlockState>>(
+        &mut self,
+        pos: Vec3I,
+        block: B,
+        nbt: Compound,
+    ) -> anyhow::Result<()> {
+        self.put_block(pos, block.into(), Some(nbt))
+    }
+
+    fn put_block(
+        &mut self,
+        pos: Vec3I,
+        state: BlockState,
+        nbt: Option<Compound>,
+    ) -> anyhow::Result<()> {
+        self.check_bounds(Vec3D(pos.0 as f64, pos.1 as f64, pos.2 as f64))?;
+        let state = self.state_index(state);
+        self.blocks
+            .insert((pos.0, pos.1, pos.2), StructureBlock { pos, state, nbt });
+        Ok(())
+    }
+
+    pub fn remove_block(&mut self, pos: Vec3I) -> Option<StructureBlock> {
+        self.blocks.shift_remove(&(pos.0, pos.1, pos.2))
+    }
+
+    /// Adds an entity with provided meta at the position relative to the structure origin
+    pub fn add_entity(
+        &mut self,
+        pos: Vec3D,
+        ty: EntityType,
+        meta: EntityMeta,
+    ) -> anyhow::Result<()> {
+        self.check_bounds(pos)?;
+        let mut nbt = match meta.tag() {
+            NbtTag::Compound(comp) => comp,
+            _ => Compound::default(),
+        };
+        strip_empty(&mut nbt);
+        nbt.insert("id", ty);
+        self.entities.push(StructureEntity {
+            pos,
+            block_pos: Vec3I(
+                pos.0.floor() as i32,
+                pos.1.floor() as i32,
+                pos.2.floor() as i32,
+            ),
+            nbt,
+        });
+        Ok(())
+    }
+
+    /// Writes this structure as gzipped NBT, the way the game stores it
+    pub fn write<W: Write>(&self, write: W) -> anyhow::Result<()> {
+        CompressedNbtWriter::new(write, NbtCompression::Gzip)
+            .write_tag(Some(""), &self.clone().into())?;
+        Ok(())
+    }
+
+    /// Reads a structure template, automatically detecting its compression
+    pub fn read<R: Read>(read: R) -> anyhow::Result<Self> {
+        let (_, tag) = CompressedNbtReader::new(read).read_tag()?;
+        Self::try_from(tag)
+    }
+}
+
+impl Into<NbtTag> for Structure {
+    fn into(self) -> NbtTag {
+        let blocks = self
+            .blocks
+            .into_values()
+            .map(|block| {
+                let mut comp = Compound::default();
+                comp.insert("pos", int_list(block.pos));
+                comp.insert("state", block.state as i32);
+                if let Some(nbt) = block.nbt {
+                    comp.insert("nbt", nbt);
+                }
+                NbtTag::Compound(comp)
+            })
+            .collect::<Vec<NbtTag>>();
+        let entities = self
+            .entities
+            .into_iter()
+            .map(|entity| {
+                let mut comp = Compound::default();
+                comp.insert("pos", entity.pos);
+                comp.insert("blockPos", int_list(entity.block_pos));
+                comp.insert("nbt", entity.nbt);
+                NbtTag::Compound(comp)
+            })
+            .collect::<Vec<NbtTag>>();
+
+        let mut comp = Compound::default();
+        comp.insert("DataVersion", self.data_version);
+        comp.insert("size", int_list(self.size));
+        comp.insert("palette", self.palette);
+        comp.insert("blocks", blocks);
+        comp.insert("entities", entities);
+        NbtTag::Compound(comp)
+    }
+}
+
+impl TryFrom<NbtTag> for Structure {
+    type Error = anyhow::Error;
+
+    /// Reads a structure template. Only the first palette is used
+    /// for templates with multiple palettes, like shipwrecks.
+    fn try_from(tag: NbtTag) -> Result<Self, Self::Error> {
+        let mut comp = Compound::try_from(tag)?;
+        let mut structure = Structure::new(read_int_list(comp.remove("size"))?);
+        if let Some(version) = comp.get_int("DataVersion") {
+            structure.data_version = version;
+        }
+
+        let palette = match (comp.remove("palette"), comp.remove("palettes")) {
+            (Some(palette), _) => palette,
+            (None, Some(NbtTag::List(palettes))) if !palettes.is_empty() => {
+                palettes.into_iter().next().unwrap()
+            }
+            _ => bail!("Structure is missing its palette"),
+        };
+        structure.palette = Vec::<BlockState>::try_from(palette)?;
+
+        for block in
+            Vec::<Compound>::try_from(comp.remove("blocks").unwrap_or(NbtTag::List(vec![])))?
+        {
+            let mut block = block;
+            let pos = read_int_list(block.remove("pos"))?;
+            let state = match block.get_int("state") {
+                Some(state) if (state as usize) < structure.palette.len() && state >= 0 => {
+                    state as usize
+                }
+                _ => bail!("Block at {:?} has invalid palette state", pos),
+            };
+            let nbt = block.remove("nbt").map(Compound::try_from).transpose()?;
+            structure
+                .blocks
+                .insert((pos.0, pos.1, pos.2), StructureBlock { pos, state, nbt });
+        }
+
+        for entity in
+            Vec::<Compound>::try_from(comp.remove("entities").unwrap_or(NbtTag::List(vec![])))?
+        {
+            let mut entity = entity;
+            let pos = read_double_list(entity.remove("pos"))?;
+            let block_pos = read_int_list(entity.remove("blockPos"))?;
+            let nbt = match entity.remove("nbt") {
+                Some(nbt) => Compound::try_from(nbt)?,
+                None => bail!("Entity at {:?} is missing its data", pos),
+            };
+            structure.entities.push(StructureEntity {
+                pos,
+                block_pos,
+                nbt,
+            });
+        }
+        Ok(structure)
+    }
+}
@@ -2,10 +2,13 @@ use crate::mc::entity::Entity;
 use std::io::Write;
 use std::sync::{Arc, Mutex};
 
+use crate::mc::scoreboard::Scoreboard;
 use crate::mc::Identified;
 use crate::modules::functions::FunctionWriter;
+use crate::modules::MinecraftVersion;
 
-use crate::prelude::{CommandLike, Location};
+use crate::nbt::NbtTag;
+use crate::prelude::{CommandLike, EntityType, Location};
 
 #[derive(Debug, Clone)]
 pub struct WorldAccess<W> {
@@ -20,21 +23,30 @@ where
         Self { writer }
     }
 
-    pub fn execute(&mut self, cmd: &mut impl CommandLike) {
-        self.write_line(cmd.compile())
+    pub fn execute(&mut self, cmd: &mut impl CommandLike) -> anyhow::Result<()> {
+        self.write_line(cmd.compile()?);
+        Ok(())
     }
 
-    pub fn summon_entity(&mut self, at: Location, entity: Entity) {
-        let ty = entity.get_type();
-        let mut meta = entity.meta;
-        let mut metastr = meta.compile();
-        if metastr.contains("Tags:[") {
-            metastr = metastr.replacen("Tags:[", &format!("Tags:[\"fluxd{}\"", entity.id), 1)
-        } else {
-            let comma = if metastr.len() > 2 { "," } else { "" };
-            metastr = metastr.replacen("{", &format!("{{Tags:[\"fluxd{}\"]{}", entity.id, comma), 1)
-        }
-        self.write_line(format!("summon {} {} {}", ty.id(), at.to_string(), metastr))
+    pub fn summon_entity(&mut self, at: Location, entity: Entity) -> anyhow::Result<()> {
+        self.summon_meta(at, entity.get_type(), &entity.tracked_tag())
+    }
+
+    /// Summons an entity from its meta tag, see [Entity::tracked_tag]
+    pub(crate) fn summon_meta(
+        &mut self,
+        at: Location,
+        ty: EntityType,
+        meta: &NbtTag,
+    ) -> anyhow::Result<()> {
+        let metastr = meta.stringify()?;
+        self.write_line(format!("summon {} {} {}", ty.id(), at.to_string(), metastr));
+        Ok(())
+    }
+
+    /// Scoreboard that writes into the same function, checking commands against the version
+    pub fn scoreboard(&self, version: MinecraftVersion) -> Scoreboard<W> {
+        Scoreboard::new(version, self.writer.clone())
     }
 
     pub fn write_line<S: Into<String>>(&mut self, line: S) {
@@ -46,6 +46,14 @@ pub struct GlobalFluxConfiguration {
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 #[allow(non_camel_case_types)]
 pub enum MinecraftVersion {
+    #[serde(rename = "1.21")]
+    v1_21,
+    #[serde(rename = "1.20.3")]
+    v1_20_3,
+    #[serde(rename = "1.20.2")]
+    v1_20_2,
+    #[serde(rename = "1.20")]
+    v1_20,
     #[serde(rename = "1.19")]
     v1_19,
     #[serde(rename = "1.18.2")]
@@ -67,13 +75,38 @@ pub enum MinecraftVersion {
 impl MinecraftVersion {
     pub fn id(&self) -> i32 {
         match self {
+            MinecraftVersion::v1_21 => 48,
+            MinecraftVersion::v1_20_3 => 26,
+            MinecraftVersion::v1_20_2 => 18,
+            MinecraftVersion::v1_20 => 15,
             MinecraftVersion::v1_18_2 | MinecraftVersion::v1_19 => 9,
             MinecraftVersion::v1_18 => 8,
             MinecraftVersion::v1_17 => 7,
             MinecraftVersion::v1_16 => 6,
             MinecraftVersion::v1_15 => 5,
             MinecraftVersion::v1_13 => 4,
-            MinecraftVersion::Latest => 9,
+            MinecraftVersion::Latest => 48,
+        }
+    }
+
+    /// Whether text components are written as NBT compounds instead of JSON strings,
+    /// which is the case since 1.20.3
+    pub fn nbt_text_components(&self) -> bool {
+        self.id() >= MinecraftVersion::v1_20_3.id()
+    }
+
+    /// Whether attribute modifiers are identified by namespaced ids instead of UUIDs and
+    /// names, which is the case since 1.21
+    pub fn attribute_modifier_ids(&self) -> bool {
+        self.id() >= MinecraftVersion::v1_21.id()
+    }
+
+    /// Maximum length of scoreboard objective names, which is not limited since 1.18
+    pub fn max_objective_name_length(&self) -> Option<usize> {
+        if self.id() >= MinecraftVersion::v1_18.id() {
+            None
+        } else {
+            Some(16)
         }
     }
 }
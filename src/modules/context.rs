@@ -1,3 +1,4 @@
+use crate::mc::structure::Structure;
 use crate::modules::functions::{FunctionGenerator, FunctionWriter};
 use crate::modules::Module;
 use std::fs::File;
@@ -31,6 +32,22 @@ where
         FunctionGenerator::new(named.into(), &self.namespace_dir)
     }
 
+    /// Writes the structure template to `data/<namespace>/structures/<named>.nbt`
+    pub fn create_structure<S: Into<String>>(
+        &self,
+        named: S,
+        structure: &Structure,
+    ) -> anyhow::Result<()> {
+        let path = self
+            .namespace_dir
+            .join("structures")
+            .join(format!("{}.nbt", named.into()));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        structure.write(File::create(path)?)
+    }
+
     #[cfg(feature = "async_runtime")]
     pub fn create_function_async<S: Into<String>>(
         &self,
@@ -19,6 +19,11 @@ where
         Ok(self.writer.write(line.into().as_bytes())?)
     }
 
+    /// Returns the underlying output, e.g. to inspect the written lines
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
     pub fn close(&mut self) {
         self.writer
             .flush()
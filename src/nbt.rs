@@ -1,10 +1,58 @@
-use std::collections::hash_map::IntoIter;
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::{Read, Write};
+
+use crate::nbt::error::{NbtError, NbtResult};
+use crate::nbt::path::NbtPath;
+use crate::snbt::{SnbtOptions, StringNbtWriter};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use indexmap::map::{IntoIter, Iter};
+use indexmap::IndexMap;
+
+pub mod compression;
+pub mod de;
+pub mod diff;
+pub mod error;
+pub mod json;
+#[doc(hidden)]
+pub mod literal;
+pub mod mutf8;
+pub mod path;
+pub mod ser;
+pub mod typed;
+
+/// Maximum nesting depth of lists and compounds, same as the one used by the game
+pub(crate) const MAX_DEPTH: usize = 512;
+
+/// Binary format of NBT, since Java and Bedrock editions encode tags differently
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NbtEncoding {
+    /// Big-endian NBT with Modified UTF-8 strings, used by Java edition files
+    #[default]
+    Java,
+    /// Same as [NbtEncoding::Java], but the root tag has no name. Used by
+    /// the Java edition protocol since 1.20.2
+    JavaNetwork,
+    /// Little-endian NBT with UTF-8 strings, used by Bedrock edition files
+    BedrockLittleEndian,
+    /// Little-endian NBT where ints, longs and lengths are stored as zigzag varints,
+    /// and strings are prefixed with unsigned varint length. Used by the Bedrock edition protocol
+    BedrockNetwork,
+}
+
+impl NbtEncoding {
+    fn is_little_endian(self) -> bool {
+        matches!(
+            self,
+            NbtEncoding::BedrockLittleEndian | NbtEncoding::BedrockNetwork
+        )
+    }
 
-use crate::snbt::StringNbtWriter;
-use anyhow::bail;
-use byteorder::{BigEndian, WriteBytesExt};
+    /// Whether the root tag is written with a name
+    fn named_root(self) -> bool {
+        self != NbtEncoding::JavaNetwork
+    }
+}
 
 macro_rules! bare_fn {
     ($(
@@ -13,26 +61,162 @@ macro_rules! bare_fn {
     });* $(;)*) =>
     {
         $(
-        fn $wname(bare: $typ, write: &mut impl Write) -> anyhow::Result<()> {
-            write.$write::<BigEndian>(bare).map_err(anyhow::Error::from)
+        fn $wname(bare: $typ, encoding: NbtEncoding, write: &mut impl Write) -> NbtResult<()> {
+            if encoding.is_little_endian() {
+                write.$write::<LittleEndian>(bare)
+            } else {
+                write.$write::<BigEndian>(bare)
+            }
+            .map_err(NbtError::from)
         }
         )*
     };
 }
 
-fn write_byte(bare: i8, write: &mut impl Write) -> anyhow::Result<()> {
-    write.write_i8(bare).map_err(anyhow::Error::from)
+macro_rules! bare_read_fn {
+    ($(
+    $typ:ty = {
+        $read:ident as $rname:ident
+    });* $(;)*) =>
+    {
+        $(
+        fn $rname(encoding: NbtEncoding, read: &mut impl Read) -> NbtResult<$typ> {
+            if encoding.is_little_endian() {
+                read.$read::<LittleEndian>()
+            } else {
+                read.$read::<BigEndian>()
+            }
+            .map_err(NbtError::from)
+        }
+        )*
+    };
 }
 
-bare_fn! {
+fn read_var_u64(read: &mut impl Read, max_bytes: u32) -> NbtResult<u64> {
+    let mut value = 0u64;
+    for idx in 0..max_bytes {
+        let byte = read.read_u8().map_err(NbtError::from)?;
+        value |= ((byte & 0x7F) as u64) << (idx * 7);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(NbtError::parse(format!(
+        "VarInt in NBT input is longer than {} bytes",
+        max_bytes
+    )))
+}
+
+fn write_var_u64(mut value: u64, write: &mut impl Write) -> NbtResult<()> {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            return write.write_u8(byte).map_err(NbtError::from);
+        }
+        write.write_u8(byte | 0x80)?;
+    }
+}
+
+fn read_byte(_encoding: NbtEncoding, read: &mut impl Read) -> NbtResult<i8> {
+    read.read_i8().map_err(NbtError::from)
+}
+
+bare_read_fn! {
     i16 = {
-        write_i16 as write_short
+        read_i16 as read_short
     };
-    i32 = {
-        write_i32 as write_int
+    f32 = {
+        read_f32 as read_float
     };
-    i64 = {
-        write_i64 as write_long
+    f64 = {
+        read_f64 as read_double
+    };
+}
+
+fn read_int(encoding: NbtEncoding, read: &mut impl Read) -> NbtResult<i32> {
+    if encoding == NbtEncoding::BedrockNetwork {
+        let zigzag = read_var_u64(read, 5)? as u32;
+        Ok(((zigzag >> 1) as i32) ^ -((zigzag & 1) as i32))
+    } else if encoding.is_little_endian() {
+        read.read_i32::<LittleEndian>().map_err(NbtError::from)
+    } else {
+        read.read_i32::<BigEndian>().map_err(NbtError::from)
+    }
+}
+
+fn read_long(encoding: NbtEncoding, read: &mut impl Read) -> NbtResult<i64> {
+    if encoding == NbtEncoding::BedrockNetwork {
+        let zigzag = read_var_u64(read, 10)?;
+        Ok(((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64))
+    } else if encoding.is_little_endian() {
+        read.read_i64::<LittleEndian>().map_err(NbtError::from)
+    } else {
+        read.read_i64::<BigEndian>().map_err(NbtError::from)
+    }
+}
+
+fn read_length(encoding: NbtEncoding, read: &mut impl Read) -> NbtResult<usize> {
+    let len = read_int(encoding, read)?;
+    if len < 0 {
+        return Err(NbtError::parse(format!(
+            "Negative length {} provided in NBT input",
+            len
+        )));
+    }
+    Ok(len as usize)
+}
+
+fn read_vec<R, T>(
+    encoding: NbtEncoding,
+    read: &mut R,
+    reader: impl Fn(NbtEncoding, &mut R) -> NbtResult<T>,
+) -> NbtResult<Vec<T>>
+where
+    R: Read,
+{
+    let len = read_length(encoding, read)?;
+    // the length is untrusted, so we don't preallocate more than a small chunk
+    let mut vec = Vec::with_capacity(len.min(4096));
+    for _ in 0..len {
+        vec.push(reader(encoding, read)?);
+    }
+    Ok(vec)
+}
+
+fn read_string(encoding: NbtEncoding, read: &mut impl Read) -> NbtResult<String> {
+    let len = match encoding {
+        NbtEncoding::Java | NbtEncoding::JavaNetwork => {
+            read.read_u16::<BigEndian>().map_err(NbtError::from)? as usize
+        }
+        NbtEncoding::BedrockLittleEndian => {
+            read.read_u16::<LittleEndian>().map_err(NbtError::from)? as usize
+        }
+        NbtEncoding::BedrockNetwork => read_var_u64(read, 5)? as u32 as usize,
+    };
+    // same as with lists, the length is untrusted
+    let mut buf = Vec::with_capacity(len.min(4096));
+    read.by_ref()
+        .take(len as u64)
+        .read_to_end(&mut buf)
+        .map_err(NbtError::from)?;
+    if buf.len() != len {
+        return Err(NbtError::parse("Unexpected end of NBT input"));
+    }
+    if encoding.is_little_endian() {
+        String::from_utf8(buf).map_err(|_| NbtError::parse("Invalid string encoding in NBT input"))
+    } else {
+        mutf8::decode(&buf)
+    }
+}
+
+fn write_byte(bare: i8, _encoding: NbtEncoding, write: &mut impl Write) -> NbtResult<()> {
+    write.write_i8(bare).map_err(NbtError::from)
+}
+
+bare_fn! {
+    i16 = {
+        write_i16 as write_short
     };
     f32 = {
         write_f32 as write_float
@@ -42,59 +226,243 @@ bare_fn! {
     };
 }
 
-fn write_byte_array(arr: &[i8], write: &mut impl Write) -> anyhow::Result<()> {
-    write.write_i32::<BigEndian>(arr.len() as i32)?;
-    for &v in arr {
-        write_byte(v, write)?;
+fn write_int(bare: i32, encoding: NbtEncoding, write: &mut impl Write) -> NbtResult<()> {
+    if encoding == NbtEncoding::BedrockNetwork {
+        write_var_u64(((bare << 1) ^ (bare >> 31)) as u32 as u64, write)
+    } else if encoding.is_little_endian() {
+        write
+            .write_i32::<LittleEndian>(bare)
+            .map_err(NbtError::from)
+    } else {
+        write.write_i32::<BigEndian>(bare).map_err(NbtError::from)
     }
-    Ok(())
 }
 
-fn write_int_array(arr: &[i32], write: &mut impl Write) -> anyhow::Result<()> {
-    write.write_i32::<BigEndian>(arr.len() as i32)?;
-    for &v in arr {
-        write_int(v, write)?;
+fn write_long(bare: i64, encoding: NbtEncoding, write: &mut impl Write) -> NbtResult<()> {
+    if encoding == NbtEncoding::BedrockNetwork {
+        write_var_u64(((bare << 1) ^ (bare >> 63)) as u64, write)
+    } else if encoding.is_little_endian() {
+        write
+            .write_i64::<LittleEndian>(bare)
+            .map_err(NbtError::from)
+    } else {
+        write.write_i64::<BigEndian>(bare).map_err(NbtError::from)
+    }
+}
+
+fn write_length(len: usize, encoding: NbtEncoding, write: &mut impl Write) -> NbtResult<()> {
+    match i32::try_from(len) {
+        Ok(len) => write_int(len, encoding, write),
+        Err(_) => Err(NbtError::ArrayTooLong {
+            path: NbtPath::new(),
+            len,
+        }),
     }
-    Ok(())
 }
 
-fn write_long_array(arr: &[i64], write: &mut impl Write) -> anyhow::Result<()> {
-    write.write_i32::<BigEndian>(arr.len() as i32)?;
+fn write_vec<T: Copy, W: Write>(
+    arr: &[T],
+    encoding: NbtEncoding,
+    write: &mut W,
+    writer: impl Fn(T, NbtEncoding, &mut W) -> NbtResult<()>,
+) -> NbtResult<()> {
+    write_length(arr.len(), encoding, write)?;
     for &v in arr {
-        write_long(v, write)?;
+        writer(v, encoding, write)?;
     }
     Ok(())
 }
 
-fn write_string(str: String, write: &mut impl Write) -> anyhow::Result<()> {
-    write_short(str.len() as i16, write)?;
-    write.write_all(str.as_bytes()).map_err(anyhow::Error::from)
+fn write_string(str: &str, encoding: NbtEncoding, write: &mut impl Write) -> NbtResult<()> {
+    let bytes = if encoding.is_little_endian() {
+        Cow::Borrowed(str.as_bytes())
+    } else {
+        Cow::Owned(mutf8::encode(str))
+    };
+    if encoding == NbtEncoding::BedrockNetwork {
+        write_var_u64(bytes.len() as u64, write)?;
+        return write.write_all(&bytes).map_err(NbtError::from);
+    }
+    if bytes.len() > u16::MAX as usize {
+        return Err(NbtError::StringTooLong {
+            path: NbtPath::new(),
+            len: bytes.len(),
+        });
+    }
+    if encoding.is_little_endian() {
+        write.write_u16::<LittleEndian>(bytes.len() as u16)?;
+    } else {
+        write.write_u16::<BigEndian>(bytes.len() as u16)?;
+    }
+    write.write_all(&bytes).map_err(NbtError::from)
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Compound tag, which keeps its tags in the order they were inserted,
+/// so that it is always written the same way
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Compound {
-    pub(crate) tags: HashMap<String, NbtTag>,
+    pub(crate) tags: IndexMap<String, NbtTag>,
 }
 
 impl Compound {
-    pub fn new(tags: HashMap<String, NbtTag>) -> Self {
-        Self { tags }
+    pub fn new<I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = (String, NbtTag)>,
+    {
+        Self {
+            tags: tags.into_iter().collect(),
+        }
     }
 
-    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, String, NbtTag> {
+    pub fn iter(&self) -> Iter<'_, String, NbtTag> {
         self.tags.iter()
     }
+
+    /// Sorts keys of this compound and all the compounds inside it
+    pub fn sort_keys(&mut self) {
+        self.tags.sort_keys();
+        for tag in self.tags.values_mut() {
+            tag.sort_keys();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn keys(&self) -> indexmap::map::Keys<'_, String, NbtTag> {
+        self.tags.keys()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.tags.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&NbtTag> {
+        self.tags.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut NbtTag> {
+        self.tags.get_mut(key)
+    }
+
+    /// Inserts the tag, returning the previous one. Replaced tags keep their position.
+    pub fn insert<K, V>(&mut self, key: K, value: V) -> Option<NbtTag>
+    where
+        K: Into<String>,
+        V: IntoTag,
+    {
+        self.tags.insert(key.into(), value.nbt())
+    }
+
+    /// Removes the tag, keeping the order of the other tags
+    pub fn remove(&mut self, key: &str) -> Option<NbtTag> {
+        self.tags.shift_remove(key)
+    }
+
+    /// Deeply merges other compound into this one, the same way as `/data merge` does:
+    /// compounds present in both are merged recursively, everything else is replaced
+    pub fn merge(&mut self, other: Compound) {
+        for (key, value) in other {
+            match (self.tags.get_mut(&key), value) {
+                (Some(NbtTag::Compound(existing)), NbtTag::Compound(incoming)) => {
+                    existing.merge(incoming)
+                }
+                (_, value) => {
+                    self.tags.insert(key, value);
+                }
+            }
+        }
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(NbtTag::as_bool)
+    }
+}
+
+macro_rules! tag_accessors {
+    ($($variant:ident: $as:ident, $getter:ident -> $typ:ty = |$v:ident| $conv:expr),* $(,)*) => {
+        impl NbtTag {
+            $(
+            pub fn $as(&self) -> Option<$typ> {
+                match self {
+                    NbtTag::$variant($v) => Some($conv),
+                    _ => None,
+                }
+            }
+            )*
+        }
+
+        impl Compound {
+            $(
+            pub fn $getter(&self, key: &str) -> Option<$typ> {
+                self.get(key).and_then(NbtTag::$as)
+            }
+            )*
+        }
+    };
+}
+
+tag_accessors! {
+    Byte: as_byte, get_byte -> i8 = |v| *v,
+    Short: as_short, get_short -> i16 = |v| *v,
+    Int: as_int, get_int -> i32 = |v| *v,
+    Long: as_long, get_long -> i64 = |v| *v,
+    Float: as_float, get_float -> f32 = |v| *v,
+    Double: as_double, get_double -> f64 = |v| *v,
+    String: as_str, get_string -> &str = |v| v.as_str(),
+    ByteArray: as_byte_array, get_byte_array -> &[i8] = |v| v.as_slice(),
+    IntArray: as_int_array, get_int_array -> &[i32] = |v| v.as_slice(),
+    LongArray: as_long_array, get_long_array -> &[i64] = |v| v.as_slice(),
+    List: as_list, get_list -> &[NbtTag] = |v| v.as_slice(),
+    Compound: as_compound, get_compound -> &Compound = |v| v,
+}
+
+macro_rules! tag_mut_accessors {
+    ($($variant:ident: $as:ident, $getter:ident -> $typ:ty),* $(,)*) => {
+        impl NbtTag {
+            $(
+            pub fn $as(&mut self) -> Option<&mut $typ> {
+                match self {
+                    NbtTag::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+            )*
+        }
+
+        impl Compound {
+            $(
+            pub fn $getter(&mut self, key: &str) -> Option<&mut $typ> {
+                self.get_mut(key).and_then(NbtTag::$as)
+            }
+            )*
+        }
+    };
+}
+
+tag_mut_accessors! {
+    List: as_list_mut, get_list_mut -> Vec<NbtTag>,
+    Compound: as_compound_mut, get_compound_mut -> Compound,
 }
 
 impl From<HashMap<String, NbtTag>> for Compound {
+    /// Creates compound from hash map. Since hash maps don't have a stable order,
+    /// the keys are sorted
     fn from(map: HashMap<String, NbtTag>) -> Self {
-        Compound::new(map)
+        let mut tags = map.into_iter().collect::<IndexMap<String, NbtTag>>();
+        tags.sort_keys();
+        Self { tags }
     }
 }
 
 impl Into<HashMap<String, NbtTag>> for Compound {
     fn into(self) -> HashMap<String, NbtTag> {
-        self.tags
+        self.tags.into_iter().collect()
     }
 }
 
@@ -122,6 +490,65 @@ where
     }
 }
 
+/// Converts borrowed values into tags, so that metas can be written without
+/// being cloned first
+pub trait ToTag {
+    fn to_tag(&self) -> NbtTag;
+}
+
+/// Implements [ToTag] by cloning, for types that do not hold metas or other large trees
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __to_tag_by_clone {
+    ($($typ:ty),* $(,)?) => {
+        $(
+            impl $crate::nbt::ToTag for $typ {
+                fn to_tag(&self) -> $crate::nbt::NbtTag {
+                    Clone::clone(self).into()
+                }
+            }
+        )*
+    };
+}
+
+crate::__to_tag_by_clone!(bool, u8, i8, i16, i32, i64, f32, f64, String, NbtTag);
+
+impl ToTag for Compound {
+    fn to_tag(&self) -> NbtTag {
+        NbtTag::Compound(self.clone())
+    }
+}
+
+impl<T> ToTag for Option<T>
+where
+    T: ToTag,
+{
+    fn to_tag(&self) -> NbtTag {
+        match self {
+            Some(value) => value.to_tag(),
+            None => NbtTag::Empty,
+        }
+    }
+}
+
+impl<T> ToTag for Box<T>
+where
+    T: ToTag,
+{
+    fn to_tag(&self) -> NbtTag {
+        (**self).to_tag()
+    }
+}
+
+impl<T> ToTag for Vec<T>
+where
+    T: ToTag,
+{
+    fn to_tag(&self) -> NbtTag {
+        NbtTag::List(self.iter().map(ToTag::to_tag).collect())
+    }
+}
+
 impl<B> From<Box<B>> for NbtTag
 where
     B: Into<NbtTag>,
@@ -184,20 +611,23 @@ where
             .into_iter()
             .map(|(k, v)| (k.into(), v.into()))
             .collect::<HashMap<String, NbtTag>>();
-        NbtTag::Compound(Compound::new(map))
+        NbtTag::Compound(Compound::from(map))
     }
 }
 
 impl NbtTag {
-    pub fn stringify(self) -> String {
+    pub fn stringify(&self) -> NbtResult<String> {
+        self.stringify_with(SnbtOptions::compact())
+    }
+
+    /// Stringifies this tag with provided formatting, e.g. to pretty print it
+    pub fn stringify_with(&self, options: SnbtOptions) -> NbtResult<String> {
         let mut buf = vec![];
 
-        let mut writer = StringNbtWriter::new(&mut buf);
-        writer
-            .write_tag(None, self)
-            .expect("Could not stringify nbt tag");
+        let mut writer = StringNbtWriter::with_options(&mut buf, options);
+        writer.write_tag(None, self)?;
 
-        String::from_utf8(buf).expect("Could not assemble string from ut8 bytes")
+        String::from_utf8(buf).map_err(|_| NbtError::unsupported("SNBT output is not valid UTF-8"))
     }
 }
 
@@ -271,6 +701,20 @@ where
 }
 
 impl NbtTag {
+    /// Sorts keys of all the compounds inside this tag
+    pub fn sort_keys(&mut self) {
+        match self {
+            NbtTag::Compound(comp) => comp.sort_keys(),
+            NbtTag::List(list) => list.iter_mut().for_each(NbtTag::sort_keys),
+            _ => {}
+        }
+    }
+
+    /// Returns value of a byte tag as boolean, the same way the game does
+    pub fn as_bool(&self) -> Option<bool> {
+        self.as_byte().map(|b| b != 0)
+    }
+
     pub fn id(&self) -> u8 {
         match *self {
             NbtTag::Byte(_) => 0x01,
@@ -290,12 +734,87 @@ impl NbtTag {
     }
 }
 
+fn unexpected_tag(expected: &str, tag: &NbtTag) -> NbtError {
+    NbtError::parse(format!(
+        "Expected {}, got tag with id {}",
+        expected,
+        tag.id()
+    ))
+}
+
+macro_rules! try_from_tag {
+    ($($variant:ident => $typ:ty),* $(,)*) => {
+        $(
+        impl TryFrom<NbtTag> for $typ {
+            type Error = NbtError;
+
+            fn try_from(tag: NbtTag) -> Result<Self, Self::Error> {
+                match tag {
+                    NbtTag::$variant(v) => Ok(v),
+                    other => Err(unexpected_tag(stringify!($variant), &other)),
+                }
+            }
+        }
+        )*
+    };
+}
+
+try_from_tag! {
+    Byte => i8,
+    Short => i16,
+    Int => i32,
+    Long => i64,
+    Float => f32,
+    Double => f64,
+    String => String,
+    Compound => Compound,
+}
+
+impl TryFrom<NbtTag> for bool {
+    type Error = NbtError;
+
+    fn try_from(tag: NbtTag) -> Result<Self, Self::Error> {
+        tag.as_bool().ok_or_else(|| unexpected_tag("Byte", &tag))
+    }
+}
+
+impl<T> TryFrom<NbtTag> for Vec<T>
+where
+    T: TryFrom<NbtTag, Error = NbtError>,
+{
+    type Error = NbtError;
+
+    /// Converts elements of a list or an array tag
+    fn try_from(tag: NbtTag) -> Result<Self, Self::Error> {
+        match tag {
+            NbtTag::List(list) => list
+                .into_iter()
+                .enumerate()
+                .map(|(idx, ele)| T::try_from(ele).map_err(|err| err.at_index(idx)))
+                .collect(),
+            NbtTag::ByteArray(arr) => arr
+                .into_iter()
+                .map(|v| NbtTag::Byte(v).try_into())
+                .collect(),
+            NbtTag::IntArray(arr) => arr.into_iter().map(|v| NbtTag::Int(v).try_into()).collect(),
+            NbtTag::LongArray(arr) => arr
+                .into_iter()
+                .map(|v| NbtTag::Long(v).try_into())
+                .collect(),
+            other => Err(unexpected_tag("List or array", &other)),
+        }
+    }
+}
+
 pub trait NbtWriter {
-    fn write_tag(&mut self, name: Option<String>, tag: NbtTag) -> anyhow::Result<()>;
+    /// Writes the tag without taking ownership of it, so that large trees can be
+    /// written without being cloned
+    fn write_tag(&mut self, name: Option<&str>, tag: &NbtTag) -> NbtResult<()>;
 }
 
 pub struct BinaryNbtWriter<W> {
     write: W,
+    encoding: NbtEncoding,
 }
 
 impl<W> BinaryNbtWriter<W>
@@ -303,64 +822,202 @@ where
     W: Write,
 {
     pub fn new(write: W) -> Self {
-        Self { write }
+        Self::with_encoding(write, NbtEncoding::Java)
+    }
+
+    pub fn with_encoding(write: W, encoding: NbtEncoding) -> Self {
+        Self { write, encoding }
     }
 
-    fn _nn_write_tag(&mut self, tag: NbtTag) -> anyhow::Result<()> {
+    fn _nn_write_tag(&mut self, tag: &NbtTag) -> NbtResult<()> {
+        let enc = self.encoding;
         match tag {
-            NbtTag::Byte(v) => write_byte(v, &mut self.write)?,
-            NbtTag::Short(v) => write_short(v, &mut self.write)?,
-            NbtTag::Int(v) => write_int(v, &mut self.write)?,
-            NbtTag::Long(v) => write_long(v, &mut self.write)?,
-            NbtTag::Float(v) => write_float(v, &mut self.write)?,
-            NbtTag::Double(v) => write_double(v, &mut self.write)?,
-            NbtTag::ByteArray(v) => write_byte_array(&v, &mut self.write)?,
-            NbtTag::String(v) => write_string(v, &mut self.write)?,
+            NbtTag::Byte(v) => write_byte(*v, enc, &mut self.write)?,
+            NbtTag::Short(v) => write_short(*v, enc, &mut self.write)?,
+            NbtTag::Int(v) => write_int(*v, enc, &mut self.write)?,
+            NbtTag::Long(v) => write_long(*v, enc, &mut self.write)?,
+            NbtTag::Float(v) => write_float(*v, enc, &mut self.write)?,
+            NbtTag::Double(v) => write_double(*v, enc, &mut self.write)?,
+            NbtTag::ByteArray(v) => write_vec(v, enc, &mut self.write, write_byte)?,
+            NbtTag::String(v) => write_string(v, enc, &mut self.write)?,
             NbtTag::List(v) => {
                 if v.is_empty() {
                     self.write.write_u8(0u8)?;
-                    self.write.write_i32::<BigEndian>(0)?;
+                    write_length(0, enc, &mut self.write)?;
                 } else {
                     let ty = v[0].id();
                     self.write.write_u8(ty)?;
-                    self.write.write_i32::<BigEndian>(v.len() as i32)?;
-                    for ele in v {
+                    write_length(v.len(), enc, &mut self.write)?;
+                    for (idx, ele) in v.iter().enumerate() {
                         if ele.id() != ty {
-                            bail!("List with more than single type provided!")
+                            return Err(NbtError::HeterogeneousList {
+                                path: NbtPath::new(),
+                                expected: ty,
+                                found: ele.id(),
+                            }
+                            .at_index(idx));
                         }
 
-                        self._nn_write_tag(ele)?;
+                        self._nn_write_tag(ele).map_err(|err| err.at_index(idx))?;
                     }
                 }
             }
             NbtTag::Compound(v) => {
                 for (k, v) in v.iter() {
-                    self.write_tag(Some(k.to_owned()), v.to_owned())?;
+                    self.write_named(k, v).map_err(|err| err.at_key(k))?;
                 }
                 self.write.write_u8(0x00)?;
             }
-            NbtTag::IntArray(v) => write_int_array(&v, &mut self.write)?,
-            NbtTag::LongArray(v) => write_long_array(&v, &mut self.write)?,
+            NbtTag::IntArray(v) => write_vec(v, enc, &mut self.write, write_int)?,
+            NbtTag::LongArray(v) => write_vec(v, enc, &mut self.write, write_long)?,
             _ => {}
         };
         Ok(())
     }
+
+    fn write_named(&mut self, name: &str, tag: &NbtTag) -> NbtResult<()> {
+        if *tag == NbtTag::Empty {
+            return Ok(());
+        }
+        self.write.write_u8(tag.id())?;
+        write_string(name, self.encoding, &mut self.write)?;
+        self._nn_write_tag(tag)
+    }
+
+    /// Unwraps this writer, returning the underlying output
+    pub fn into_inner(self) -> W {
+        self.write
+    }
 }
 
 impl<W> NbtWriter for BinaryNbtWriter<W>
 where
     W: Write,
 {
-    fn write_tag(&mut self, name: Option<String>, tag: NbtTag) -> anyhow::Result<()> {
-        if tag == NbtTag::Empty {
+    /// Writes the root tag. The name is ignored for [NbtEncoding::JavaNetwork],
+    /// since the root tag has no name there
+    fn write_tag(&mut self, name: Option<&str>, tag: &NbtTag) -> NbtResult<()> {
+        if self.encoding.named_root() {
+            return self.write_named(name.unwrap_or_default(), tag);
+        }
+        if *tag == NbtTag::Empty {
             return Ok(());
         }
         self.write.write_u8(tag.id())?;
-        if let Some(name) = name {
-            write_string(name, &mut self.write)?;
-        } else {
-            write_string(String::new(), &mut self.write)?;
-        }
         self._nn_write_tag(tag)
     }
 }
+
+pub trait NbtReader {
+    /// Reads a single named tag, returning its name and the tag itself
+    fn read_tag(&mut self) -> NbtResult<(String, NbtTag)>;
+}
+
+pub struct BinaryNbtReader<R> {
+    read: R,
+    depth: usize,
+    encoding: NbtEncoding,
+}
+
+impl<R> BinaryNbtReader<R>
+where
+    R: Read,
+{
+    pub fn new(read: R) -> Self {
+        Self::with_encoding(read, NbtEncoding::Java)
+    }
+
+    pub fn with_encoding(read: R, encoding: NbtEncoding) -> Self {
+        Self {
+            read,
+            depth: 0,
+            encoding,
+        }
+    }
+
+    fn _nn_read_tag(&mut self, id: u8) -> NbtResult<NbtTag> {
+        let enc = self.encoding;
+        Ok(match id {
+            0x01 => NbtTag::Byte(read_byte(enc, &mut self.read)?),
+            0x02 => NbtTag::Short(read_short(enc, &mut self.read)?),
+            0x03 => NbtTag::Int(read_int(enc, &mut self.read)?),
+            0x04 => NbtTag::Long(read_long(enc, &mut self.read)?),
+            0x05 => NbtTag::Float(read_float(enc, &mut self.read)?),
+            0x06 => NbtTag::Double(read_double(enc, &mut self.read)?),
+            0x07 => NbtTag::ByteArray(read_vec(enc, &mut self.read, read_byte)?),
+            0x08 => NbtTag::String(read_string(enc, &mut self.read)?),
+            0x09 => {
+                let ty = self.read.read_u8().map_err(NbtError::from)?;
+                let len = read_length(enc, &mut self.read)?;
+                if ty == 0x00 && len > 0 {
+                    return Err(NbtError::parse(format!(
+                        "List of {} elements provided with TAG_End element type",
+                        len
+                    )));
+                }
+                self.enter()?;
+                let mut list = Vec::with_capacity(len.min(4096));
+                for idx in 0..len {
+                    let ele = self._nn_read_tag(ty).map_err(|err| err.at_index(idx))?;
+                    list.push(ele);
+                }
+                self.depth -= 1;
+                NbtTag::List(list)
+            }
+            0x0a => {
+                self.enter()?;
+                let mut tags = IndexMap::new();
+                loop {
+                    let ty = self.read.read_u8().map_err(NbtError::from)?;
+                    if ty == 0x00 {
+                        break;
+                    }
+                    let name = read_string(enc, &mut self.read)?;
+                    let tag = self._nn_read_tag(ty).map_err(|err| err.at_key(&name))?;
+                    tags.insert(name, tag);
+                }
+                self.depth -= 1;
+                NbtTag::Compound(Compound::new(tags))
+            }
+            0x0b => NbtTag::IntArray(read_vec(enc, &mut self.read, read_int)?),
+            0x0c => NbtTag::LongArray(read_vec(enc, &mut self.read, read_long)?),
+            other => return Err(NbtError::parse(format!("Unknown NBT tag id {}", other))),
+        })
+    }
+
+    fn enter(&mut self) -> NbtResult<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(NbtError::parse(format!(
+                "NBT input is nested deeper than {} levels",
+                MAX_DEPTH
+            )));
+        }
+        Ok(())
+    }
+
+    /// Unwraps this reader, returning the underlying input
+    pub fn into_inner(self) -> R {
+        self.read
+    }
+}
+
+impl<R> NbtReader for BinaryNbtReader<R>
+where
+    R: Read,
+{
+    /// Reads the root tag. The returned name is empty for [NbtEncoding::JavaNetwork]
+    fn read_tag(&mut self) -> NbtResult<(String, NbtTag)> {
+        let id = self.read.read_u8().map_err(NbtError::from)?;
+        if id == 0x00 {
+            return Err(NbtError::parse("Root NBT tag can not be TAG_End"));
+        }
+        let name = if self.encoding.named_root() {
+            read_string(self.encoding, &mut self.read)?
+        } else {
+            String::new()
+        };
+        let tag = self._nn_read_tag(id)?;
+        Ok((name, tag))
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::{BufReader, Read, Write};
+
+use flate2::bufread::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+
+use crate::nbt::error::NbtResult;
+use crate::nbt::{BinaryNbtReader, BinaryNbtWriter, NbtReader, NbtTag, NbtWriter};
+
+/// Compression applied to binary NBT data
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NbtCompression {
+    /// Raw binary NBT
+    None,
+    /// Gzip compression, used by level.dat, playerdata and structure files
+    #[default]
+    Gzip,
+    /// Zlib compression, used by most chunks in region files
+    Zlib,
+}
+
+impl NbtCompression {
+    /// Detects compression from the first bytes of NBT data
+    pub fn detect(header: &[u8]) -> Self {
+        match header {
+            [0x1f, 0x8b, ..] => NbtCompression::Gzip,
+            // 0x78 is never a valid tag id, so it can only be a zlib header
+            [0x78, flg, ..] if (0x78u16 * 256 + *flg as u16).is_multiple_of(31) => {
+                NbtCompression::Zlib
+            }
+            _ => NbtCompression::None,
+        }
+    }
+}
+
+/// Binary NBT writer that compresses every written root tag
+pub struct CompressedNbtWriter<W> {
+    write: W,
+    compression: NbtCompression,
+}
+
+impl<W> CompressedNbtWriter<W>
+where
+    W: Write,
+{
+    pub fn new(write: W, compression: NbtCompression) -> Self {
+        Self { write, compression }
+    }
+
+    /// Unwraps this writer, returning the underlying output
+    pub fn into_inner(self) -> W {
+        self.write
+    }
+}
+
+impl<W> NbtWriter for CompressedNbtWriter<W>
+where
+    W: Write,
+{
+    fn write_tag(&mut self, name: Option<&str>, tag: &NbtTag) -> NbtResult<()> {
+        match self.compression {
+            NbtCompression::None => BinaryNbtWriter::new(&mut self.write).write_tag(name, tag),
+            NbtCompression::Gzip => {
+                let mut encoder = GzEncoder::new(&mut self.write, Compression::default());
+                BinaryNbtWriter::new(&mut encoder).write_tag(name, tag)?;
+                encoder.finish()?;
+                Ok(())
+            }
+            NbtCompression::Zlib => {
+                let mut encoder = ZlibEncoder::new(&mut self.write, Compression::default());
+                BinaryNbtWriter::new(&mut encoder).write_tag(name, tag)?;
+                encoder.finish()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Binary NBT reader that detects compression of every read root tag
+pub struct CompressedNbtReader<R> {
+    read: BufReader<R>,
+    compression: Option<NbtCompression>,
+}
+
+impl<R> CompressedNbtReader<R>
+where
+    R: Read,
+{
+    /// Creates a reader that automatically detects compression
+    pub fn new(read: R) -> Self {
+        Self {
+            read: BufReader::new(read),
+            compression: None,
+        }
+    }
+
+    /// Creates a reader that always expects provided compression
+    pub fn with_compression(read: R, compression: NbtCompression) -> Self {
+        Self {
+            read: BufReader::new(read),
+            compression: Some(compression),
+        }
+    }
+}
+
+impl<R> NbtReader for CompressedNbtReader<R>
+where
+    R: Read,
+{
+    fn read_tag(&mut self) -> NbtResult<(String, NbtTag)> {
+        let mut header = [0u8; 2];
+        let mut len = 0;
+        if self.compression.is_none() {
+            // a single read can return less than the header at the end of the buffer
+            while len < header.len() {
+                let read = self.read.read(&mut header[len..])?;
+                if read == 0 {
+                    break;
+                }
+                len += read;
+            }
+        }
+        let compression = self
+            .compression
+            .unwrap_or_else(|| NbtCompression::detect(&header[..len]));
+        // decoders only consume the bytes of their own stream, so following root tags are kept
+        let mut read = (&header[..len]).chain(&mut self.read);
+        match compression {
+            NbtCompression::None => BinaryNbtReader::new(&mut read).read_tag(),
+            NbtCompression::Gzip => read_to_end(BinaryNbtReader::new(GzDecoder::new(read))),
+            NbtCompression::Zlib => read_to_end(BinaryNbtReader::new(ZlibDecoder::new(read))),
+        }
+    }
+}
+
+/// Reads a tag from a decoder, then the rest of its stream, e.g. the gzip trailer,
+/// so that the next root tag starts right after it
+fn read_to_end<D: Read>(mut reader: BinaryNbtReader<D>) -> NbtResult<(String, NbtTag)> {
+    let tag = reader.read_tag()?;
+    std::io::copy(&mut reader.into_inner(), &mut std::io::sink())?;
+    Ok(tag)
+}
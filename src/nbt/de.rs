@@ -0,0 +1,341 @@
+use std::io::Read;
+use std::str::FromStr;
+
+use serde::de::value::{SeqDeserializer, StringDeserializer};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::nbt::error::{NbtError, NbtResult};
+use crate::nbt::{BinaryNbtReader, NbtReader, NbtTag};
+
+/// Converts an NBT tag into a deserializable value
+pub fn from_nbt<T>(tag: NbtTag) -> Result<T, NbtError>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(NbtDeserializer::new(tag))
+}
+
+/// Reads a binary NBT tag and deserializes it, returning the name of the root tag with the value
+pub fn from_binary<R, T>(read: R) -> NbtResult<(String, T)>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    let (name, tag) = BinaryNbtReader::new(read).read_tag()?;
+    Ok((name, from_nbt(tag)?))
+}
+
+/// Parses an SNBT string and deserializes it
+pub fn from_snbt<T>(snbt: &str) -> NbtResult<T>
+where
+    T: DeserializeOwned,
+{
+    from_nbt(NbtTag::from_str(snbt)?)
+}
+
+/// Deserializer that reads values from an owned [NbtTag].
+///
+/// Numeric tags can be read into any numeric type they fit into,
+/// and unsigned integers read from the signed tag of the same width keep its bits.
+#[derive(Debug, Clone)]
+pub struct NbtDeserializer {
+    tag: NbtTag,
+}
+
+impl NbtDeserializer {
+    pub fn new(tag: NbtTag) -> Self {
+        Self { tag }
+    }
+
+    fn unexpected(&self, expected: &str) -> NbtError {
+        de::Error::custom(format!(
+            "Expected {}, got tag with id {}",
+            expected,
+            self.tag.id()
+        ))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, NbtError> for NbtTag {
+    type Deserializer = NbtDeserializer;
+
+    fn into_deserializer(self) -> NbtDeserializer {
+        NbtDeserializer::new(self)
+    }
+}
+
+macro_rules! deserialize_unsigned {
+    ($($name:ident: $variant:ident as $typ:ty => $visit:ident),* $(,)*) => {
+        $(
+        fn $name<V>(self, visitor: V) -> Result<V::Value, NbtError>
+        where
+            V: Visitor<'de>,
+        {
+            match self.tag {
+                NbtTag::$variant(v) => visitor.$visit(v as $typ),
+                _ => self.deserialize_any(visitor),
+            }
+        }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for NbtDeserializer {
+    type Error = NbtError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.tag {
+            NbtTag::Empty => visitor.visit_unit(),
+            NbtTag::Byte(v) => visitor.visit_i8(v),
+            NbtTag::Short(v) => visitor.visit_i16(v),
+            NbtTag::Int(v) => visitor.visit_i32(v),
+            NbtTag::Long(v) => visitor.visit_i64(v),
+            NbtTag::Float(v) => visitor.visit_f32(v),
+            NbtTag::Double(v) => visitor.visit_f64(v),
+            NbtTag::String(v) => visitor.visit_string(v),
+            NbtTag::ByteArray(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+            NbtTag::IntArray(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+            NbtTag::LongArray(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+            NbtTag::List(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+            NbtTag::Compound(v) => visitor.visit_map(CompoundAccess {
+                iter: v.into_iter(),
+                value: None,
+            }),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.tag {
+            NbtTag::Byte(v) => visitor.visit_bool(v != 0),
+            _ => Err(self.unexpected("boolean byte")),
+        }
+    }
+
+    deserialize_unsigned! {
+        deserialize_u8: Byte as u8 => visit_u8,
+        deserialize_u16: Short as u16 => visit_u16,
+        deserialize_u32: Int as u32 => visit_u32,
+        deserialize_u64: Long as u64 => visit_u64,
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.tag {
+            NbtTag::String(v) => {
+                let mut chars = v.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => visitor.visit_char(c),
+                    _ => Err(de::Error::custom("Expected a single character string")),
+                }
+            }
+            _ => Err(self.unexpected("string")),
+        }
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.tag {
+            NbtTag::ByteArray(v) => {
+                visitor.visit_byte_buf(v.into_iter().map(|b| b as u8).collect())
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.tag {
+            NbtTag::Empty => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.tag {
+            NbtTag::Empty => visitor.visit_unit(),
+            NbtTag::Compound(ref comp) if comp.tags.is_empty() => visitor.visit_unit(),
+            _ => Err(self.unexpected("empty value")),
+        }
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.tag {
+            NbtTag::String(variant) => visitor.visit_enum(VariantDeserializer {
+                variant,
+                value: None,
+            }),
+            NbtTag::Compound(comp) if comp.tags.len() == 1 => {
+                let (variant, value) = comp.into_iter().next().unwrap();
+                visitor.visit_enum(VariantDeserializer {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            _ => Err(self.unexpected("enum variant name or compound with single key")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u128 f32 f64 str string
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct CompoundAccess {
+    iter: indexmap::map::IntoIter<String, NbtTag>,
+    /// Key and value of the current entry, the key is kept to report errors
+    value: Option<(String, NbtTag)>,
+}
+
+impl<'de> MapAccess<'de> for CompoundAccess {
+    type Error = NbtError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, NbtError>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some((key.clone(), value));
+                let key: StringDeserializer<NbtError> = key.into_deserializer();
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, NbtError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| NbtError::parse("Value requested before its key"))?;
+        seed.deserialize(NbtDeserializer::new(value))
+            .map_err(|err: NbtError| err.at_key(&key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct VariantDeserializer {
+    variant: String,
+    value: Option<NbtTag>,
+}
+
+impl<'de> EnumAccess<'de> for VariantDeserializer {
+    type Error = NbtError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), NbtError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant: StringDeserializer<NbtError> = self.variant.clone().into_deserializer();
+        Ok((seed.deserialize(variant)?, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = NbtError;
+
+    fn unit_variant(self) -> Result<(), NbtError> {
+        match self.value {
+            None => Ok(()),
+            Some(_) => Err(de::Error::custom("Expected unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, NbtError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(NbtDeserializer::new(value)),
+            None => Err(de::Error::custom("Expected newtype variant")),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(value) => de::Deserializer::deserialize_seq(NbtDeserializer::new(value), visitor),
+            None => Err(de::Error::custom("Expected tuple variant")),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, NbtError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(value) => de::Deserializer::deserialize_map(NbtDeserializer::new(value), visitor),
+            None => Err(de::Error::custom("Expected struct variant")),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::nbt::error::{NbtError, NbtResult};
+use crate::nbt::path::{NbtPath, NbtPathNode};
+use crate::nbt::{Compound, NbtTag};
+
+/// Single operation that turns one tag into another, matching one `/data` command
+#[derive(Debug, Clone, PartialEq)]
+pub enum NbtPatch {
+    /// `data merge` at the root, or `data modify ... merge value` for deeper paths
+    Merge(NbtPath, Compound),
+    /// `data modify ... set value`
+    Set(NbtPath, NbtTag),
+    /// `data remove`
+    Remove(NbtPath),
+}
+
+impl NbtPatch {
+    /// Compiles this patch into a command for the target, e.g. `entity @s` or `storage flux:data`.
+    /// Fails for patches that set or remove the root, since `/data` can only merge into it
+    pub fn command<S: Into<String>>(&self, target: S) -> NbtResult<String> {
+        let target = target.into();
+        Ok(match self {
+            NbtPatch::Set(path, _) | NbtPatch::Remove(path) if path.nodes().is_empty() => {
+                return Err(NbtError::unsupported(
+                    "Root of a data target can only be merged, not set or removed",
+                ))
+            }
+            NbtPatch::Merge(path, value) if path.nodes().is_empty() => format!(
+                "data merge {} {}",
+                target,
+                NbtTag::Compound(value.clone()).stringify()?
+            ),
+            NbtPatch::Merge(path, value) => format!(
+                "data modify {} {} merge value {}",
+                target,
+                path,
+                NbtTag::Compound(value.clone()).stringify()?
+            ),
+            NbtPatch::Set(path, value) => format!(
+                "data modify {} {} set value {}",
+                target,
+                path,
+                value.stringify()?
+            ),
+            NbtPatch::Remove(path) => format!("data remove {} {}", target, path),
+        })
+    }
+}
+
+/// Computes operations that turn the old tag into the new one.
+///
+/// Since merging is deep, all added and changed tags are collected into a single
+/// merge, and only the removed tags need separate operations. Lists and arrays are
+/// always replaced as a whole, and keys with [NbtTag::Empty] values count as missing.
+/// Roots that are not both compounds are set as a whole, which can not be compiled into
+/// a command.
+pub fn diff(old: &NbtTag, new: &NbtTag) -> Vec<NbtPatch> {
+    match (old, new) {
+        (NbtTag::Compound(old), NbtTag::Compound(new)) => {
+            let mut patches = vec![];
+            let merged = diff_compound(&NbtPath::new(), old, new, &mut patches);
+            if !merged.is_empty() {
+                patches.push(NbtPatch::Merge(NbtPath::new(), merged));
+            }
+            patches
+        }
+        (old, new) if old == new => vec![],
+        (_, new) => vec![NbtPatch::Set(NbtPath::new(), new.clone())],
+    }
+}
+
+/// Pushes removals into patches, returning the compound that should be merged
+fn diff_compound(
+    path: &NbtPath,
+    old: &Compound,
+    new: &Compound,
+    patches: &mut Vec<NbtPatch>,
+) -> Compound {
+    let present = |comp: &Compound, key: &str| -> bool {
+        !matches!(comp.get(key), None | Some(NbtTag::Empty))
+    };
+    for key in old
+        .keys()
+        .filter(|key| present(old, key) && !present(new, key))
+    {
+        patches.push(NbtPatch::Remove(
+            path.clone().node(NbtPathNode::Named(key.clone())),
+        ));
+    }
+
+    let mut merged = Compound::default();
+    for (key, value) in new.iter() {
+        match (old.get(key), value) {
+            (_, NbtTag::Empty) => {}
+            (Some(NbtTag::Compound(old)), NbtTag::Compound(new)) => {
+                let path = path.clone().node(NbtPathNode::Named(key.clone()));
+                let nested = diff_compound(&path, old, new, patches);
+                if !nested.is_empty() {
+                    merged.insert(key.clone(), nested);
+                }
+            }
+            (Some(old), new) if old == new => {}
+            (_, new) => {
+                merged.insert(key.clone(), new.clone());
+            }
+        }
+    }
+    merged
+}
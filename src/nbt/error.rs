@@ -0,0 +1,146 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{de, ser};
+
+use crate::nbt::path::{NbtPath, NbtPathNode};
+
+pub type NbtResult<T> = Result<T, NbtError>;
+
+/// Error raised when NBT can not be read, written or converted.
+///
+/// Errors that happen inside compounds and lists carry the path to the offending tag,
+/// e.g. `Passengers[2].HandItems[0]`, so it is easy to find in large trees.
+#[derive(Debug)]
+pub enum NbtError {
+    /// List contains tags of different types, which NBT does not allow
+    HeterogeneousList {
+        path: NbtPath,
+        expected: u8,
+        found: u8,
+    },
+    /// String is longer than 65535 bytes once encoded
+    StringTooLong { path: NbtPath, len: usize },
+    /// Array or list has more elements than its length can hold
+    ArrayTooLong { path: NbtPath, len: usize },
+    /// Input is not valid binary or string NBT
+    Parse { path: NbtPath, message: String },
+    /// Value can not be represented in the output, e.g. a NaN double in SNBT
+    Unsupported { path: NbtPath, message: String },
+    /// Underlying reader or writer failed
+    Io(std::io::Error),
+}
+
+impl NbtError {
+    pub fn parse<S: Into<String>>(message: S) -> Self {
+        NbtError::Parse {
+            path: NbtPath::new(),
+            message: message.into(),
+        }
+    }
+
+    pub fn unsupported<S: Into<String>>(message: S) -> Self {
+        NbtError::Unsupported {
+            path: NbtPath::new(),
+            message: message.into(),
+        }
+    }
+
+    /// Path to the tag that caused this error, if it happened inside a tag
+    pub fn path(&self) -> Option<&NbtPath> {
+        match self {
+            NbtError::HeterogeneousList { path, .. }
+            | NbtError::StringTooLong { path, .. }
+            | NbtError::ArrayTooLong { path, .. }
+            | NbtError::Parse { path, .. }
+            | NbtError::Unsupported { path, .. } => Some(path),
+            NbtError::Io(_) => None,
+        }
+    }
+
+    fn prepend(mut self, node: NbtPathNode) -> Self {
+        match &mut self {
+            NbtError::HeterogeneousList { path, .. }
+            | NbtError::StringTooLong { path, .. }
+            | NbtError::ArrayTooLong { path, .. }
+            | NbtError::Parse { path, .. }
+            | NbtError::Unsupported { path, .. } => path.prepend(node),
+            NbtError::Io(_) => {}
+        }
+        self
+    }
+
+    /// Marks this error as happened inside the compound entry with the key
+    pub(crate) fn at_key(self, key: &str) -> Self {
+        self.prepend(NbtPathNode::Named(key.to_string()))
+    }
+
+    /// Marks this error as happened inside the list element with the index
+    pub(crate) fn at_index(self, index: usize) -> Self {
+        self.prepend(NbtPathNode::Index(index as i32))
+    }
+}
+
+impl Display for NbtError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NbtError::HeterogeneousList {
+                expected, found, ..
+            } => write!(
+                f,
+                "List contains tags of different types: expected id {}, found id {}",
+                expected, found
+            )?,
+            NbtError::StringTooLong { len, .. } => write!(
+                f,
+                "String is too long ({} bytes, at most {} allowed)",
+                len,
+                u16::MAX
+            )?,
+            NbtError::ArrayTooLong { len, .. } => write!(
+                f,
+                "Array is too long ({} elements, at most {} allowed)",
+                len,
+                i32::MAX
+            )?,
+            NbtError::Parse { message, .. } | NbtError::Unsupported { message, .. } => {
+                f.write_str(message)?
+            }
+            NbtError::Io(err) => return write!(f, "I/O error: {}", err),
+        };
+        match self.path() {
+            Some(path) if !path.nodes().is_empty() => write!(f, " at {}", path),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for NbtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NbtError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for NbtError {
+    fn from(err: std::io::Error) -> Self {
+        if err.kind() == std::io::ErrorKind::UnexpectedEof {
+            NbtError::parse("Unexpected end of NBT input")
+        } else {
+            NbtError::Io(err)
+        }
+    }
+}
+
+impl ser::Error for NbtError {
+    fn custom<T: Display>(msg: T) -> Self {
+        NbtError::unsupported(msg.to_string())
+    }
+}
+
+impl de::Error for NbtError {
+    fn custom<T: Display>(msg: T) -> Self {
+        NbtError::parse(msg.to_string())
+    }
+}
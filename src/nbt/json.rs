@@ -0,0 +1,195 @@
+//! Conversions between NBT tags and JSON values, for data that is shared between
+//! datapack JSON files, like advancements and loot tables, and NBT storage.
+//!
+//! JSON numbers have no type, so the tag type of each number is picked by a [NumberPolicy].
+//! Converting NBT to JSON keeps every value, but forgets the tag types.
+
+use serde_json::{Map, Number, Value};
+
+use crate::nbt::error::{NbtError, NbtResult};
+use crate::nbt::path::NbtPath;
+use crate::nbt::{Compound, NbtTag};
+
+/// Controls the tag types used for numbers when converting JSON into NBT
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NumberPolicy {
+    /// Integers become the smallest type they fit in, from byte to long,
+    /// and decimal numbers become doubles
+    #[default]
+    Narrowest,
+    /// Integers become ints, or longs if they do not fit in an int,
+    /// and decimal numbers become doubles
+    Int,
+    /// Integers become longs and decimal numbers become doubles
+    Long,
+    /// All numbers become doubles
+    Double,
+}
+
+impl NumberPolicy {
+    fn number(self, number: &Number) -> NbtResult<NbtTag> {
+        let int = match number.as_i64() {
+            Some(int) if self != NumberPolicy::Double => int,
+            _ => {
+                return match number.as_f64() {
+                    Some(double) => Ok(NbtTag::Double(double)),
+                    None => Err(NbtError::unsupported(format!(
+                        "Number {} can not be represented in NBT",
+                        number
+                    ))),
+                }
+            }
+        };
+        Ok(match self {
+            NumberPolicy::Narrowest if int as i8 as i64 == int => NbtTag::Byte(int as i8),
+            NumberPolicy::Narrowest if int as i16 as i64 == int => NbtTag::Short(int as i16),
+            NumberPolicy::Narrowest | NumberPolicy::Int if int as i32 as i64 == int => {
+                NbtTag::Int(int as i32)
+            }
+            _ => NbtTag::Long(int),
+        })
+    }
+}
+
+/// Order in which numeric tags are widened so all elements of a list share the same type
+fn numeric_rank(tag: &NbtTag) -> Option<u8> {
+    match tag {
+        NbtTag::Byte(_) => Some(0),
+        NbtTag::Short(_) => Some(1),
+        NbtTag::Int(_) => Some(2),
+        NbtTag::Long(_) => Some(3),
+        NbtTag::Double(_) => Some(4),
+        _ => None,
+    }
+}
+
+fn widen(tag: NbtTag, rank: u8) -> NbtTag {
+    let (int, double) = match tag {
+        NbtTag::Byte(v) => (v as i64, v as f64),
+        NbtTag::Short(v) => (v as i64, v as f64),
+        NbtTag::Int(v) => (v as i64, v as f64),
+        NbtTag::Long(v) => (v, v as f64),
+        other => return other,
+    };
+    match rank {
+        0 => NbtTag::Byte(int as i8),
+        1 => NbtTag::Short(int as i16),
+        2 => NbtTag::Int(int as i32),
+        3 => NbtTag::Long(int),
+        _ => NbtTag::Double(double),
+    }
+}
+
+fn list(values: &[Value], policy: NumberPolicy) -> NbtResult<NbtTag> {
+    let elements = values
+        .iter()
+        .enumerate()
+        .map(|(idx, value)| from_json(value, policy).map_err(|err| err.at_index(idx)))
+        .filter(|tag| !matches!(tag, Ok(NbtTag::Empty)))
+        .collect::<NbtResult<Vec<NbtTag>>>()?;
+
+    // numbers of different types are widened to the largest one
+    if let Some(ranks) = elements
+        .iter()
+        .map(numeric_rank)
+        .collect::<Option<Vec<u8>>>()
+    {
+        let rank = ranks.into_iter().max().unwrap_or_default();
+        return Ok(NbtTag::List(
+            elements.into_iter().map(|tag| widen(tag, rank)).collect(),
+        ));
+    }
+    if let Some(first) = elements.first() {
+        if let Some(other) = elements.iter().find(|tag| tag.id() != first.id()) {
+            return Err(NbtError::HeterogeneousList {
+                path: NbtPath::new(),
+                expected: first.id(),
+                found: other.id(),
+            });
+        }
+    }
+    Ok(NbtTag::List(elements))
+}
+
+/// Converts a JSON value into an NBT tag. Booleans become bytes and `null` becomes
+/// [NbtTag::Empty], which is skipped inside compounds and lists.
+pub fn from_json(value: &Value, policy: NumberPolicy) -> NbtResult<NbtTag> {
+    Ok(match value {
+        Value::Null => NbtTag::Empty,
+        Value::Bool(bool) => NbtTag::Byte(*bool as i8),
+        Value::Number(number) => policy.number(number)?,
+        Value::String(str) => NbtTag::String(str.clone()),
+        Value::Array(values) => list(values, policy)?,
+        Value::Object(map) => {
+            let mut comp = Compound::default();
+            for (key, value) in map {
+                let tag = from_json(value, policy).map_err(|err| err.at_key(key))?;
+                if tag != NbtTag::Empty {
+                    comp.insert(key.as_str(), tag);
+                }
+            }
+            NbtTag::Compound(comp)
+        }
+    })
+}
+
+fn double(value: f64) -> NbtResult<Value> {
+    match Number::from_f64(value) {
+        Some(number) => Ok(Value::Number(number)),
+        None => Err(NbtError::unsupported(format!(
+            "Non-finite number {} can not be represented in JSON",
+            value
+        ))),
+    }
+}
+
+/// Converts an NBT tag into a JSON value. Numbers and arrays of all types become
+/// JSON numbers and arrays, and [NbtTag::Empty] becomes `null`.
+pub fn to_json(tag: &NbtTag) -> NbtResult<Value> {
+    Ok(match tag {
+        NbtTag::Empty => Value::Null,
+        NbtTag::Byte(v) => Value::from(*v),
+        NbtTag::Short(v) => Value::from(*v),
+        NbtTag::Int(v) => Value::from(*v),
+        NbtTag::Long(v) => Value::from(*v),
+        // shortest representation of the float, so `0.1f` does not become `0.10000000149011612`
+        NbtTag::Float(v) => double(v.to_string().parse().unwrap_or(*v as f64))?,
+        NbtTag::Double(v) => double(*v)?,
+        NbtTag::String(v) => Value::String(v.clone()),
+        NbtTag::ByteArray(arr) => Value::from(arr.clone()),
+        NbtTag::IntArray(arr) => Value::from(arr.clone()),
+        NbtTag::LongArray(arr) => Value::from(arr.clone()),
+        NbtTag::List(list) => Value::Array(
+            list.iter()
+                .enumerate()
+                .map(|(idx, tag)| to_json(tag).map_err(|err| err.at_index(idx)))
+                .collect::<NbtResult<Vec<Value>>>()?,
+        ),
+        NbtTag::Compound(comp) => {
+            let mut map = Map::new();
+            for (key, value) in comp.iter() {
+                if *value != NbtTag::Empty {
+                    map.insert(key.clone(), to_json(value).map_err(|err| err.at_key(key))?);
+                }
+            }
+            Value::Object(map)
+        }
+    })
+}
+
+impl TryFrom<NbtTag> for Value {
+    type Error = NbtError;
+
+    fn try_from(tag: NbtTag) -> Result<Self, Self::Error> {
+        to_json(&tag)
+    }
+}
+
+impl TryFrom<Value> for NbtTag {
+    type Error = NbtError;
+
+    /// Converts the value with the [NumberPolicy::Narrowest] policy
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        from_json(&value, NumberPolicy::default())
+    }
+}
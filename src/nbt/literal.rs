@@ -0,0 +1,294 @@
+//! Conversion of literals in the [nbt!](crate::nbt!) macro, checked at compile time.
+//!
+//! Besides Rust literals, numbers can use SNBT type suffixes like `1b` or `0.5f`,
+//! which the compiler accepts as long as they are only passed to macros. Since their
+//! value is only available as source text, the macro parses it in a constant,
+//! so a literal like `300b` or `1x` fails to compile instead of panicking.
+//! Floats are only checked for their syntax there, and parsed with [str::parse]
+//! when the tag is created.
+
+use crate::nbt::NbtTag;
+
+/// Value of a literal. Strings are unescaped, and floats are stripped of their suffix
+/// and underscores, into a buffer as long as their source text
+#[doc(hidden)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MacroLiteral<const N: usize> {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float([u8; N], usize),
+    Double([u8; N], usize),
+    String([u8; N], usize),
+}
+
+impl<const N: usize> MacroLiteral<N> {
+    /// Parses the source text of a literal, which must be at most `N` bytes long.
+    /// Literals without any suffix become ints and doubles, like in Rust.
+    pub const fn parse(token: &str) -> Self {
+        let token = token.as_bytes();
+        if token.len() > N {
+            panic!("Literal is longer than its buffer")
+        }
+        match token {
+            b"true" => MacroLiteral::Byte(1),
+            b"false" => MacroLiteral::Byte(0),
+            [b'"' | b'\'', ..] => string(token),
+            [b'r', b'"' | b'#', ..] => raw_string(token),
+            [b'b' | b'c', ..] => panic!("Byte and C string literals can not be converted into NBT"),
+            _ => number(token),
+        }
+    }
+
+    pub fn tag(&self) -> NbtTag {
+        match *self {
+            MacroLiteral::Byte(value) => NbtTag::Byte(value),
+            MacroLiteral::Short(value) => NbtTag::Short(value),
+            MacroLiteral::Int(value) => NbtTag::Int(value),
+            MacroLiteral::Long(value) => NbtTag::Long(value),
+            MacroLiteral::Float(buf, len) => NbtTag::Float(
+                text(&buf, len)
+                    .parse()
+                    .expect("Float literals are checked when the macro is compiled"),
+            ),
+            MacroLiteral::Double(buf, len) => NbtTag::Double(
+                text(&buf, len)
+                    .parse()
+                    .expect("Float literals are checked when the macro is compiled"),
+            ),
+            MacroLiteral::String(buf, len) => NbtTag::String(text(&buf, len).to_string()),
+        }
+    }
+}
+
+fn text(buf: &[u8], len: usize) -> &str {
+    std::str::from_utf8(&buf[..len]).expect("Literals are valid UTF-8")
+}
+
+/// Copies the contents of a raw string, e.g. `r#"a"b"#`
+const fn raw_string<const N: usize>(token: &[u8]) -> MacroLiteral<N> {
+    let mut hashes = 0;
+    while token[hashes + 1] == b'#' {
+        hashes += 1;
+    }
+    let mut buf = [0u8; N];
+    let mut len = 0;
+    let mut i = hashes + 2;
+    while i < token.len() - hashes - 1 {
+        buf[len] = token[i];
+        len += 1;
+        i += 1;
+    }
+    MacroLiteral::String(buf, len)
+}
+
+/// Unescapes a string or char literal
+const fn string<const N: usize>(token: &[u8]) -> MacroLiteral<N> {
+    let mut buf = [0u8; N];
+    let mut len = 0;
+    let mut i = 1;
+    while i < token.len() - 1 {
+        if token[i] != b'\\' {
+            buf[len] = token[i];
+            len += 1;
+            i += 1;
+            continue;
+        }
+        i += 1;
+        match token[i] {
+            b'n' => len = push_char(&mut buf, len, '\n' as u32),
+            b'r' => len = push_char(&mut buf, len, '\r' as u32),
+            b't' => len = push_char(&mut buf, len, '\t' as u32),
+            b'0' => len = push_char(&mut buf, len, 0),
+            b'x' => {
+                let code = digit(token[i + 1], 16) * 16 + digit(token[i + 2], 16);
+                len = push_char(&mut buf, len, code as u32);
+                i += 2;
+            }
+            b'u' => {
+                // `\u{e9}`, digits can be separated by underscores
+                let mut code = 0u32;
+                i += 2;
+                while token[i] != b'}' {
+                    if token[i] != b'_' {
+                        code = code * 16 + digit(token[i], 16) as u32;
+                    }
+                    i += 1;
+                }
+                len = push_char(&mut buf, len, code);
+            }
+            // line continuation skips the line break and leading whitespace
+            b'\n' | b'\r' => {
+                while token[i + 1].is_ascii_whitespace() {
+                    i += 1;
+                }
+            }
+            other => len = push_char(&mut buf, len, other as u32),
+        }
+        i += 1;
+    }
+    MacroLiteral::String(buf, len)
+}
+
+/// Writes the UTF-8 encoding of the char into the buffer, returning the new length
+const fn push_char<const N: usize>(buf: &mut [u8; N], len: usize, code: u32) -> usize {
+    let c = match char::from_u32(code) {
+        Some(c) => c,
+        None => panic!("Literal contains an invalid char escape"),
+    };
+    let width = c.len_utf8();
+    if width == 1 {
+        buf[len] = code as u8;
+        return len + 1;
+    }
+    // leading byte has as many high bits set as the encoding has bytes
+    let mut i = width - 1;
+    let mut rest = code;
+    while i > 0 {
+        buf[len + i] = 0x80 | (rest & 0x3f) as u8;
+        rest >>= 6;
+        i -= 1;
+    }
+    buf[len] = (0xff00u16 >> width) as u8 | rest as u8;
+    len + width
+}
+
+const fn digit(byte: u8, radix: u32) -> u64 {
+    let value = match byte {
+        b'0'..=b'9' => byte - b'0',
+        b'a'..=b'f' => byte - b'a' + 10,
+        b'A'..=b'F' => byte - b'A' + 10,
+        _ => panic!("Literal contains an invalid digit"),
+    };
+    if value as u32 >= radix {
+        panic!("Literal contains an invalid digit")
+    }
+    value as u64
+}
+
+const fn number<const N: usize>(token: &[u8]) -> MacroLiteral<N> {
+    let negative = token[0] == b'-';
+    let mut i = negative as usize;
+    // `0b1` is a binary number, but `0b` is a zero byte
+    if token.len() > i + 2 && token[i] == b'0' && matches!(token[i + 1], b'x' | b'o' | b'b') {
+        let radix = match token[i + 1] {
+            b'x' => 16,
+            b'o' => 8,
+            _ => 2,
+        };
+        i += 2;
+        let mut value = Some(0i128);
+        while i < token.len() && !matches!(token[i], b'i' | b'u') {
+            if token[i] != b'_' {
+                value = push_digit(value, radix, digit(token[i], radix as u32));
+            }
+            i += 1;
+        }
+        return integer(value, negative, token.split_at(i).1);
+    }
+
+    // decimal numbers are copied without underscores, in case they turn out to be floats
+    let mut value = Some(0i128);
+    let mut buf = [0u8; N];
+    let mut len = 0;
+    if negative {
+        buf[0] = b'-';
+        len = 1;
+    }
+    let mut decimal = false;
+    while i < token.len() && (token[i].is_ascii_digit() || token[i] == b'_') {
+        if token[i] != b'_' {
+            value = push_digit(value, 10, digit(token[i], 10));
+            buf[len] = token[i];
+            len += 1;
+        }
+        i += 1;
+    }
+    if i < token.len() && token[i] == b'.' {
+        decimal = true;
+        buf[len] = b'.';
+        len += 1;
+        i += 1;
+        while i < token.len() && (token[i].is_ascii_digit() || token[i] == b'_') {
+            if token[i] != b'_' {
+                buf[len] = token[i];
+                len += 1;
+            }
+            i += 1;
+        }
+    }
+    if i < token.len() && matches!(token[i], b'e' | b'E') {
+        decimal = true;
+        buf[len] = b'e';
+        len += 1;
+        i += 1;
+        if i < token.len() && matches!(token[i], b'-' | b'+') {
+            buf[len] = token[i];
+            len += 1;
+            i += 1;
+        }
+        let mut digits = 0;
+        while i < token.len() && (token[i].is_ascii_digit() || token[i] == b'_') {
+            if token[i] != b'_' {
+                buf[len] = token[i];
+                len += 1;
+                digits += 1;
+            }
+            i += 1;
+        }
+        if digits == 0 {
+            panic!("Float literal has an exponent without digits")
+        }
+    }
+
+    let suffix = token.split_at(i).1;
+    match suffix {
+        b"f" | b"F" | b"f32" => MacroLiteral::Float(buf, len),
+        b"d" | b"D" | b"f64" => MacroLiteral::Double(buf, len),
+        b"" if decimal => MacroLiteral::Double(buf, len),
+        _ if decimal => panic!("Decimal literal has an integer suffix"),
+        _ => integer(value, negative, suffix),
+    }
+}
+
+const fn push_digit(value: Option<i128>, radix: i128, digit: u64) -> Option<i128> {
+    match value {
+        Some(value) => match value.checked_mul(radix) {
+            Some(value) => value.checked_add(digit as i128),
+            None => None,
+        },
+        None => None,
+    }
+}
+
+/// Converts an integer with SNBT or Rust suffix, checking it is in range of the type
+const fn integer<const N: usize>(
+    value: Option<i128>,
+    negative: bool,
+    suffix: &[u8],
+) -> MacroLiteral<N> {
+    let value = match value {
+        Some(value) if negative => -value,
+        Some(value) => value,
+        None => panic!("Integer literal is out of range"),
+    };
+    let (min, max) = match suffix {
+        b"" | b"i32" => (i32::MIN as i128, i32::MAX as i128),
+        b"b" | b"B" | b"i8" => (i8::MIN as i128, i8::MAX as i128),
+        b"u8" => (0, u8::MAX as i128),
+        b"s" | b"S" | b"i16" => (i16::MIN as i128, i16::MAX as i128),
+        b"l" | b"L" | b"i64" => (i64::MIN as i128, i64::MAX as i128),
+        _ => panic!("Literal has a suffix that can not be converted into NBT"),
+    };
+    if value < min || value > max {
+        panic!("Integer literal is out of range of its type")
+    }
+    match suffix {
+        b"" | b"i32" => MacroLiteral::Int(value as i32),
+        // bytes are signed, so `255u8` is -1b
+        b"b" | b"B" | b"i8" | b"u8" => MacroLiteral::Byte(value as u8 as i8),
+        b"s" | b"S" | b"i16" => MacroLiteral::Short(value as i16),
+        _ => MacroLiteral::Long(value as i64),
+    }
+}
@@ -0,0 +1,87 @@
+//! Modified UTF-8 encoding, used by Java for strings in binary NBT.
+//!
+//! It differs from the regular UTF-8 in two ways: the NUL character is encoded
+//! as two bytes (`0xC0 0x80`), and supplementary characters are encoded as
+//! surrogate pairs, three bytes per surrogate.
+
+use crate::nbt::error::{NbtError, NbtResult};
+
+/// Encodes the string into Modified UTF-8
+pub fn encode(str: &str) -> Vec<u8> {
+    if !str.bytes().any(|b| b == 0 || b >= 0xF0) {
+        // no NUL and no supplementary characters, so encodings are identical
+        return str.as_bytes().to_vec();
+    }
+
+    let mut buf = Vec::with_capacity(encoded_len(str));
+    let mut units = [0u16; 2];
+    for c in str.chars() {
+        for unit in c.encode_utf16(&mut units).iter() {
+            let unit = *unit as u32;
+            match unit {
+                0x01..=0x7F => buf.push(unit as u8),
+                0x00 | 0x80..=0x7FF => {
+                    buf.push((0xC0 | (unit >> 6)) as u8);
+                    buf.push((0x80 | (unit & 0x3F)) as u8);
+                }
+                _ => {
+                    buf.push((0xE0 | (unit >> 12)) as u8);
+                    buf.push((0x80 | ((unit >> 6) & 0x3F)) as u8);
+                    buf.push((0x80 | (unit & 0x3F)) as u8);
+                }
+            }
+        }
+    }
+    buf
+}
+
+/// Returns the amount of bytes the string takes in Modified UTF-8
+pub fn encoded_len(str: &str) -> usize {
+    str.chars()
+        .map(|c| match c as u32 {
+            0x01..=0x7F => 1,
+            0x00 | 0x80..=0x7FF => 2,
+            0x800..=0xFFFF => 3,
+            _ => 6,
+        })
+        .sum()
+}
+
+/// Decodes a Modified UTF-8 string.
+///
+/// Unpaired surrogates, which Java strings may contain, are replaced with `U+FFFD`.
+/// Raw NUL bytes are accepted like Java's `DataInput.readUTF` does, while four byte
+/// sequences are rejected since Modified UTF-8 has none.
+pub fn decode(bytes: &[u8]) -> NbtResult<String> {
+    if !bytes.iter().any(|b| *b == 0xC0 || *b == 0xED || *b >= 0xF0) {
+        // no encoded NUL, surrogates or four byte sequences, so encodings are identical
+        return match std::str::from_utf8(bytes) {
+            Ok(str) => Ok(str.to_string()),
+            Err(_) => Err(NbtError::parse("Invalid string encoding in NBT input")),
+        };
+    }
+
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter().copied();
+    let continuation = |iter: &mut dyn Iterator<Item = u8>| -> NbtResult<u16> {
+        match iter.next() {
+            Some(b) if b & 0xC0 == 0x80 => Ok((b & 0x3F) as u16),
+            _ => Err(NbtError::parse("Invalid string encoding in NBT input")),
+        }
+    };
+    while let Some(b) = iter.next() {
+        let unit = match b {
+            0x00..=0x7F => b as u16,
+            0xC0..=0xDF => ((b & 0x1F) as u16) << 6 | continuation(&mut iter)?,
+            0xE0..=0xEF => {
+                let high = ((b & 0x0F) as u16) << 12 | continuation(&mut iter)? << 6;
+                high | continuation(&mut iter)?
+            }
+            _ => return Err(NbtError::parse("Invalid string encoding in NBT input")),
+        };
+        units.push(unit);
+    }
+    Ok(char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect())
+}
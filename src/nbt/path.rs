@@ -0,0 +1,715 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::nbt::error::{NbtError, NbtResult};
+use crate::nbt::{Compound, NbtTag};
+use crate::snbt::SnbtParser;
+
+/// Single node of an [NbtPath]
+#[derive(Debug, Clone, PartialEq)]
+pub enum NbtPathNode {
+    /// `{filter}`, matches the root compound if it contains the filter
+    Root(Compound),
+    /// `key`
+    Named(String),
+    /// `key{filter}`, matches the compound under the key if it contains the filter
+    NamedFilter(String, Compound),
+    /// `[]`, matches all elements of a list or an array
+    AllElements,
+    /// `[index]`, negative indices count from the end
+    Index(i32),
+    /// `[{filter}]`, matches all compounds in a list that contain the filter
+    ElementFilter(Compound),
+}
+
+/// Path to tags inside an NBT tree, the same as the ones used by `/data` commands.
+///
+/// Paths can be used to query and modify tags in Rust, with the same semantics as the game:
+/// ```ignore
+/// let path = NbtPath::from_str("Items[0].tag.display.Name")?;
+/// path.set(&mut tag, NbtTag::String("Sword".into()))?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NbtPath {
+    nodes: Vec<NbtPathNode>,
+}
+
+/// Checks whether the tag contains everything present in the filter,
+/// the same way the game compares tags in paths and selectors
+fn matches(filter: &NbtTag, tag: &NbtTag) -> bool {
+    match (filter, tag) {
+        (NbtTag::Compound(filter), NbtTag::Compound(tag)) => compound_matches(filter, tag),
+        (NbtTag::List(filter), NbtTag::List(list)) => {
+            if filter.is_empty() {
+                return list.is_empty();
+            }
+            filter
+                .iter()
+                .all(|expected| list.iter().any(|ele| matches(expected, ele)))
+        }
+        _ => filter == tag,
+    }
+}
+
+fn compound_matches(filter: &Compound, tag: &Compound) -> bool {
+    filter.iter().all(|(k, expected)| {
+        tag.tags
+            .get(k)
+            .map(|actual| matches(expected, actual))
+            .unwrap_or(false)
+    })
+}
+
+fn tag_matches(filter: &Compound, tag: &NbtTag) -> bool {
+    match tag {
+        NbtTag::Compound(comp) => compound_matches(filter, comp),
+        _ => false,
+    }
+}
+
+fn resolve_index(index: i32, len: usize) -> Option<usize> {
+    let idx = if index < 0 {
+        len as i64 + index as i64
+    } else {
+        index as i64
+    };
+    if idx >= 0 && (idx as usize) < len {
+        Some(idx as usize)
+    } else {
+        None
+    }
+}
+
+/// Checks whether the value can replace an element of the list without making it heterogeneous
+fn fits_list(list: &[NbtTag], skip: Option<usize>, value: &NbtTag) -> bool {
+    list.iter()
+        .enumerate()
+        .all(|(idx, ele)| Some(idx) == skip || ele.id() == value.id())
+}
+
+/// Error for a value that has a different type than the other elements of the list
+fn mismatched_element(list: &[NbtTag], value: &NbtTag) -> NbtError {
+    NbtError::HeterogeneousList {
+        path: NbtPath::new(),
+        expected: list
+            .iter()
+            .map(NbtTag::id)
+            .find(|id| *id != value.id())
+            .unwrap_or_default(),
+        found: value.id(),
+    }
+}
+
+fn is_unquoted_key_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '"' | '\'' | '[' | ']' | '.' | '{' | '}')
+}
+
+impl NbtPathNode {
+    fn get<'a>(&self, tag: &'a NbtTag, out: &mut Vec<&'a NbtTag>) {
+        match (self, tag) {
+            (NbtPathNode::Root(filter), tag) if tag_matches(filter, tag) => out.push(tag),
+            (NbtPathNode::Named(key), NbtTag::Compound(comp)) => out.extend(comp.tags.get(key)),
+            (NbtPathNode::NamedFilter(key, filter), NbtTag::Compound(comp)) => out.extend(
+                comp.tags
+                    .get(key)
+                    .filter(|child| tag_matches(filter, child)),
+            ),
+            (NbtPathNode::AllElements, NbtTag::List(list)) => out.extend(list.iter()),
+            (NbtPathNode::Index(idx), NbtTag::List(list)) => {
+                out.extend(resolve_index(*idx, list.len()).map(|idx| &list[idx]))
+            }
+            (NbtPathNode::ElementFilter(filter), NbtTag::List(list)) => {
+                out.extend(list.iter().filter(|ele| tag_matches(filter, ele)))
+            }
+            _ => {}
+        }
+    }
+
+    fn get_mut<'a>(&self, tag: &'a mut NbtTag, out: &mut Vec<&'a mut NbtTag>) {
+        match (self, tag) {
+            (NbtPathNode::Root(filter), tag) if tag_matches(filter, tag) => out.push(tag),
+            (NbtPathNode::Named(key), NbtTag::Compound(comp)) => out.extend(comp.tags.get_mut(key)),
+            (NbtPathNode::NamedFilter(key, filter), NbtTag::Compound(comp)) => out.extend(
+                comp.tags
+                    .get_mut(key)
+                    .filter(|child| tag_matches(filter, child)),
+            ),
+            (NbtPathNode::AllElements, NbtTag::List(list)) => out.extend(list.iter_mut()),
+            (NbtPathNode::Index(idx), NbtTag::List(list)) => {
+                if let Some(idx) = resolve_index(*idx, list.len()) {
+                    out.push(&mut list[idx])
+                }
+            }
+            (NbtPathNode::ElementFilter(filter), NbtTag::List(list)) => {
+                out.extend(list.iter_mut().filter(|ele| tag_matches(filter, ele)))
+            }
+            _ => {}
+        }
+    }
+
+    /// Same as [NbtPathNode::get_mut], but creates missing tags where it is possible
+    fn get_or_create<'a>(
+        &self,
+        tag: &'a mut NbtTag,
+        create: &dyn Fn() -> NbtTag,
+        out: &mut Vec<&'a mut NbtTag>,
+    ) {
+        match (self, tag) {
+            (NbtPathNode::Named(key), NbtTag::Compound(comp)) => {
+                out.push(comp.tags.entry(key.clone()).or_insert_with(create))
+            }
+            (NbtPathNode::NamedFilter(key, filter), NbtTag::Compound(comp)) => {
+                let child = comp
+                    .tags
+                    .entry(key.clone())
+                    .or_insert_with(|| NbtTag::Compound(filter.clone()));
+                if tag_matches(filter, child) {
+                    out.push(child)
+                }
+            }
+            (NbtPathNode::AllElements, NbtTag::List(list)) => {
+                if list.is_empty() {
+                    list.push(create())
+                }
+                out.extend(list.iter_mut())
+            }
+            (NbtPathNode::ElementFilter(filter), NbtTag::List(list)) => {
+                if !list.iter().any(|ele| tag_matches(filter, ele)) {
+                    list.push(NbtTag::Compound(filter.clone()))
+                }
+                out.extend(list.iter_mut().filter(|ele| tag_matches(filter, ele)))
+            }
+            (node, tag) => node.get_mut(tag, out),
+        }
+    }
+
+    /// Creates an empty tag this node can be applied to
+    fn create_parent(&self) -> NbtTag {
+        match self {
+            NbtPathNode::Root(_) | NbtPathNode::Named(_) | NbtPathNode::NamedFilter(_, _) => {
+                NbtTag::Compound(Compound::default())
+            }
+            _ => NbtTag::List(vec![]),
+        }
+    }
+
+    fn set(&self, parent: &mut NbtTag, value: &NbtTag) -> NbtResult<usize> {
+        Ok(match (self, parent) {
+            (NbtPathNode::Root(_), _) => {
+                return Err(NbtError::unsupported(
+                    "Can not replace the root tag of a path",
+                ))
+            }
+            (NbtPathNode::Named(key), NbtTag::Compound(comp)) => {
+                comp.tags.insert(key.clone(), value.clone());
+                1
+            }
+            (NbtPathNode::NamedFilter(key, filter), NbtTag::Compound(comp)) => {
+                match comp.tags.get_mut(key) {
+                    Some(child) if tag_matches(filter, child) => {
+                        *child = value.clone();
+                        1
+                    }
+                    _ => 0,
+                }
+            }
+            (NbtPathNode::AllElements, NbtTag::List(list)) => {
+                if !fits_list(list, None, value) {
+                    return Err(mismatched_element(list, value));
+                }
+                if list.is_empty() {
+                    list.push(value.clone());
+                } else {
+                    list.iter_mut().for_each(|ele| *ele = value.clone());
+                }
+                list.len()
+            }
+            (NbtPathNode::Index(idx), NbtTag::List(list)) => {
+                match resolve_index(*idx, list.len()) {
+                    Some(idx) if fits_list(list, Some(idx), value) => {
+                        list[idx] = value.clone();
+                        1
+                    }
+                    Some(_) => return Err(mismatched_element(list, value)),
+                    None => 0,
+                }
+            }
+            (NbtPathNode::ElementFilter(filter), NbtTag::List(list)) => {
+                let mut count = 0;
+                for ele in list.iter_mut().filter(|ele| tag_matches(filter, ele)) {
+                    *ele = value.clone();
+                    count += 1;
+                }
+                count
+            }
+            _ => 0,
+        })
+    }
+
+    fn remove(&self, parent: &mut NbtTag) -> usize {
+        match (self, parent) {
+            (NbtPathNode::Root(_), NbtTag::Compound(comp)) => {
+                let len = comp.tags.len();
+                comp.tags.clear();
+                len
+            }
+            (NbtPathNode::Named(key), NbtTag::Compound(comp)) => {
+                comp.tags.shift_remove(key).map_or(0, |_| 1)
+            }
+            (NbtPathNode::NamedFilter(key, filter), NbtTag::Compound(comp))
+                if comp.tags.get(key).is_some_and(|c| tag_matches(filter, c)) =>
+            {
+                comp.tags.shift_remove(key);
+                1
+            }
+            (NbtPathNode::AllElements, NbtTag::List(list)) => {
+                let len = list.len();
+                list.clear();
+                len
+            }
+            (NbtPathNode::Index(idx), NbtTag::List(list)) => {
+                match resolve_index(*idx, list.len()) {
+                    Some(idx) => {
+                        list.remove(idx);
+                        1
+                    }
+                    None => 0,
+                }
+            }
+            (NbtPathNode::ElementFilter(filter), NbtTag::List(list)) => {
+                let len = list.len();
+                list.retain(|ele| !tag_matches(filter, ele));
+                len - list.len()
+            }
+            _ => 0,
+        }
+    }
+}
+
+impl NbtPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn nodes(&self) -> &[NbtPathNode] {
+        &self.nodes
+    }
+
+    /// Inserts a node at the start of this path, used to track where errors happen
+    pub(crate) fn prepend(&mut self, node: NbtPathNode) {
+        self.nodes.insert(0, node)
+    }
+
+    /// Appends a node to this path
+    pub fn node(&mut self, node: NbtPathNode) -> Self {
+        self.nodes.push(node);
+        self.clone()
+    }
+
+    /// Appends a `key` node to this path
+    pub fn key<S: Into<String>>(&mut self, key: S) -> Self {
+        self.node(NbtPathNode::Named(key.into()))
+    }
+
+    /// Appends an `[index]` node to this path
+    pub fn index(&mut self, index: i32) -> Self {
+        self.node(NbtPathNode::Index(index))
+    }
+
+    /// Appends an `[]` node to this path
+    pub fn all(&mut self) -> Self {
+        self.node(NbtPathNode::AllElements)
+    }
+
+    /// Appends a `[{filter}]` node to this path
+    pub fn filter(&mut self, filter: Compound) -> Self {
+        self.node(NbtPathNode::ElementFilter(filter))
+    }
+
+    /// Returns all the tags matched by this path
+    pub fn get<'a>(&self, root: &'a NbtTag) -> Vec<&'a NbtTag> {
+        let mut current = vec![root];
+        for node in &self.nodes {
+            let mut next = vec![];
+            for tag in current {
+                node.get(tag, &mut next);
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// Returns all the tags matched by this path mutably
+    pub fn get_mut<'a>(&self, root: &'a mut NbtTag) -> Vec<&'a mut NbtTag> {
+        let mut current = vec![root];
+        for node in &self.nodes {
+            let mut next = vec![];
+            for tag in current {
+                node.get_mut(tag, &mut next);
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// Walks through provided nodes, creating the missing tags. The last
+    /// matched tags are created with the provided function if they are missing.
+    fn walk_or_create<'a>(
+        nodes: &[NbtPathNode],
+        root: &'a mut NbtTag,
+        last: &dyn Fn() -> NbtTag,
+    ) -> Vec<&'a mut NbtTag> {
+        let mut current = vec![root];
+        for (idx, node) in nodes.iter().enumerate() {
+            let create = || match nodes.get(idx + 1) {
+                Some(next) => next.create_parent(),
+                None => last(),
+            };
+            let mut next = vec![];
+            for tag in current {
+                node.get_or_create(tag, &create, &mut next);
+            }
+            current = next;
+        }
+        current
+    }
+
+    fn split_last(&self) -> NbtResult<(&NbtPathNode, &[NbtPathNode])> {
+        match self.nodes.split_last() {
+            Some(split) => Ok(split),
+            None => Err(NbtError::unsupported(
+                "Can not modify tags through an empty path",
+            )),
+        }
+    }
+
+    /// Error for a modification that failed at the tag matched by this path
+    fn error(&self, message: String) -> NbtError {
+        NbtError::Unsupported {
+            path: self.clone(),
+            message,
+        }
+    }
+
+    /// Sets all the tags matched by this path to the value, creating missing parent tags.
+    /// Returns the amount of changed tags.
+    pub fn set(&self, root: &mut NbtTag, value: NbtTag) -> NbtResult<usize> {
+        let (last, parents) = self.split_last()?;
+        let create = || last.create_parent();
+        let mut count = 0;
+        for parent in Self::walk_or_create(parents, root, &create) {
+            count += last.set(parent, &value).map_err(|err| match err {
+                NbtError::HeterogeneousList {
+                    expected, found, ..
+                } => NbtError::HeterogeneousList {
+                    path: self.clone(),
+                    expected,
+                    found,
+                },
+                NbtError::Unsupported { message, .. } => self.error(message),
+                err => err,
+            })?;
+        }
+        Ok(count)
+    }
+
+    /// Inserts the value into all lists matched by this path, the same way as
+    /// `data modify ... insert` does. Negative indices count from the end, so
+    /// `-1` appends the value. Returns the amount of changed lists.
+    pub fn insert(&self, root: &mut NbtTag, index: i32, value: NbtTag) -> NbtResult<usize> {
+        let create = || NbtTag::List(vec![]);
+        let mut count = 0;
+        for target in Self::walk_or_create(&self.nodes, root, &create) {
+            let list = match target {
+                NbtTag::List(list) => list,
+                other => {
+                    return Err(
+                        self.error(format!("Expected a list, got tag with id {}", other.id()))
+                    )
+                }
+            };
+            if !fits_list(list, None, &value) {
+                return Err(NbtError::HeterogeneousList {
+                    path: self.clone(),
+                    expected: list[0].id(),
+                    found: value.id(),
+                });
+            }
+            let len = list.len() as i64;
+            let idx = if index < 0 {
+                len + index as i64 + 1
+            } else {
+                index as i64
+            };
+            if idx < 0 || idx > len {
+                return Err(self.error(format!("Index {} is out of bounds", index)));
+            }
+            list.insert(idx as usize, value.clone());
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Removes all the tags matched by this path, returning the amount of removed tags
+    pub fn remove(&self, root: &mut NbtTag) -> usize {
+        let (last, parents) = match self.nodes.split_last() {
+            Some(split) => split,
+            None => return 0,
+        };
+        let mut current = vec![root];
+        for node in parents {
+            let mut next = vec![];
+            for tag in current {
+                node.get_mut(tag, &mut next);
+            }
+            current = next;
+        }
+        current.into_iter().map(|parent| last.remove(parent)).sum()
+    }
+
+    /// Deeply merges the compound into all the compounds matched by this path, creating
+    /// missing tags, the same way as `data modify ... merge` does.
+    /// Returns the amount of changed compounds.
+    pub fn merge(&self, root: &mut NbtTag, value: Compound) -> NbtResult<usize> {
+        let create = || NbtTag::Compound(Compound::default());
+        let mut count = 0;
+        for target in Self::walk_or_create(&self.nodes, root, &create) {
+            match target {
+                NbtTag::Compound(comp) => comp.merge(value.clone()),
+                other => {
+                    return Err(self.error(format!(
+                        "Expected a compound, got tag with id {}",
+                        other.id()
+                    )))
+                }
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+struct PathParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> PathParser<'a> {
+    fn error(&self, msg: &str) -> NbtError {
+        NbtError::parse(format!(
+            "{} at position {}: {}<--[HERE]",
+            msg,
+            self.pos,
+            &self.src[..self.pos]
+        ))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn expect(&mut self, c: char) -> NbtResult<()> {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected '{}'", c)))
+        }
+    }
+
+    fn parse_compound(&mut self) -> NbtResult<Compound> {
+        let mut parser = SnbtParser::new(&self.src[self.pos..]);
+        let (tag, consumed) = parser
+            .parse_partial()
+            .map_err(|e| self.error(&format!("Invalid filter ({})", e)))?;
+        self.pos += consumed;
+        match tag {
+            NbtTag::Compound(comp) => Ok(comp),
+            _ => Err(self.error("Expected compound filter")),
+        }
+    }
+
+    fn parse_key(&mut self) -> NbtResult<String> {
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.pos += 1;
+                let mut key = String::new();
+                loop {
+                    match self.peek() {
+                        None => return Err(self.error("Unclosed quoted key")),
+                        Some('\\') => {
+                            self.pos += 1;
+                            match self.peek() {
+                                Some(c @ ('\\' | '"' | '\'')) => {
+                                    key.push(c);
+                                    self.pos += 1;
+                                }
+                                _ => return Err(self.error("Invalid escape sequence")),
+                            }
+                        }
+                        Some(c) => {
+                            self.pos += c.len_utf8();
+                            if c == quote {
+                                return Ok(key);
+                            }
+                            key.push(c);
+                        }
+                    }
+                }
+            }
+            _ => {
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    if !is_unquoted_key_char(c) {
+                        break;
+                    }
+                    self.pos += c.len_utf8();
+                }
+                if start == self.pos {
+                    return Err(self.error("Expected key"));
+                }
+                Ok(self.src[start..self.pos].to_string())
+            }
+        }
+    }
+
+    fn parse_bracket(&mut self) -> NbtResult<NbtPathNode> {
+        self.expect('[')?;
+        let node = match self.peek() {
+            Some(']') => NbtPathNode::AllElements,
+            Some('{') => NbtPathNode::ElementFilter(self.parse_compound()?),
+            _ => {
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    if !(c.is_ascii_digit() || (c == '-' && self.pos == start)) {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                let idx = i32::from_str(&self.src[start..self.pos])
+                    .map_err(|_| self.error("Expected index"))?;
+                NbtPathNode::Index(idx)
+            }
+        };
+        self.expect(']')?;
+        Ok(node)
+    }
+
+    fn parse(&mut self) -> NbtResult<NbtPath> {
+        let mut nodes = vec![];
+        while self.pos < self.src.len() {
+            let node = match self.peek() {
+                Some('{') if nodes.is_empty() => NbtPathNode::Root(self.parse_compound()?),
+                Some('{') => return Err(self.error("Filters are only allowed at path start")),
+                Some('[') => self.parse_bracket()?,
+                _ => {
+                    let key = self.parse_key()?;
+                    if self.peek() == Some('{') {
+                        NbtPathNode::NamedFilter(key, self.parse_compound()?)
+                    } else {
+                        NbtPathNode::Named(key)
+                    }
+                }
+            };
+            nodes.push(node);
+            if !matches!(self.peek(), None | Some('[')) {
+                self.expect('.')?;
+                if self.pos == self.src.len() {
+                    return Err(self.error("Expected key"));
+                }
+            }
+        }
+        if nodes.is_empty() {
+            return Err(self.error("Expected path"));
+        }
+        Ok(NbtPath { nodes })
+    }
+}
+
+impl FromStr for NbtPath {
+    type Err = NbtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PathParser { src: s, pos: 0 }.parse()
+    }
+}
+
+fn write_key(f: &mut Formatter<'_>, key: &str) -> std::fmt::Result {
+    if !key.is_empty() && key.chars().all(is_unquoted_key_char) {
+        f.write_str(key)
+    } else {
+        f.write_str("\"")?;
+        for c in key.chars() {
+            if c == '"' || c == '\\' {
+                f.write_str("\\")?;
+            }
+            f.write_fmt(format_args!("{}", c))?;
+        }
+        f.write_str("\"")
+    }
+}
+
+fn write_filter(f: &mut Formatter<'_>, filter: &Compound) -> std::fmt::Result {
+    let snbt = NbtTag::Compound(filter.clone())
+        .stringify()
+        .map_err(|_| std::fmt::Error)?;
+    f.write_str(&snbt)
+}
+
+impl Display for NbtPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (idx, node) in self.nodes.iter().enumerate() {
+            match node {
+                NbtPathNode::Root(filter) => write_filter(f, filter)?,
+                NbtPathNode::Named(key) | NbtPathNode::NamedFilter(key, _) => {
+                    if idx > 0 {
+                        f.write_str(".")?;
+                    }
+                    write_key(f, key)?;
+                    if let NbtPathNode::NamedFilter(_, filter) = node {
+                        write_filter(f, filter)?;
+                    }
+                }
+                NbtPathNode::AllElements => f.write_str("[]")?,
+                NbtPathNode::Index(idx) => f.write_fmt(format_args!("[{}]", idx))?,
+                NbtPathNode::ElementFilter(filter) => {
+                    f.write_str("[")?;
+                    write_filter(f, filter)?;
+                    f.write_str("]")?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Into<String> for NbtPath {
+    fn into(self) -> String {
+        self.to_string()
+    }
+}
+
+impl TryFrom<&str> for NbtPath {
+    type Error = <NbtPath as FromStr>::Err;
+
+    fn try_from(path: &str) -> Result<Self, Self::Error> {
+        NbtPath::from_str(path)
+    }
+}
+
+impl TryFrom<String> for NbtPath {
+    type Error = <NbtPath as FromStr>::Err;
+
+    fn try_from(path: String) -> Result<Self, Self::Error> {
+        NbtPath::from_str(&path)
+    }
+}
+
+/// Converts a path argument, so that commands can take both built paths and strings
+pub(crate) fn try_path<P>(path: P) -> anyhow::Result<NbtPath>
+where
+    P: TryInto<NbtPath>,
+    P::Error: Into<anyhow::Error>,
+{
+    path.try_into().map_err(Into::into)
+}
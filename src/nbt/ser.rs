@@ -0,0 +1,598 @@
+use std::io::Write;
+
+use serde::ser::{
+    Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+};
+use serde::{ser, Serialize};
+
+use crate::nbt::error::{NbtError, NbtResult};
+use crate::nbt::path::NbtPath;
+use crate::nbt::typed::{BYTE_ARRAY_TOKEN, INT_ARRAY_TOKEN, LONG_ARRAY_TOKEN};
+use crate::nbt::{BinaryNbtWriter, Compound, NbtTag, NbtWriter};
+
+/// Converts a serializable value into an NBT tag
+pub fn to_nbt<T>(value: &T) -> Result<NbtTag, NbtError>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(NbtSerializer)
+}
+
+/// Serializes a value and writes it as a named binary NBT tag
+pub fn to_binary<W, T>(write: W, name: Option<String>, value: &T) -> NbtResult<()>
+where
+    W: Write,
+    T: Serialize + ?Sized,
+{
+    BinaryNbtWriter::new(write).write_tag(name.as_deref(), &to_nbt(value)?)
+}
+
+/// Serializes a value into an SNBT string
+pub fn to_snbt<T>(value: &T) -> NbtResult<String>
+where
+    T: Serialize + ?Sized,
+{
+    to_nbt(value)?.stringify()
+}
+
+/// Serializer that produces [NbtTag]s.
+///
+/// Signed integers map to the tag of the same width, unsigned integers
+/// are stored in the signed tag of the same width, keeping their bits.
+/// `None` is serialized as [NbtTag::Empty], which is omitted from compounds.
+#[derive(Debug, Copy, Clone)]
+pub struct NbtSerializer;
+
+impl ser::Serializer for NbtSerializer {
+    type Ok = NbtTag;
+    type Error = NbtError;
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeVariant<SerializeList>;
+    type SerializeMap = SerializeCompound;
+    type SerializeStruct = SerializeCompound;
+    type SerializeStructVariant = SerializeVariant<SerializeCompound>;
+
+    fn serialize_bool(self, v: bool) -> Result<NbtTag, NbtError> {
+        Ok(v.into())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Byte(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Short(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Int(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Long(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Byte(v as i8))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Short(v as i16))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Int(v as i32))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Long(v as i64))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Float(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::ByteArray(v.iter().map(|&b| b as i8).collect()))
+    }
+
+    fn serialize_none(self) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Empty)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<NbtTag, NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Empty)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Compound(Compound::default()))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<NbtTag, NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let tag = value.serialize(self)?;
+        match name {
+            BYTE_ARRAY_TOKEN => typed_array(tag, |t| match t {
+                NbtTag::Byte(v) => Some(v),
+                _ => None,
+            })
+            .map(NbtTag::ByteArray),
+            INT_ARRAY_TOKEN => typed_array(tag, |t| match t {
+                NbtTag::Int(v) => Some(v),
+                _ => None,
+            })
+            .map(NbtTag::IntArray),
+            LONG_ARRAY_TOKEN => typed_array(tag, |t| match t {
+                NbtTag::Long(v) => Some(v),
+                _ => None,
+            })
+            .map(NbtTag::LongArray),
+            _ => Ok(tag),
+        }
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<NbtTag, NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let tag = value.serialize(self)?;
+        Ok(NbtTag::Compound(Compound::new([(
+            variant.to_string(),
+            tag,
+        )])))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, NbtError> {
+        Ok(SerializeList {
+            list: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, NbtError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, NbtError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeList>, NbtError> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeCompound, NbtError> {
+        Ok(SerializeCompound {
+            compound: Compound::default(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeCompound, NbtError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeCompound>, NbtError> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+fn typed_array<T>(tag: NbtTag, extract: fn(NbtTag) -> Option<T>) -> Result<Vec<T>, NbtError> {
+    match tag {
+        NbtTag::List(list) => list
+            .into_iter()
+            .map(|ele| {
+                let id = ele.id();
+                extract(ele).ok_or_else(|| {
+                    NbtError::unsupported(format!(
+                        "Unexpected tag with id {} inside typed array",
+                        id
+                    ))
+                })
+            })
+            .collect(),
+        other => Err(NbtError::unsupported(format!(
+            "Expected a sequence for typed array, got tag with id {}",
+            other.id()
+        ))),
+    }
+}
+
+pub struct SerializeList {
+    list: Vec<NbtTag>,
+}
+
+impl SerializeSeq for SerializeList {
+    type Ok = NbtTag;
+    type Error = NbtError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let idx = self.list.len();
+        let tag = value
+            .serialize(NbtSerializer)
+            .map_err(|err| err.at_index(idx))?;
+        if tag == NbtTag::Empty {
+            return Err(
+                NbtError::unsupported("Can not store an empty value inside a list").at_index(idx),
+            );
+        }
+        if let Some(first) = self.list.first() {
+            if first.id() != tag.id() {
+                return Err(NbtError::HeterogeneousList {
+                    path: NbtPath::new(),
+                    expected: first.id(),
+                    found: tag.id(),
+                }
+                .at_index(idx));
+            }
+        }
+        self.list.push(tag);
+        Ok(())
+    }
+
+    fn end(self) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::List(self.list))
+    }
+}
+
+impl SerializeTuple for SerializeList {
+    type Ok = NbtTag;
+    type Error = NbtError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<NbtTag, NbtError> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl SerializeTupleStruct for SerializeList {
+    type Ok = NbtTag;
+    type Error = NbtError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<NbtTag, NbtError> {
+        SerializeSeq::end(self)
+    }
+}
+
+pub struct SerializeCompound {
+    compound: Compound,
+    key: Option<String>,
+}
+
+impl SerializeCompound {
+    fn insert(&mut self, key: String, tag: NbtTag) {
+        if tag != NbtTag::Empty {
+            self.compound.tags.insert(key, tag);
+        }
+    }
+}
+
+impl SerializeMap for SerializeCompound {
+    type Ok = NbtTag;
+    type Error = NbtError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.key = Some(key.serialize(MapKeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| NbtError::unsupported("Value serialized before its key"))?;
+        let tag = value
+            .serialize(NbtSerializer)
+            .map_err(|err| err.at_key(&key))?;
+        self.insert(key, tag);
+        Ok(())
+    }
+
+    fn end(self) -> Result<NbtTag, NbtError> {
+        Ok(NbtTag::Compound(self.compound))
+    }
+}
+
+impl SerializeStruct for SerializeCompound {
+    type Ok = NbtTag;
+    type Error = NbtError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let tag = value
+            .serialize(NbtSerializer)
+            .map_err(|err| err.at_key(key))?;
+        self.insert(key.to_string(), tag);
+        Ok(())
+    }
+
+    fn end(self) -> Result<NbtTag, NbtError> {
+        SerializeMap::end(self)
+    }
+}
+
+/// Serializes enum variants with data as a compound with a single key
+pub struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> SerializeVariant<S> {
+    fn wrap(variant: &'static str, tag: NbtTag) -> NbtTag {
+        NbtTag::Compound(Compound::new([(variant.to_string(), tag)]))
+    }
+}
+
+impl SerializeTupleVariant for SerializeVariant<SerializeList> {
+    type Ok = NbtTag;
+    type Error = NbtError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<NbtTag, NbtError> {
+        Ok(Self::wrap(self.variant, SerializeSeq::end(self.inner)?))
+    }
+}
+
+impl SerializeStructVariant for SerializeVariant<SerializeCompound> {
+    type Ok = NbtTag;
+    type Error = NbtError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<NbtTag, NbtError> {
+        Ok(Self::wrap(self.variant, SerializeMap::end(self.inner)?))
+    }
+}
+
+/// Serializes compound keys, which can only be strings, chars or integers
+struct MapKeySerializer;
+
+fn key_error() -> NbtError {
+    NbtError::unsupported("Compound keys must be strings")
+}
+
+macro_rules! key_to_string {
+    ($($name:ident: $typ:ty),* $(,)*) => {
+        $(
+        fn $name(self, v: $typ) -> Result<String, NbtError> {
+            Ok(v.to_string())
+        }
+        )*
+    };
+}
+
+impl ser::Serializer for MapKeySerializer {
+    type Ok = String;
+    type Error = NbtError;
+    type SerializeSeq = Impossible<String, NbtError>;
+    type SerializeTuple = Impossible<String, NbtError>;
+    type SerializeTupleStruct = Impossible<String, NbtError>;
+    type SerializeTupleVariant = Impossible<String, NbtError>;
+    type SerializeMap = Impossible<String, NbtError>;
+    type SerializeStruct = Impossible<String, NbtError>;
+    type SerializeStructVariant = Impossible<String, NbtError>;
+
+    key_to_string! {
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<String, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_none(self) -> Result<String, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<String, NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, NbtError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<String, NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, NbtError>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, NbtError> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, NbtError> {
+        Err(key_error())
+    }
+}
@@ -0,0 +1,380 @@
+//! Helpers for `#[serde(with = "...")]` attributes, that control which tag is used
+//! to store a value in NBT.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Villager {
+//!     #[serde(with = "flux::nbt::typed::byte")]
+//!     level: i32,
+//!     #[serde(with = "flux::nbt::typed::short", default)]
+//!     cooldown: Option<u32>,
+//!     #[serde(with = "flux::nbt::typed::int_array")]
+//!     uuid: Vec<i32>,
+//! }
+//! ```
+//!
+//! Values that do not fit into the chosen width fail to serialize. Typed arrays are
+//! plain sequences for any serde format other than NBT.
+
+use std::fmt::Formatter;
+use std::marker::PhantomData;
+
+use serde::de::{Error as _, SeqAccess, Visitor};
+use serde::ser::{Error as _, SerializeSeq};
+use serde::{Deserializer, Serialize, Serializer};
+
+pub(crate) const BYTE_ARRAY_TOKEN: &str = "__flux_nbt_byte_array";
+pub(crate) const INT_ARRAY_TOKEN: &str = "__flux_nbt_int_array";
+pub(crate) const LONG_ARRAY_TOKEN: &str = "__flux_nbt_long_array";
+
+/// Numeric tag a value is stored as
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Width {
+    Byte,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+}
+
+/// Numeric value that can be stored in a numeric tag of any width
+pub trait NbtNumber: Sized {
+    fn serialize_as<S>(&self, width: Width, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer;
+
+    fn deserialize_number<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>;
+}
+
+fn serialize_integer<S>(value: i128, width: Width, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    macro_rules! narrow {
+        ($typ:ty, $ser:ident) => {
+            match <$typ>::try_from(value) {
+                Ok(v) => serializer.$ser(v),
+                Err(_) => Err(S::Error::custom(format!(
+                    "Value {} does not fit into {:?} tag",
+                    value, width
+                ))),
+            }
+        };
+    }
+
+    match width {
+        Width::Byte => narrow!(i8, serialize_i8),
+        Width::Short => narrow!(i16, serialize_i16),
+        Width::Int => narrow!(i32, serialize_i32),
+        Width::Long => narrow!(i64, serialize_i64),
+        Width::Float => serializer.serialize_f32(value as f32),
+        Width::Double => serializer.serialize_f64(value as f64),
+    }
+}
+
+enum Number {
+    Integer(i128),
+    Float(f64),
+}
+
+struct NumberVisitor;
+
+macro_rules! visit_number {
+    ($($visit:ident: $typ:ty => $variant:ident as $cast:ty),* $(,)*) => {
+        $(
+        fn $visit<E>(self, v: $typ) -> Result<Number, E> {
+            Ok(Number::$variant(v as $cast))
+        }
+        )*
+    };
+}
+
+impl<'de> Visitor<'de> for NumberVisitor {
+    type Value = Number;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a numeric tag")
+    }
+
+    visit_number! {
+        visit_i8: i8 => Integer as i128,
+        visit_i16: i16 => Integer as i128,
+        visit_i32: i32 => Integer as i128,
+        visit_i64: i64 => Integer as i128,
+        visit_u8: u8 => Integer as i128,
+        visit_u16: u16 => Integer as i128,
+        visit_u32: u32 => Integer as i128,
+        visit_u64: u64 => Integer as i128,
+        visit_f32: f32 => Float as f64,
+        visit_f64: f64 => Float as f64,
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Number, E> {
+        Ok(Number::Integer(v as i128))
+    }
+}
+
+macro_rules! integer_number {
+    ($($typ:ty),* $(,)*) => {
+        $(
+        impl NbtNumber for $typ {
+            fn serialize_as<S>(&self, width: Width, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serialize_integer(*self as i128, width, serializer)
+            }
+
+            fn deserialize_number<'de, D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                match deserializer.deserialize_any(NumberVisitor)? {
+                    Number::Integer(v) => <$typ>::try_from(v).map_err(|_| {
+                        D::Error::custom(format!(
+                            "Value {} does not fit into {}",
+                            v,
+                            stringify!($typ)
+                        ))
+                    }),
+                    Number::Float(_) => Err(D::Error::custom(concat!(
+                        "Expected an integer tag for ",
+                        stringify!($typ)
+                    ))),
+                }
+            }
+        }
+        )*
+    };
+}
+
+integer_number!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+macro_rules! float_number {
+    ($($typ:ty),* $(,)*) => {
+        $(
+        impl NbtNumber for $typ {
+            fn serialize_as<S>(&self, width: Width, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                match width {
+                    Width::Float => serializer.serialize_f32(*self as f32),
+                    Width::Double => serializer.serialize_f64(*self as f64),
+                    _ => Err(S::Error::custom(format!(
+                        "Can not store floating point value in {:?} tag",
+                        width
+                    ))),
+                }
+            }
+
+            fn deserialize_number<'de, D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                Ok(match deserializer.deserialize_any(NumberVisitor)? {
+                    Number::Integer(v) => v as $typ,
+                    Number::Float(v) => v as $typ,
+                })
+            }
+        }
+        )*
+    };
+}
+
+float_number!(f32, f64);
+
+impl<N> NbtNumber for Option<N>
+where
+    N: NbtNumber,
+{
+    fn serialize_as<S>(&self, width: Width, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Some(v) => v.serialize_as(width, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    fn deserialize_number<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct OptionVisitor<N>(PhantomData<N>);
+
+        impl<'de, N> Visitor<'de> for OptionVisitor<N>
+        where
+            N: NbtNumber,
+        {
+            type Value = Option<N>;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                formatter.write_str("an optional numeric tag")
+            }
+
+            fn visit_none<E>(self) -> Result<Self::Value, E> {
+                Ok(None)
+            }
+
+            fn visit_unit<E>(self) -> Result<Self::Value, E> {
+                Ok(None)
+            }
+
+            fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                N::deserialize_number(deserializer).map(Some)
+            }
+        }
+
+        deserializer.deserialize_option(OptionVisitor(PhantomData))
+    }
+}
+
+macro_rules! width_modules {
+    ($($name:ident => $width:ident),* $(,)*) => {
+        $(
+        #[doc = concat!("Stores a numeric value as ", stringify!($width), " tag")]
+        pub mod $name {
+            use super::{NbtNumber, Width};
+            use serde::{Deserializer, Serializer};
+
+            pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                T: NbtNumber,
+                S: Serializer,
+            {
+                value.serialize_as(Width::$width, serializer)
+            }
+
+            pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+            where
+                T: NbtNumber,
+                D: Deserializer<'de>,
+            {
+                T::deserialize_number(deserializer)
+            }
+        }
+        )*
+    };
+}
+
+width_modules! {
+    byte => Byte,
+    short => Short,
+    int => Int,
+    long => Long,
+    float => Float,
+    double => Double,
+}
+
+struct TypedSeq<'a, T>(&'a [T], Width);
+
+impl<'a, T> Serialize for TypedSeq<'a, T>
+where
+    T: NbtNumber,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        struct Element<'a, T>(&'a T, Width);
+
+        impl<'a, T> Serialize for Element<'a, T>
+        where
+            T: NbtNumber,
+        {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                self.0.serialize_as(self.1, serializer)
+            }
+        }
+
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for ele in self.0 {
+            seq.serialize_element(&Element(ele, self.1))?;
+        }
+        seq.end()
+    }
+}
+
+struct TypedSeqVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for TypedSeqVisitor<T>
+where
+    T: NbtNumber,
+{
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a numeric array or list")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Vec<T>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        struct Element<T>(T);
+
+        impl<'de, T> serde::Deserialize<'de> for Element<T>
+        where
+            T: NbtNumber,
+        {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                T::deserialize_number(deserializer).map(Element)
+            }
+        }
+
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(Element(v)) = seq.next_element::<Element<T>>()? {
+            values.push(v);
+        }
+        Ok(values)
+    }
+}
+
+macro_rules! array_modules {
+    ($($name:ident => $token:ident as $width:ident),* $(,)*) => {
+        $(
+        #[doc = concat!("Stores a sequence of numbers as ", stringify!($name), " tag")]
+        pub mod $name {
+            use super::{NbtNumber, TypedSeq, TypedSeqVisitor, Width, $token};
+            use serde::{Deserializer, Serializer};
+            use std::marker::PhantomData;
+
+            pub fn serialize<T, S>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+            where
+                T: NbtNumber,
+                S: Serializer,
+            {
+                serializer.serialize_newtype_struct($token, &TypedSeq(values, Width::$width))
+            }
+
+            pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+            where
+                T: NbtNumber,
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_seq(TypedSeqVisitor(PhantomData))
+            }
+        }
+        )*
+    };
+}
+
+array_modules! {
+    byte_array => BYTE_ARRAY_TOKEN as Byte,
+    int_array => INT_ARRAY_TOKEN as Int,
+    long_array => LONG_ARRAY_TOKEN as Long,
+}
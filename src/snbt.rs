@@ -1,11 +1,45 @@
 use std::fmt::Display;
-use std::io::Write;
+use std::io::{Read, Write};
+use std::str::FromStr;
 
-use crate::nbt::{NbtTag, NbtWriter};
-use crate::utils::escape;
+use crate::nbt::error::{NbtError, NbtResult};
+use crate::nbt::path::NbtPath;
+use crate::nbt::{Compound, NbtReader, NbtTag, NbtWriter, MAX_DEPTH};
+use crate::utils::quote;
+use indexmap::IndexMap;
+
+/// Formatting options of [StringNbtWriter]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SnbtOptions {
+    indent: Option<usize>,
+    sort_keys: bool,
+}
+
+impl SnbtOptions {
+    /// Writes everything on a single line without spaces, the way commands expect it
+    pub fn compact() -> Self {
+        Self::default()
+    }
+
+    /// Puts compound entries and nested lists on separate lines, indented by the amount of spaces
+    pub fn pretty(indent: usize) -> Self {
+        Self {
+            indent: Some(indent),
+            sort_keys: false,
+        }
+    }
+
+    /// Writes compound keys in the alphabetical order instead of the insertion one
+    pub fn sort_keys(&mut self, sort: bool) -> Self {
+        self.sort_keys = sort;
+        self.clone()
+    }
+}
 
 pub struct StringNbtWriter<W> {
     write: W,
+    options: SnbtOptions,
+    depth: usize,
 }
 
 impl<W> StringNbtWriter<W>
@@ -13,37 +47,115 @@ where
     W: Write,
 {
     pub fn new(write: W) -> Self {
-        Self { write }
+        Self::with_options(write, SnbtOptions::compact())
     }
 
-    fn write_str(&mut self, str: &str) -> anyhow::Result<()> {
-        self.write
-            .write(str.as_bytes())
-            .map_err(anyhow::Error::from)
-            .map(|_| ())
+    pub fn with_options(write: W, options: SnbtOptions) -> Self {
+        Self {
+            write,
+            options,
+            depth: 0,
+        }
     }
 
-    fn write(&mut self, str: String) -> anyhow::Result<()> {
-        self.write
-            .write(str.as_bytes())
-            .map_err(anyhow::Error::from)
-            .map(|_| ())
+    fn write_str(&mut self, str: &str) -> NbtResult<()> {
+        self.write.write_all(str.as_bytes()).map_err(NbtError::from)
+    }
+
+    fn separator(&self) -> &'static str {
+        if self.options.indent.is_some() {
+            ", "
+        } else {
+            ","
+        }
+    }
+
+    /// Starts a new line at the current depth, if the output is pretty
+    fn new_line(&mut self) -> NbtResult<()> {
+        if let Some(indent) = self.options.indent {
+            write!(self.write, "\n{:width$}", "", width = indent * self.depth)?;
+        }
+        Ok(())
     }
 
-    fn write_vec<T>(&mut self, str: &mut String, v: Vec<T>) -> anyhow::Result<()>
+    fn write_vec<T>(&mut self, prefix: &str, v: &[T], suffix: &str) -> NbtResult<()>
     where
-        T: Display + PartialEq,
+        T: Display,
     {
+        self.write_str(prefix)?;
+        if self.options.indent.is_some() && !v.is_empty() {
+            self.write_str(" ")?;
+        }
         let mut iter = v.iter().peekable();
         while let Some(ele) = iter.next() {
-            str.push_str(format!("{}", ele).as_str());
+            write!(self.write, "{}{}", ele, suffix)?;
 
             if iter.peek().is_some() {
-                str.push_str(",");
+                self.write_str(self.separator())?;
+            };
+        }
+        self.write_str("]")
+    }
+
+    fn write_list(&mut self, list: &[NbtTag]) -> NbtResult<()> {
+        if let Some(first) = list.first() {
+            if let Some(idx) = list.iter().position(|ele| ele.id() != first.id()) {
+                return Err(NbtError::HeterogeneousList {
+                    path: NbtPath::new(),
+                    expected: first.id(),
+                    found: list[idx].id(),
+                }
+                .at_index(idx));
+            }
+        }
+        // lists of numbers and strings stay on a single line even in pretty output
+        let multiline = self.options.indent.is_some()
+            && list
+                .iter()
+                .any(|ele| matches!(ele, NbtTag::List(_) | NbtTag::Compound(_)));
+        self.write_str("[")?;
+        self.depth += 1;
+        let mut iter = list.iter().enumerate().peekable();
+        while let Some((idx, ele)) = iter.next() {
+            if multiline {
+                self.new_line()?;
+            }
+            self.write_tag(None, ele).map_err(|err| err.at_index(idx))?;
+            if iter.peek().is_some() {
+                self.write_str(if multiline { "," } else { self.separator() })?;
             };
         }
-        str.push_str("]");
-        self.write(str.to_owned())
+        self.depth -= 1;
+        if multiline {
+            self.new_line()?;
+        }
+        self.write_str("]")
+    }
+
+    fn write_compound(&mut self, comp: &Compound) -> NbtResult<()> {
+        let mut entries = comp
+            .iter()
+            .filter(|(_, v)| **v != NbtTag::Empty)
+            .collect::<Vec<(&String, &NbtTag)>>();
+        if self.options.sort_keys {
+            entries.sort_by_key(|(k, _)| *k);
+        }
+        self.write_str("{")?;
+        self.depth += 1;
+        let empty = entries.is_empty();
+        let mut iter = entries.into_iter().peekable();
+        while let Some((k, v)) = iter.next() {
+            self.new_line()?;
+            self.write_tag(Some(k), v).map_err(|err| err.at_key(k))?;
+            if iter.peek().is_some() {
+                self.write_str(",")?;
+            }
+        }
+        self.depth -= 1;
+        if !empty {
+            self.new_line()?;
+        }
+        self.write_str("}")
     }
 }
 
@@ -51,62 +163,428 @@ impl<W> NbtWriter for StringNbtWriter<W>
 where
     W: Write,
 {
-    fn write_tag(&mut self, name: Option<String>, tag: NbtTag) -> anyhow::Result<()> {
-        if tag == NbtTag::Empty {
+    fn write_tag(&mut self, name: Option<&str>, tag: &NbtTag) -> NbtResult<()> {
+        if *tag == NbtTag::Empty {
             return Ok(());
         };
         if let Some(name) = name {
-            self.write(format!("{}:", name))?;
+            if !name.is_empty() && name.chars().all(is_unquoted_char) {
+                self.write_str(name)?;
+            } else {
+                self.write_str(&quote(name))?;
+            }
+            self.write_str(if self.options.indent.is_some() {
+                ": "
+            } else {
+                ":"
+            })?;
         };
 
         match tag {
-            NbtTag::Byte(v) => self.write(format!("{}b", v))?,
-            NbtTag::Short(v) => self.write(format!("{}s", v))?,
-            NbtTag::Int(v) => self.write(format!("{}", v))?,
-            NbtTag::Long(v) => self.write(format!("{}L", v))?,
-            NbtTag::Float(v) => self.write(format!("{}f", v))?,
-            NbtTag::Double(v) => self.write(format!("{}d", v))?,
-            NbtTag::ByteArray(v) => {
-                let mut str = String::from("[");
-                self.write_vec(&mut str, v)?;
-            }
-            NbtTag::String(v) => self.write(format!("\"{}\"", escape(v)))?,
-            NbtTag::List(v) => {
-                self.write_str("[")?;
-                let mut iter = v.iter().peekable();
-                while let Some(ele) = iter.next() {
-                    self.write_tag(None, ele.to_owned())?;
-                    if iter.peek().is_some() {
-                        self.write_str(",")?;
+            NbtTag::Byte(v) => write!(self.write, "{}b", v)?,
+            NbtTag::Short(v) => write!(self.write, "{}s", v)?,
+            NbtTag::Int(v) => write!(self.write, "{}", v)?,
+            NbtTag::Long(v) => write!(self.write, "{}L", v)?,
+            // debug formatting is the shortest one that reads back into the same value
+            NbtTag::Float(v) if v.is_finite() => write!(self.write, "{:?}f", v)?,
+            NbtTag::Double(v) if v.is_finite() => write!(self.write, "{:?}d", v)?,
+            NbtTag::Float(v) => {
+                return Err(NbtError::unsupported(format!(
+                    "Can not represent float {} in SNBT",
+                    v
+                )))
+            }
+            NbtTag::Double(v) => {
+                return Err(NbtError::unsupported(format!(
+                    "Can not represent double {} in SNBT",
+                    v
+                )))
+            }
+            NbtTag::ByteArray(v) => self.write_vec("[B;", v, "b")?,
+            NbtTag::String(v) => self.write_str(&quote(v))?,
+            NbtTag::List(v) => self.write_list(v)?,
+            NbtTag::Compound(comp) => self.write_compound(comp)?,
+            NbtTag::IntArray(v) => self.write_vec("[I;", v, "")?,
+            NbtTag::LongArray(v) => self.write_vec("[L;", v, "L")?,
+            _ => {}
+        };
+
+        Ok(())
+    }
+}
+
+/// Parses SNBT strings, following the same grammar that the game uses
+pub struct SnbtParser<'a> {
+    src: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> SnbtParser<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    /// Parses a single tag, failing if anything but whitespace follows it
+    pub fn parse(&mut self) -> NbtResult<NbtTag> {
+        let tag = self.parse_value()?;
+        self.skip_whitespace();
+        if self.pos < self.src.len() {
+            return Err(self.error("Trailing data found"));
+        }
+        Ok(tag)
+    }
+
+    /// Parses a single tag without checking for trailing data, returning the
+    /// amount of consumed bytes with it
+    pub(crate) fn parse_partial(&mut self) -> NbtResult<(NbtTag, usize)> {
+        let tag = self.parse_value()?;
+        Ok((tag, self.pos))
+    }
+
+    fn error(&self, msg: &str) -> NbtError {
+        let start = self.src[..self.pos]
+            .char_indices()
+            .rev()
+            .nth(9)
+            .map(|(idx, _)| idx)
+            .unwrap_or(0);
+        let context = &self.src[start..self.pos];
+        let prefix = if start > 0 { "..." } else { "" };
+        NbtError::parse(format!(
+            "{} at position {}: {}{}<--[HERE]",
+            msg, self.pos, prefix, context
+        ))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> NbtResult<()> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.pos += expected.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected '{}'", expected)))
+        }
+    }
+
+    /// Consumes the separator after an element, returning whether another element follows
+    fn separator(&mut self, close: char) -> NbtResult<bool> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.next() {
+            Some(',') => Ok(true),
+            Some(c) if c == close => Ok(false),
+            _ => {
+                self.pos = start;
+                Err(self.error(&format!("Expected ',' or '{}'", close)))
+            }
+        }
+    }
+
+    fn parse_nested(&mut self, parse: fn(&mut Self) -> NbtResult<NbtTag>) -> NbtResult<NbtTag> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(&format!(
+                "SNBT input is nested deeper than {} levels",
+                MAX_DEPTH
+            )));
+        }
+        let tag = parse(self);
+        self.depth -= 1;
+        tag
+    }
+
+    fn parse_value(&mut self) -> NbtResult<NbtTag> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.parse_nested(Self::parse_compound),
+            Some('[') => self.parse_nested(Self::parse_list_or_array),
+            Some('"') | Some('\'') => Ok(NbtTag::String(self.parse_quoted()?)),
+            Some(_) => {
+                let start = self.pos;
+                let token = self.parse_unquoted();
+                if token.is_empty() {
+                    self.pos = start;
+                    return Err(self.error("Expected value"));
+                }
+                Ok(typed_value(token))
+            }
+            None => Err(self.error("Expected value")),
+        }
+    }
+
+    fn parse_key(&mut self) -> NbtResult<String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') | Some('\'') => self.parse_quoted(),
+            _ => {
+                let key = self.parse_unquoted();
+                if key.is_empty() {
+                    return Err(self.error("Expected key"));
+                }
+                Ok(key.to_string())
+            }
+        }
+    }
+
+    fn parse_unquoted(&mut self) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !is_unquoted_char(c) {
+                break;
+            }
+            self.pos += 1;
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn parse_quoted(&mut self) -> NbtResult<String> {
+        let quote = self.next().unwrap();
+        let mut buf = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error("Unclosed quoted string")),
+                Some(c) if c == quote => return Ok(buf),
+                Some('\\') => {
+                    let escaped = match self.next() {
+                        Some('\\') => '\\',
+                        Some('"') => '"',
+                        Some('\'') => '\'',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('s') => ' ',
+                        Some('u') => {
+                            let start = self.pos;
+                            let hex = self.src.get(start..start + 4).unwrap_or("");
+                            let c = u32::from_str_radix(hex, 16)
+                                .ok()
+                                .filter(|_| hex.len() == 4)
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("Invalid unicode escape"))?;
+                            self.pos += 4;
+                            c
+                        }
+                        _ => return Err(self.error("Invalid escape sequence")),
                     };
+                    buf.push(escaped);
                 }
-                self.write_str("]")?;
-            }
-            NbtTag::Compound(comp) => {
-                self.write_str("{")?;
-                let mut iter = comp.iter().peekable();
-                while let Some((k, v)) = iter.next() {
-                    if v.to_owned() == NbtTag::Empty {
-                        continue;
-                    }
-                    self.write_tag(Some(k.to_owned()), v.to_owned())?;
-                    if iter.peek().is_some() {
-                        self.write_str(",")?;
-                    }
+                Some(c) => buf.push(c),
+            }
+        }
+    }
+
+    fn parse_compound(&mut self) -> NbtResult<NbtTag> {
+        self.expect('{')?;
+        let mut tags = IndexMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(NbtTag::Compound(Compound::new(tags)));
+        }
+        loop {
+            let key = self.parse_key()?;
+            self.expect(':')?;
+            let value = self.parse_value().map_err(|err| err.at_key(&key))?;
+            tags.insert(key, value);
+            if !self.separator('}')? {
+                return Ok(NbtTag::Compound(Compound::new(tags)));
+            }
+        }
+    }
+
+    fn parse_list_or_array(&mut self) -> NbtResult<NbtTag> {
+        self.expect('[')?;
+        let rest = &self.src[self.pos..];
+        let mut chars = rest.chars();
+        if let (Some(ty @ ('B' | 'I' | 'L')), Some(';')) = (chars.next(), chars.next()) {
+            self.pos += 2;
+            return self.parse_array(ty);
+        }
+
+        let mut list = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(NbtTag::List(list));
+        }
+        loop {
+            let start = self.pos;
+            let value = self.parse_value().map_err(|err| err.at_index(list.len()))?;
+            if let Some(first) = list.first() {
+                if NbtTag::id(first) != value.id() {
+                    self.pos = start;
+                    self.skip_whitespace();
+                    return Err(self.error("Can't insert element of a different type into list"));
                 }
-                self.write_str("}")?;
             }
-            NbtTag::IntArray(v) => {
-                let mut str = String::from("[I;");
-                self.write_vec(&mut str, v)?;
+            list.push(value);
+            if !self.separator(']')? {
+                return Ok(NbtTag::List(list));
             }
-            NbtTag::LongArray(v) => {
-                let mut str = String::from("[L;");
-                self.write_vec(&mut str, v)?;
+        }
+    }
+
+    fn parse_array(&mut self, ty: char) -> NbtResult<NbtTag> {
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+        } else {
+            loop {
+                self.skip_whitespace();
+                let start = self.pos;
+                let value = match self.parse_value()? {
+                    NbtTag::Byte(v) => v as i64,
+                    NbtTag::Short(v) => v as i64,
+                    NbtTag::Int(v) => v as i64,
+                    NbtTag::Long(v) => v,
+                    _ => {
+                        self.pos = start;
+                        return Err(self.error("Expected integer array element"));
+                    }
+                };
+                values.push((start, value));
+                if !self.separator(']')? {
+                    break;
+                }
             }
-            _ => {}
-        };
+        }
 
-        Ok(())
+        macro_rules! narrow {
+            ($variant:ident, $typ:ty) => {{
+                let mut arr = Vec::with_capacity(values.len());
+                for (start, v) in values {
+                    match <$typ>::try_from(v) {
+                        Ok(v) => arr.push(v),
+                        Err(_) => {
+                            self.pos = start;
+                            return Err(self.error("Array element is out of range"));
+                        }
+                    }
+                }
+                NbtTag::$variant(arr)
+            }};
+        }
+
+        Ok(match ty {
+            'B' => narrow!(ByteArray, i8),
+            'I' => narrow!(IntArray, i32),
+            _ => NbtTag::LongArray(values.into_iter().map(|(_, v)| v).collect()),
+        })
+    }
+}
+
+fn is_unquoted_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+fn is_integer(s: &str) -> bool {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && (digits == "0" || !digits.starts_with('0'))
+}
+
+fn is_decimal(s: &str) -> bool {
+    let s = s.strip_prefix(['-', '+']).unwrap_or(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(idx) => (&s[..idx], Some(&s[idx + 1..])),
+        None => (s, None),
+    };
+    let (int, frac) = match mantissa.find('.') {
+        Some(idx) => (&mantissa[..idx], &mantissa[idx + 1..]),
+        None => (mantissa, ""),
+    };
+    let mantissa_valid = (!int.is_empty() || !frac.is_empty())
+        && int.chars().all(|c| c.is_ascii_digit())
+        && frac.chars().all(|c| c.is_ascii_digit());
+    let exponent_valid = exponent.is_none_or(|exp| {
+        let exp = exp.strip_prefix(['-', '+']).unwrap_or(exp);
+        !exp.is_empty() && exp.chars().all(|c| c.is_ascii_digit())
+    });
+    mantissa_valid && exponent_valid
+}
+
+/// Converts an unquoted token into a typed tag. Like in the game,
+/// tokens that do not form a valid number are kept as strings.
+fn typed_value(token: &str) -> NbtTag {
+    if token.eq_ignore_ascii_case("true") {
+        return NbtTag::Byte(1);
+    } else if token.eq_ignore_ascii_case("false") {
+        return NbtTag::Byte(0);
+    }
+
+    let (body, suffix) = token.split_at(token.len() - 1);
+    let parsed = match suffix {
+        "b" | "B" if is_integer(body) => body.parse().ok().map(NbtTag::Byte),
+        "s" | "S" if is_integer(body) => body.parse().ok().map(NbtTag::Short),
+        "l" | "L" if is_integer(body) => body.parse().ok().map(NbtTag::Long),
+        "f" | "F" if is_decimal(body) => body.parse().ok().map(NbtTag::Float),
+        "d" | "D" if is_decimal(body) => body.parse().ok().map(NbtTag::Double),
+        _ if is_integer(token) => token.parse().ok().map(NbtTag::Int),
+        // the game needs a dot in doubles without suffix, so `1e5` is a string
+        _ if is_decimal(token) && token.contains('.') => token.parse().ok().map(NbtTag::Double),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| NbtTag::String(token.to_string()))
+}
+
+impl FromStr for NbtTag {
+    type Err = NbtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SnbtParser::new(s).parse()
+    }
+}
+
+/// Reads SNBT from the input. Since SNBT has no root names,
+/// read tags are always named with an empty string.
+pub struct StringNbtReader<R> {
+    read: R,
+}
+
+impl<R> StringNbtReader<R>
+where
+    R: Read,
+{
+    pub fn new(read: R) -> Self {
+        Self { read }
+    }
+}
+
+impl<R> NbtReader for StringNbtReader<R>
+where
+    R: Read,
+{
+    fn read_tag(&mut self) -> NbtResult<(String, NbtTag)> {
+        let mut buf = String::new();
+        self.read.read_to_string(&mut buf)?;
+        Ok((String::new(), NbtTag::from_str(&buf)?))
     }
 }
@@ -1,4 +1,5 @@
-use crate::nbt::NbtTag;
+use crate::nbt::error::NbtError;
+use crate::nbt::{NbtTag, ToTag};
 use byteorder::{BigEndian, ReadBytesExt};
 use colored::{Color, Colorize};
 use lazy_static::lazy_static;
@@ -92,21 +93,35 @@ impl Keybind {
 }
 
 pub fn escape(src: String) -> String {
+    escape_with(&src, '"')
+}
+
+/// Escapes backslashes and the quote. Other characters are written as they are, since
+/// SNBT before 1.21.5 has no other escape sequences and allows them inside quotes
+fn escape_with(src: &str, quote: char) -> String {
     let mut escaped = String::with_capacity(src.len());
     for c in src.chars() {
         match c {
-            '\r' => escaped += "\\r",
-            '\n' => escaped += "\\n",
-            '\t' => escaped += "\\t",
-            '"' => escaped += "\\\"",
-            '\\' => escaped += "\\",
+            '\\' => escaped += "\\\\",
+            c if c == quote => {
+                escaped.push('\\');
+                escaped.push(c)
+            }
             c => escaped.push(c),
         };
     }
     escaped
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Quotes and escapes the string, choosing the quote that needs less escaping
+pub fn quote(src: &str) -> String {
+    let double = src.matches('"').count();
+    let single = src.matches('\'').count();
+    let quote = if single < double { '\'' } else { '"' };
+    format!("{}{}{}", quote, escape_with(src, quote), quote)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Vec3D(pub f64, pub f64, pub f64);
 
 impl Into<NbtTag> for Vec3D {
@@ -160,6 +175,19 @@ where
     }
 }
 
+impl<F, S> ToTag for Either<F, S>
+where
+    F: ToTag,
+    S: ToTag,
+{
+    fn to_tag(&self) -> NbtTag {
+        match self {
+            Either::First(first) => first.to_tag(),
+            Either::Second(second) => second.to_tag(),
+        }
+    }
+}
+
 #[cfg(not(feature = "legacy_uuids"))]
 fn uuid_to_int_array(id: Uuid) -> NbtTag {
     let bytes = id.as_bytes().to_vec();
@@ -179,6 +207,31 @@ impl Into<NbtTag> for Uuid {
     }
 }
 
+impl TryFrom<NbtTag> for Uuid {
+    type Error = NbtError;
+
+    /// Reads UUID from either an int array with 4 elements or a hyphenated string,
+    /// regardless of the `legacy_uuids` feature
+    fn try_from(tag: NbtTag) -> Result<Self, Self::Error> {
+        match tag {
+            NbtTag::IntArray(ints) if ints.len() == 4 => {
+                let mut bytes = [0u8; 16];
+                for (chunk, int) in bytes.chunks_mut(4).zip(ints) {
+                    chunk.copy_from_slice(&int.to_be_bytes());
+                }
+                Ok(Uuid::from_bytes(bytes))
+            }
+            NbtTag::String(str) => {
+                Uuid::parse_str(&str).map_err(|err| NbtError::parse(err.to_string()))
+            }
+            other => Err(NbtError::parse(format!(
+                "Expected int array with 4 elements or string, got tag with id {}",
+                other.id()
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Positive<I> {
     value: I,
@@ -193,7 +246,16 @@ where
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+impl<I> ToTag for Positive<I>
+where
+    I: ToTag,
+{
+    fn to_tag(&self) -> NbtTag {
+        self.value.to_tag()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Vec3I(pub i32, pub i32, pub i32);
 
 impl Into<NbtTag> for Vec3I {
@@ -229,6 +291,8 @@ impl Into<NbtTag> for GeneralColor {
     }
 }
 
+crate::__to_tag_by_clone!(Vec3D, Vec3F, Vec2F, Vec3I, Uuid, GeneralColor);
+
 macro_rules! __positive_impl {
     ($($int:ident),* $(,)*) => {
         $(
@@ -290,28 +354,38 @@ macro_rules! __meta_struct {
             }
 
             impl MetaContainer for $name {
-                fn write_meta<W>(&mut self, writer: &mut W) -> anyhow::Result<()> where W: NbtWriter {
-                    let comp = self.tag();
-                    writer.write_tag(None, comp)?;
-                    Ok(())
+                fn write_meta<W>(&mut self, writer: &mut W) -> $crate::nbt::error::NbtResult<()> where W: NbtWriter {
+                    writer.write_tag(None, &self.tag())
                 }
 
+                // built from borrowed fields, so the meta itself is never cloned
                 fn tag(&self) -> $crate::nbt::NbtTag {
+                    let mut comp = $crate::nbt::Compound::default();
                     $(
-                    let $field_name: NbtTag = Clone::clone(&self.$field_name).into();
+                    if let Some(value) = &self.$field_name {
+                        comp.insert(stringify!($stored_name), $crate::nbt::ToTag::to_tag(value));
+                    }
                     )*
-                    let comp = $crate::nbt::NbtTag::Compound($crate::nbt! {
-                        $(
-                        $stored_name: $field_name,
-                        )*
-                    });
-                    comp
+                    $crate::nbt::NbtTag::Compound(comp)
+                }
+            }
+
+            impl $crate::nbt::ToTag for $name {
+                fn to_tag(&self) -> $crate::nbt::NbtTag {
+                    MetaContainer::tag(self)
                 }
             }
 
             impl Into<$crate::nbt::NbtTag> for $name {
+                // fields are moved into the tag, so nested metas are not cloned again
                 fn into(self) -> $crate::nbt::NbtTag {
-                    self.tag()
+                    let mut comp = $crate::nbt::Compound::default();
+                    $(
+                    if let Some(value) = self.$field_name {
+                        comp.insert(stringify!($stored_name), value);
+                    }
+                    )*
+                    $crate::nbt::NbtTag::Compound(comp)
                 }
             }
         )*